@@ -1,7 +1,16 @@
 use clap::{Parser, Subcommand};
-use oracle_mapping::{
-    instruction::{AddMappingIxData, InitializeRegistryIxData, IntoBytes},
-    state::{DataLen, MintMapping, ScopeMappingRegistry},
+use oracle_mapping::state::{
+    AuditLog, DataLen, MappingProof, MintAlias, MintMapping, PriceCache, PriceSelection,
+    ProviderConfig, QuoteCurrency, RegistrySnapshot, ScopeMappingRegistry, SourceEntry, SourceType,
+    TokenMetadata, ValidityWindow,
+};
+use oracle_mapping_client::{
+    self as client, AddMappingIxData, AddPageIxData, CloseMappingIxData, CompactIxData,
+    InitializeAuditLogIxData, InitializeDraftIxData, InitializePriceCacheIxData,
+    InitializeRegistryIxData, PromoteIxData, PruneExpiredIxData, RefreshPricesIxData,
+    ReserveCapacityIxData, SetDelegateIxData, SetDisabledProvidersIxData, SetProviderConfigIxData,
+    SetQuoteConversionIxData, SetRateLimitIxData, SetScopeFeedIxData, SnapshotIxData,
+    UpdateSourcesIxData,
 };
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
@@ -10,8 +19,6 @@ use solana_sdk::{
     pubkey::Pubkey,
     signature::Keypair,
     signer::{EncodableKey, Signer},
-    system_program,
-    sysvar::rent,
     transaction::VersionedTransaction,
 };
 use std::str::FromStr;
@@ -20,6 +27,13 @@ use std::str::FromStr;
 #[command(name = "scope-mapping-cli")]
 #[command(about = "CLI for interacting with the Scope Mapping Solana program", long_about = None)]
 struct Cli {
+    /// Apply registry edits and lookups to the draft instead of the live registry
+    #[arg(long, global = true)]
+    draft: bool,
+    /// Apply reserve and compact to continuation page N of the live registry instead; entries on
+    /// pages are edited, pruned and looked up through the registry, whose audit log records all
+    #[arg(long, global = true, conflicts_with = "draft")]
+    page: Option<u32>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -27,7 +41,11 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize the registry
-    Init {},
+    Init {
+        /// Live entries the registry and each of its pages can hold, 0 for the default of 512
+        #[arg(long, default_value_t = 0)]
+        max_mappings: u32,
+    },
     /// Add a mapping (single or batch via JSON)
     AddMapping {
         /// Mint address (base58, required)
@@ -51,22 +69,183 @@ enum Commands {
         /// Asset id (optional)
         #[arg(long)]
         asset_id: Option<u16>,
+        /// Token symbol, up to 10 bytes (optional)
+        #[arg(long)]
+        symbol: Option<String>,
+        /// Token name, up to 32 bytes (optional)
+        #[arg(long)]
+        name: Option<String>,
+        /// Category and risk-tier bitfield (optional)
+        #[arg(long, default_value_t = 0)]
+        tags: u16,
         /// JSON file for batch creation (optional)
         #[arg(long)]
         json: Option<String>,
+        /// Keypair file of the account paying realloc rent and fees (optional)
+        #[arg(long)]
+        funder: Option<String>,
+        /// Pass the mint account so the program checks its owner and decimals
+        #[arg(long)]
+        verify_mint: bool,
+        /// Pass the Pyth and Switchboard accounts even when the registry is not in strict mode
+        #[arg(long)]
+        verify_oracles: bool,
     },
     /// Close a mapping
     CloseMapping {
         /// Mint address (base58)
         mint: String,
+        /// Account receiving the freed rent right away (base58, optional)
+        #[arg(long)]
+        refund_to: Option<String>,
+    },
+    /// Grow the registry ahead of time so later adds skip the realloc
+    Reserve {
+        /// Number of bytes to add to the mapping region
+        #[arg(long)]
+        bytes: u16,
+        /// Keypair file of the account paying realloc rent and fees (optional)
+        #[arg(long)]
+        funder: Option<String>,
+    },
+    /// Drop closed mappings and shrink the registry
+    Compact {
+        /// Free bytes to keep allocated after the live mappings
+        #[arg(long, default_value_t = 0)]
+        retain: u16,
+        /// Account receiving the freed rent (base58, optional)
+        #[arg(long)]
+        refund_to: Option<String>,
+    },
+    /// Remove mappings whose validity window has ended and refund their rent
+    PruneExpired {
+        /// Most mappings to prune in one transaction, up to 8
+        #[arg(long, default_value_t = 8)]
+        max_entries: u8,
+    },
+    /// Set the expected owner program of each oracle source
+    SetProviderConfig {
+        /// Scope program (base58, omit to disable the check)
+        #[arg(long)]
+        scope: Option<String>,
+        /// Pyth receiver program (base58, omit to disable the check)
+        #[arg(long)]
+        pyth: Option<String>,
+        /// Switchboard program (base58, omit to disable the check)
+        #[arg(long)]
+        switchboard: Option<String>,
+        /// Leave oracle accounts optional in AddMapping instead of requiring every one
+        #[arg(long)]
+        relaxed: bool,
+    },
+    /// Stop trusting a provider across all mappings, or trust it again
+    DisableProvider {
+        /// "scope", "pyth" or "switchboard"
+        provider: String,
+        /// Re-enable the provider instead
+        #[arg(long)]
+        enable: bool,
+    },
+    /// Set the mint whose USD price converts SOL-quoted mappings
+    SetQuoteConversion {
+        /// Mint address (base58), omit to clear the conversion
+        mint: Option<String>,
+    },
+    /// Name the Scope OraclePrices account at one feed index of the registry
+    SetScopeFeed {
+        /// Feed index, below 4
+        index: u8,
+        /// OraclePrices account (base58), omit to clear the feed
+        feed: Option<String>,
+    },
+    /// Let a key update the oracle sources of one mint
+    SetDelegate {
+        /// Mint address (base58)
+        mint: String,
+        /// Delegate address (base58), omit to revoke the delegation
+        delegate: Option<String>,
+    },
+    /// Replace the oracle sources of a mint, as the authority or its delegate
+    UpdateSources {
+        /// Mint address (base58)
+        mint: String,
+        /// Scope conversion chain, e.g. 1,2,0 (optional)
+        #[arg(long, value_delimiter = ',', num_args = 3)]
+        scope: Option<Vec<u16>>,
+        /// Registry Scope feed the chain reads
+        #[arg(long, default_value_t = 0)]
+        scope_feed: u8,
+        /// Pyth price account (base58, optional)
+        #[arg(long)]
+        pyth: Option<String>,
+        /// Switchboard feed account (base58, optional)
+        #[arg(long)]
+        switchboard: Option<String>,
+        /// JSON file with the tagged source list (optional)
+        #[arg(long)]
+        sources: Option<String>,
+        /// Keypair file of the delegate, the fee payer signs when omitted
+        #[arg(long)]
+        signer: Option<String>,
+        /// Pass the oracle accounts even when the registry is not in strict mode
+        #[arg(long)]
+        verify_oracles: bool,
+    },
+    /// Limit the mapping changes accepted per epoch or per number of slots
+    SetRateLimit {
+        /// Most changes per window, 0 to lift the limit
+        #[arg(long)]
+        max_mutations: u16,
+        /// Window length in slots, one window per epoch when 0
+        #[arg(long, default_value_t = 0)]
+        window_slots: u64,
+        /// Guardian allowed to override the limit (base58, optional)
+        #[arg(long)]
+        guardian: Option<String>,
+        /// Keypair file of the current guardian, required once one is set
+        #[arg(long)]
+        guardian_keypair: Option<String>,
     },
     /// Show a mapping by mint
     Show {
         /// Mint address (base58)
         mint: String,
     },
+    /// Print the Merkle inclusion proof of a mapping
+    Prove {
+        /// Mint address (base58)
+        mint: String,
+    },
     /// Show all mappings
     ShowAll {},
+    /// Show the most recent registry changes
+    ShowAuditLog {},
+    /// Cache the aggregated prices of the given mints
+    RefreshPrices {
+        /// Mint addresses (base58), up to 16
+        mints: Vec<String>,
+    },
+    /// Show the cached prices
+    ShowPrices {},
+    /// Copy the registry into a new read-only snapshot account
+    Snapshot {
+        /// Snapshot number, each can only be taken once
+        number: u64,
+    },
+    /// Show a snapshot and the mappings it holds
+    ShowSnapshot {
+        /// Snapshot number
+        number: u64,
+    },
+    /// Copy the live registry into a draft that takes edits with --draft
+    InitDraft {},
+    /// Replace the live registry with the draft and close the draft
+    Promote {
+        /// Live registry version the draft was based on, as printed by init-draft
+        expected_version: u32,
+    },
+    /// Chain a new continuation page onto the live registry, whose bookkeeping takes --page
+    AddPage {},
 }
 
 use serde::Deserialize;
@@ -79,10 +258,59 @@ struct MintMappingInput {
     decimals: u8,
     #[serde(default)]
     scope_details: Option<[u16; 3]>,
+    /// Registry Scope feed the chains read, 0 when omitted
+    #[serde(default)]
+    scope_feed: u8,
     #[serde(default)]
     pyth_account: Option<String>,
     #[serde(default)]
     switch_board: Option<String>,
+    #[serde(default)]
+    tags: u16,
+    #[serde(default)]
+    sources: Vec<SourceInput>,
+    /// Mint whose mapping this one aliases, instead of sources of its own
+    #[serde(default)]
+    alias_of: Option<String>,
+    /// Alias multiplier with 9 decimals, 1x when omitted
+    #[serde(default)]
+    alias_multiplier: Option<u64>,
+    /// Pyth price to value the mint at: "spot" (default), "ema" or "conservative"
+    #[serde(default)]
+    price_selection: Option<String>,
+    /// Currency the sources quote the mint in: "usd" (default) or "sol"
+    #[serde(default)]
+    quote_currency: Option<String>,
+    /// First slot the mapping is live in (optional)
+    #[serde(default)]
+    valid_from: Option<u64>,
+    /// Slot the mapping expires at (optional)
+    #[serde(default)]
+    valid_until: Option<u64>,
+}
+
+/// Extra source for the tagged source list, e.g. `{ "type": "stork", "account": "..." }` or
+/// `{ "type": "stake_pool", "account": "<pool>", "quote_mint": "<wSOL>" }` or
+/// `{ "type": "fixed", "value": 100, "expo": -2, "band_bps": 200, "reference": 0 }`
+#[derive(Debug, Deserialize)]
+struct SourceInput {
+    #[serde(rename = "type")]
+    source_type: String,
+    #[serde(default)]
+    account: Option<String>,
+    #[serde(default)]
+    scope_details: Option<[u16; 3]>,
+    #[serde(default)]
+    quote_mint: Option<String>,
+    #[serde(default)]
+    value: Option<i64>,
+    #[serde(default)]
+    expo: i32,
+    /// Guard band of a fixed price, checked against the `reference`-th source
+    #[serde(default)]
+    band_bps: u16,
+    #[serde(default)]
+    reference: Option<u8>,
 }
 
 fn setup_rpc_and_program() -> (RpcClient, Keypair, Pubkey, Pubkey, u8) {
@@ -93,79 +321,322 @@ fn setup_rpc_and_program() -> (RpcClient, Keypair, Pubkey, Pubkey, u8) {
     let program_id = Pubkey::from(oracle_mapping::ID);
     println!("program_id: {:?}", program_id);
 
-    let (state_pda, bump) = Pubkey::find_program_address(
-        &[b"ScopeMappingRegistry", fee_payer.pubkey().as_ref()],
-        &program_id,
-    );
+    let (state_pda, bump) = client::find_registry_address(&fee_payer.pubkey());
     (rpc, fee_payer, program_id, state_pda, bump)
 }
 
+fn get_audit_log_pda(_program_id: Pubkey, state_pda: Pubkey) -> (Pubkey, u8) {
+    client::find_audit_log_address(&state_pda)
+}
+
 fn create_close_mapping_ix(
     program_id: Pubkey,
     fee_payer: &Keypair,
     state_pda: Pubkey,
     mint: [u8; 32],
     bump: u8,
+    refund_to: Option<Pubkey>,
+    pages: &[AccountMeta],
 ) -> Instruction {
-    use oracle_mapping::instruction::CloseMappingIxData;
-    let close_mapping_ix_data = CloseMappingIxData { mint, bump };
-    let mut ix_data_with_discriminator = vec![2];
-    ix_data_with_discriminator.extend_from_slice(close_mapping_ix_data.into_bytes().unwrap());
-    let ix_data: [u8; 1 + CloseMappingIxData::LEN] = ix_data_with_discriminator.try_into().unwrap();
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data.into(),
+    client::CloseMapping {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        refund_acc: refund_to,
     }
+    .instruction_with_remaining_accounts(&CloseMappingIxData { mint, bump }, pages)
 }
 
-fn create_initialize_registry_ix(
+/// Grows `page_pda` when given, else the registry itself; either is recorded in the audit log
+/// of the registry.
+fn create_reserve_capacity_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    page_pda: Option<Pubkey>,
+    funder: Option<Pubkey>,
+    additional_bytes: u16,
+) -> Instruction {
+    client::ReserveCapacity {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: page_pda.unwrap_or(state_pda),
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        funder_acc: funder,
+    }
+    .instruction(&ReserveCapacityIxData { additional_bytes })
+}
+
+/// Compacts `page_pda` when given, else the registry itself; either is recorded in the audit log
+/// of the registry.
+fn create_compact_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    page_pda: Option<Pubkey>,
+    refund_to: Option<Pubkey>,
+    retain_bytes: u16,
+) -> Instruction {
+    client::Compact {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: page_pda.unwrap_or(state_pda),
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        refund_acc: refund_to,
+    }
+    .instruction(&CompactIxData { retain_bytes })
+}
+
+fn create_prune_expired_ix(
+    program_id: Pubkey,
+    authority: Pubkey,
+    state_pda: Pubkey,
+    max_entries: u8,
+    pages: &[AccountMeta],
+) -> Instruction {
+    client::PruneExpired {
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        authority_acc: authority,
+    }
+    .instruction_with_remaining_accounts(&PruneExpiredIxData { max_entries }, pages)
+}
+
+fn create_set_provider_config_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    providers: ProviderConfig,
+) -> Instruction {
+    client::SetProviderConfig {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&SetProviderConfigIxData { providers })
+}
+
+fn create_set_disabled_providers_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    disabled_providers: u8,
+) -> Instruction {
+    client::SetDisabledProviders {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&SetDisabledProvidersIxData { disabled_providers })
+}
+
+fn create_set_quote_conversion_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    mint: [u8; 32],
+    pages: &[AccountMeta],
+) -> Instruction {
+    client::SetQuoteConversion {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction_with_remaining_accounts(
+        &SetQuoteConversionIxData {
+            quote: QuoteCurrency::Sol,
+            mint,
+        },
+        pages,
+    )
+}
+
+fn create_set_scope_feed_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    index: u8,
+    feed: [u8; 32],
+) -> Instruction {
+    let feed_acc = Some(Pubkey::from(feed)).filter(|feed| feed.to_bytes() != [0u8; 32]);
+    client::SetScopeFeed {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        feed_acc,
+    }
+    .instruction(&SetScopeFeedIxData { index, feed })
+}
+
+fn create_set_delegate_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    mint: [u8; 32],
+    delegate: [u8; 32],
+    pages: &[AccountMeta],
+) -> Instruction {
+    client::SetDelegate {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction_with_remaining_accounts(&SetDelegateIxData { mint, delegate }, pages)
+}
+
+/// `remaining` holds the continuation pages of the registry, then one account per account-based
+/// source of the mapping, or none.
+fn create_update_sources_ix(
+    program_id: Pubkey,
+    signer: Pubkey,
+    state_pda: Pubkey,
+    remaining: &[AccountMeta],
+    mapping: MintMapping,
+) -> Instruction {
+    client::UpdateSources {
+        signer_acc: signer,
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction_with_remaining_accounts(&UpdateSourcesIxData { mapping }, remaining)
+}
+
+fn create_set_rate_limit_ix(
     program_id: Pubkey,
     fee_payer: &Keypair,
     state_pda: Pubkey,
+    guardian: Option<Pubkey>,
+    ix_data: SetRateLimitIxData,
+) -> Instruction {
+    client::SetRateLimit {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        guardian_acc: guardian,
+    }
+    .instruction(&ix_data)
+}
+
+fn create_initialize_registry_ix(
+    _program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
     bump: u8,
+    max_mappings: u32,
 ) -> Instruction {
-    let binding = InitializeRegistryIxData { bump };
-    let ix_data = binding.to_bytes();
-    let mut ix_data_with_discriminator = vec![0];
-    ix_data_with_discriminator.extend_from_slice(&ix_data);
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data_with_discriminator.try_into().unwrap(),
+    client::InitializeState {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+    }
+    .instruction(&InitializeRegistryIxData { bump, max_mappings })
+}
+
+fn create_initialize_audit_log_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+) -> Instruction {
+    let (audit_log_pda, bump) = get_audit_log_pda(program_id, state_pda);
+    client::InitializeAuditLog {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: audit_log_pda,
     }
+    .instruction(&InitializeAuditLogIxData { bump })
 }
 
+/// `remaining` holds the continuation pages of the registry, then one account per account-based
+/// source of the mapping, or none.
 fn create_add_mapping_ix(
     program_id: Pubkey,
     fee_payer: &Keypair,
     state_pda: Pubkey,
+    funder: Option<Pubkey>,
+    mint: Option<Pubkey>,
+    remaining: &[AccountMeta],
     mapping: MintMapping,
 ) -> Instruction {
-    let add_mapping_ix_data = AddMappingIxData { mapping };
-    let mut ix_data_with_discriminator = vec![1];
-    ix_data_with_discriminator.extend_from_slice(&add_mapping_ix_data.into_bytes().unwrap());
-    let ix_data: [u8; 1 + AddMappingIxData::LEN] = ix_data_with_discriminator.try_into().unwrap();
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data.try_into().unwrap(),
+    client::AddMapping {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        funder_acc: funder,
+        mint_acc: mint,
+    }
+    .instruction_with_remaining_accounts(&AddMappingIxData { mapping }, remaining)
+}
+
+fn create_initialize_price_cache_ix(fee_payer: &Keypair, state_pda: Pubkey) -> Instruction {
+    let (price_cache_pda, bump) = client::find_price_cache_address(&state_pda);
+    client::InitializePriceCache {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        price_cache_acc: price_cache_pda,
+    }
+    .instruction(&InitializePriceCacheIxData { bump })
+}
+
+/// `oracles` holds one account per source of each mint, in `MintMapping::oracle_sources` order.
+fn create_refresh_prices_ix(
+    state_pda: Pubkey,
+    mints: &[[u8; 32]],
+    oracles: &[AccountMeta],
+) -> Instruction {
+    client::RefreshPrices {
+        state_acc: state_pda,
+        price_cache_acc: client::find_price_cache_address(&state_pda).0,
+    }
+    .instruction_with_remaining_accounts(&RefreshPricesIxData::new(mints).unwrap(), oracles)
+}
+
+fn create_add_page_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    index: u32,
+    pages: &[AccountMeta],
+) -> Instruction {
+    let (page_pda, bump) = client::find_page_address(&state_pda, index);
+    client::AddPage {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        page_acc: page_pda,
+    }
+    .instruction_with_remaining_accounts(&AddPageIxData { bump }, pages)
+}
+
+fn create_snapshot_ix(fee_payer: &Keypair, state_pda: Pubkey, number: u64) -> Instruction {
+    let (snapshot_pda, bump) = client::find_snapshot_address(&state_pda, number);
+    client::Snapshot {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        snapshot_acc: snapshot_pda,
     }
+    .instruction(&SnapshotIxData { number, bump })
+}
+
+fn create_initialize_draft_ix(fee_payer: &Keypair, state_pda: Pubkey) -> Instruction {
+    let (draft_pda, bump) = client::find_draft_address(&state_pda);
+    client::InitializeDraft {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        draft_acc: draft_pda,
+    }
+    .instruction(&InitializeDraftIxData { bump })
+}
+
+fn create_promote_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    expected_version: u32,
+) -> Instruction {
+    client::Promote {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        draft_acc: client::find_draft_address(&state_pda).0,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&PromoteIxData { expected_version })
 }
 
 fn check_registry_is_initialized(rpc: &RpcClient, state_pda: &Pubkey) -> bool {
@@ -180,52 +651,152 @@ fn check_registry_is_initialized(rpc: &RpcClient, state_pda: &Pubkey) -> bool {
         == 1
 }
 
+fn check_audit_log_is_initialized(rpc: &RpcClient, audit_log_pda: &Pubkey) -> bool {
+    match rpc.get_account(audit_log_pda) {
+        Ok(account) => AuditLog::from_account_data(&account.data).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn check_price_cache_is_initialized(rpc: &RpcClient, price_cache_pda: &Pubkey) -> bool {
+    match rpc.get_account(price_cache_pda) {
+        Ok(account) => PriceCache::from_account_data(&account.data).is_ok(),
+        Err(_) => false,
+    }
+}
+
 fn get_registry(rpc: &RpcClient, state_pda: &Pubkey) -> ScopeMappingRegistry {
     let data = rpc.get_account(state_pda).unwrap().data;
     ScopeMappingRegistry::from_slice(&data[..ScopeMappingRegistry::LEN]).unwrap()
 }
 
-fn get_mapping_by_index(rpc: &RpcClient, state_pda: &Pubkey, index: usize) -> MintMapping {
-    let data = rpc.get_account(state_pda).unwrap().data;
-    // Calculate the starting offset for this mapping
-    let mut current_offset = ScopeMappingRegistry::LEN;
+/// Continuation pages of the registry in page order, writable for the instructions that edit
+/// entries held on them.
+fn page_account_metas(rpc: &RpcClient, state_pda: &Pubkey, is_writable: bool) -> Vec<AccountMeta> {
+    let reg = get_registry(rpc, state_pda);
+    (1..=reg.page_count)
+        .map(|index| {
+            let (page_pda, _) = client::find_page_address(state_pda, index);
+            if is_writable {
+                AccountMeta::new(page_pda, false)
+            } else {
+                AccountMeta::new_readonly(page_pda, false)
+            }
+        })
+        .collect()
+}
 
-    // Skip previous mappings to find the start of this mapping
-    for i in 0..index {
-        if current_offset + 35 > data.len() {
-            panic!("Mapping index {} not found", index);
-        }
-        // Read the offset byte at position 32 of each mapping to get its actual size
-        let mapping_offset = data[current_offset + 32] as usize;
-        current_offset += mapping_offset;
+/// Account data of the registry followed by that of its continuation pages, in page order.
+fn get_registry_pages(rpc: &RpcClient, state_pda: &Pubkey) -> Vec<Vec<u8>> {
+    let reg = get_registry(rpc, state_pda);
+    let mut pages = vec![rpc.get_account(state_pda).unwrap().data];
+    for index in 1..=reg.page_count {
+        let (page_pda, _) = client::find_page_address(state_pda, index);
+        pages.push(rpc.get_account(&page_pda).unwrap().data);
     }
+    pages
+}
 
-    // Now read the current mapping
-    if current_offset + 35 > data.len() {
-        panic!("Mapping index {} not found", index);
-    }
+fn get_mapping_by_mint(rpc: &RpcClient, state_pda: &Pubkey, mint: [u8; 32]) -> MintMapping {
+    let pages = get_registry_pages(rpc, state_pda);
+    let pages: Vec<&[u8]> = pages.iter().map(Vec::as_slice).collect();
+    let (page, (start, end)) = MintMapping::get_mapping_offset_pages(&pages, &mint)
+        .unwrap_or_else(|_| panic!("Mapping not found for mint: {:?}", mint));
+    MintMapping::from_bytes(&pages[page][start..end]).unwrap()
+}
 
-    // Read the offset byte to determine the actual size of this mapping
-    let mapping_size = data[current_offset + 32] as usize;
-    let mapping_data = &data[current_offset..current_offset + mapping_size];
+fn print_metadata(mapping: &MintMapping) {
+    if let Some(metadata) = mapping.get_metadata() {
+        println!(
+            "{} ({}) tags: {:#06x} risk tier: {}",
+            metadata.symbol(),
+            metadata.name(),
+            metadata.tags,
+            metadata.risk_tier()
+        );
+    }
+}
 
-    MintMapping::from_bytes(mapping_data).unwrap()
+fn print_delegate(mapping: &MintMapping) {
+    if let Some(delegate) = mapping.delegate() {
+        println!("Delegate: {}", Pubkey::from(delegate));
+    }
 }
 
-fn get_mapping_by_mint(rpc: &RpcClient, state_pda: &Pubkey, mint: [u8; 32]) -> MintMapping {
-    let reg = get_registry(rpc, state_pda);
-    for i in 0..reg.total_mappings as usize {
-        let mapping = get_mapping_by_index(rpc, state_pda, i);
-        if mapping.mint == mint {
-            return mapping;
+fn source_entry(source: &SourceInput) -> SourceEntry {
+    let source_type = SourceType::from_name(&source.source_type)
+        .unwrap_or_else(|| panic!("Unknown source type: {}", source.source_type));
+    match source_type {
+        SourceType::Scope => {
+            SourceEntry::scope(source.scope_details.expect("scope_details is required"))
         }
+        SourceType::Fixed => SourceEntry::fixed(
+            source.value.expect("value is required"),
+            source.expo,
+            source
+                .reference
+                .map(|reference| (reference, source.band_bps)),
+        ),
+        SourceType::StakePool => {
+            let account = source.account.as_ref().expect("account is required");
+            let quote_mint = source.quote_mint.as_ref().expect("quote_mint is required");
+            SourceEntry::stake_pool(
+                Pubkey::from_str(account).unwrap().to_bytes(),
+                Pubkey::from_str(quote_mint).unwrap().to_bytes(),
+            )
+        }
+        _ => {
+            let account = source.account.as_ref().expect("account is required");
+            SourceEntry::account(source_type, Pubkey::from_str(account).unwrap().to_bytes())
+        }
+    }
+}
+
+/// One account per account-based source, in the order the program checks them. Scope sources
+/// take the registry feed they read, which the program always checks; the other oracles are
+/// passed as the program id unless `verify_oracles` is set or the registry is in strict mode.
+fn oracle_account_metas(
+    program_id: Pubkey,
+    registry: &ScopeMappingRegistry,
+    mapping: &MintMapping,
+    verify_oracles: bool,
+) -> Vec<AccountMeta> {
+    let metas: Vec<AccountMeta> = mapping
+        .oracle_sources()
+        .filter_map(|source| {
+            let account = match source.source_type()? {
+                SourceType::Scope => Pubkey::from(
+                    registry
+                        .mapping_scope_feed(mapping.scope_feed())
+                        .expect("The registry has no Scope feed at the mapping's index")?,
+                ),
+                _ if verify_oracles || registry.providers.is_strict() => {
+                    Pubkey::from(source.oracle_account()?)
+                }
+                _ => {
+                    source.oracle_account()?;
+                    program_id
+                }
+            };
+            Some(AccountMeta::new_readonly(account, false))
+        })
+        .collect();
+    if metas.iter().all(|meta| meta.pubkey == program_id) {
+        return Vec::new();
     }
-    panic!("Mapping not found for mint: {:?}", mint);
+    metas
 }
 
+/// Reported when a mapping from the input has too many sources or attributes, or its entry would
+/// be larger than 255 bytes.
+const MAPPING_LIMITS: &str = "Mapping exceeds the source, attribute or 255 byte entry limits";
+
 fn process_mint_mapping(
     rpc: &RpcClient,
     fee_payer: &Keypair,
+    funder: Option<&Keypair>,
+    verify_mint: bool,
+    verify_oracles: bool,
     program_id: Pubkey,
     state_pda: Pubkey,
     mapping: MintMappingInput,
@@ -240,23 +811,82 @@ fn process_mint_mapping(
         .switch_board
         .as_ref()
         .map(|s| Pubkey::from_str(s).unwrap().to_bytes());
-    let mint_mapping = MintMapping::new(
+    let mut mint_mapping = MintMapping::new(
         mint_bytes,
         scope_details,
         pyth_account_bytes,
         switch_board_bytes,
         mapping.decimals,
     );
-    let ix = create_add_mapping_ix(program_id, fee_payer, state_pda, mint_mapping);
+    mint_mapping
+        .set_scope_feed(mapping.scope_feed)
+        .expect(MAPPING_LIMITS);
+    for source in &mapping.sources {
+        mint_mapping
+            .add_source(source_entry(source))
+            .expect(MAPPING_LIMITS);
+    }
+    if let Some(target) = &mapping.alias_of {
+        let target = Pubkey::from_str(target).unwrap().to_bytes();
+        mint_mapping
+            .set_alias(Some(MintAlias::new(target, mapping.alias_multiplier)))
+            .expect(MAPPING_LIMITS);
+    }
+    if let Some(selection) = &mapping.price_selection {
+        let selection = PriceSelection::from_name(selection)
+            .unwrap_or_else(|| panic!("Unknown price selection {selection}"));
+        mint_mapping
+            .set_price_selection(selection)
+            .expect(MAPPING_LIMITS);
+    }
+    if let Some(quote) = &mapping.quote_currency {
+        let quote = QuoteCurrency::from_name(quote)
+            .unwrap_or_else(|| panic!("Unknown quote currency {quote}"));
+        mint_mapping
+            .set_quote_currency(quote)
+            .expect(MAPPING_LIMITS);
+    }
+    mint_mapping
+        .set_validity_window(ValidityWindow::new(mapping.valid_from, mapping.valid_until))
+        .expect(MAPPING_LIMITS);
+    if !mapping.symbol.is_empty() || !mapping.name.is_empty() || mapping.tags != 0 {
+        let metadata = TokenMetadata::new(&mapping.symbol, &mapping.name, mapping.tags)
+            .expect("Symbol must be at most 10 bytes and name at most 32 bytes");
+        mint_mapping
+            .set_metadata(Some(metadata))
+            .expect(MAPPING_LIMITS);
+    }
+    let mut signers = vec![fee_payer];
+    if let Some(funder) = funder {
+        signers.insert(0, funder);
+    }
+    let registry = get_registry(rpc, &state_pda);
+    // The entry lands on the first account of the chain with room, so every page is writable
+    let mut remaining = page_account_metas(rpc, &state_pda, true);
+    remaining.extend(oracle_account_metas(
+        program_id,
+        &registry,
+        &mint_mapping,
+        verify_oracles,
+    ));
+    let ix = create_add_mapping_ix(
+        program_id,
+        fee_payer,
+        state_pda,
+        funder.map(Signer::pubkey),
+        verify_mint.then(|| Pubkey::from(mint_bytes)),
+        &remaining,
+        mint_mapping,
+    );
     let msg = v0::Message::try_compile(
-        &fee_payer.pubkey(),
+        &signers[0].pubkey(),
         &[ix],
         &[],
         rpc.get_latest_blockhash().unwrap(),
     )
     .unwrap();
     println!("Adding mapping: {:?}", mapping.symbol + " " + &mapping.name);
-    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[fee_payer]).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &signers).unwrap();
     let result = rpc.send_and_confirm_transaction(&tx);
     println!("result: {:?}", result);
     assert!(result.is_ok());
@@ -264,18 +894,52 @@ fn process_mint_mapping(
 
 fn main() {
     let cli = Cli::parse();
-    let (rpc, fee_payer, program_id, state_pda, bump) = setup_rpc_and_program();
+    let (rpc, fee_payer, program_id, live_pda, live_bump) = setup_rpc_and_program();
+    let (state_pda, bump) = if cli.draft {
+        client::find_draft_address(&live_pda)
+    } else {
+        (live_pda, live_bump)
+    };
+    // Bookkeeping commands take a continuation page in place of the registry
+    let page_pda = cli
+        .page
+        .map(|index| client::find_page_address(&live_pda, index).0);
+    let bookkeeping_pda = page_pda.unwrap_or(state_pda);
 
     match cli.command {
-        Commands::Init {} => {
+        Commands::Init { max_mappings } => {
+            let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+            let mut ixs = vec![];
             if check_registry_is_initialized(&rpc, &state_pda) {
                 println!("Registry is already initialized");
+            } else {
+                ixs.push(create_initialize_registry_ix(
+                    program_id,
+                    &fee_payer,
+                    state_pda,
+                    bump,
+                    max_mappings,
+                ));
+            }
+            if check_audit_log_is_initialized(&rpc, &audit_log_pda) {
+                println!("Audit log is already initialized");
+            } else {
+                ixs.push(create_initialize_audit_log_ix(
+                    program_id, &fee_payer, state_pda,
+                ));
+            }
+            let (price_cache_pda, _) = client::find_price_cache_address(&state_pda);
+            if check_price_cache_is_initialized(&rpc, &price_cache_pda) {
+                println!("Price cache is already initialized");
+            } else {
+                ixs.push(create_initialize_price_cache_ix(&fee_payer, state_pda));
+            }
+            if ixs.is_empty() {
                 return;
             }
-            let ix = create_initialize_registry_ix(program_id, &fee_payer, state_pda, bump);
             let msg = v0::Message::try_compile(
                 &fee_payer.pubkey(),
-                &[ix],
+                &ixs,
                 &[],
                 rpc.get_latest_blockhash().unwrap(),
             )
@@ -296,8 +960,15 @@ fn main() {
             switchboard,
             chain_id,
             asset_id,
+            symbol,
+            name,
+            tags,
             json,
+            funder,
+            verify_mint,
+            verify_oracles,
         } => {
+            let funder = funder.map(|path| Keypair::read_from_file(path).unwrap());
             if !check_registry_is_initialized(&rpc, &state_pda) {
                 println!("Registry is not initialized. Run 'init' first.");
                 return;
@@ -309,7 +980,16 @@ fn main() {
                 for mapping in mappings {
                     println!("Processing mapping: {:?}", mapping);
                     if mapping.scope_details.is_some() || mapping.pyth_account.is_some() {
-                        process_mint_mapping(&rpc, &fee_payer, program_id, state_pda, mapping);
+                        process_mint_mapping(
+                            &rpc,
+                            &fee_payer,
+                            funder.as_ref(),
+                            verify_mint,
+                            verify_oracles,
+                            program_id,
+                            state_pda,
+                            mapping,
+                        );
                     }
                 }
             } else {
@@ -317,25 +997,46 @@ fn main() {
                 let mint = mint.expect("--mint is required");
                 let decimals = decimals.expect("--decimals is required");
                 let mapping = MintMappingInput {
-                    symbol: "".to_string(),
-                    name: "".to_string(),
+                    symbol: symbol.unwrap_or_default(),
+                    name: name.unwrap_or_default(),
                     mint,
                     decimals,
                     scope_details: None,
+                    scope_feed: 0,
                     pyth_account: None,
                     switch_board: None,
+                    tags,
+                    sources: Vec::new(),
+                    alias_of: None,
+                    alias_multiplier: None,
+                    price_selection: None,
+                    quote_currency: None,
+                    valid_from: None,
+                    valid_until: None,
                 };
-                process_mint_mapping(&rpc, &fee_payer, program_id, state_pda, mapping);
+                process_mint_mapping(
+                    &rpc,
+                    &fee_payer,
+                    funder.as_ref(),
+                    verify_mint,
+                    verify_oracles,
+                    program_id,
+                    state_pda,
+                    mapping,
+                );
             }
         }
-        Commands::CloseMapping { mint } => {
+        Commands::CloseMapping { mint, refund_to } => {
             if !check_registry_is_initialized(&rpc, &state_pda) {
                 println!("Registry is not initialized. Run 'init' first.");
                 return;
             }
             let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
-            let close_ix =
-                create_close_mapping_ix(program_id, &fee_payer, state_pda, mint_bytes, bump);
+            let refund_to = refund_to.map(|refund_to| Pubkey::from_str(&refund_to).unwrap());
+            let pages = page_account_metas(&rpc, &state_pda, true);
+            let close_ix = create_close_mapping_ix(
+                program_id, &fee_payer, state_pda, mint_bytes, bump, refund_to, &pages,
+            );
             let msg = v0::Message::try_compile(
                 &fee_payer.pubkey(),
                 &[close_ix],
@@ -351,27 +1052,693 @@ fn main() {
             let reg = get_registry(&rpc, &state_pda);
             println!("Registry: {:?}", reg);
         }
-        Commands::Show { mint } => {
-            if !check_registry_is_initialized(&rpc, &state_pda) {
-                println!("Registry is not initialized.");
+        Commands::Reserve { bytes, funder } => {
+            if !check_registry_is_initialized(&rpc, &bookkeeping_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
                 return;
             }
-            let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
-            let mapping = get_mapping_by_mint(&rpc, &state_pda, mint_bytes);
-            println!("Mapping: {:?}", mapping);
+            let funder = funder.map(|path| Keypair::read_from_file(path).unwrap());
+            let reserve_ix = create_reserve_capacity_ix(
+                program_id,
+                &fee_payer,
+                state_pda,
+                page_pda,
+                funder.as_ref().map(Signer::pubkey),
+                bytes,
+            );
+            let mut signers = vec![&fee_payer];
+            if let Some(funder) = funder.as_ref() {
+                signers.insert(0, funder);
+            }
+            let msg = v0::Message::try_compile(
+                &signers[0].pubkey(),
+                &[reserve_ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &signers).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &bookkeeping_pda);
+            println!("Registry: {:?}", reg);
         }
-        Commands::ShowAll {} => {
-            if !check_registry_is_initialized(&rpc, &state_pda) {
-                println!("Registry is not initialized.");
+        Commands::Compact { retain, refund_to } => {
+            if !check_registry_is_initialized(&rpc, &bookkeeping_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
                 return;
             }
-            let reg = get_registry(&rpc, &state_pda);
+            let refund_to = refund_to.map(|refund_to| Pubkey::from_str(&refund_to).unwrap());
+            let compact_ix = create_compact_ix(
+                program_id, &fee_payer, state_pda, page_pda, refund_to, retain,
+            );
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[compact_ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &bookkeeping_pda);
+            println!("Registry: {:?}", reg);
+        }
+        Commands::PruneExpired { max_entries } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let pages = page_account_metas(&rpc, &state_pda, true);
+            let ix = create_prune_expired_ix(
+                program_id,
+                fee_payer.pubkey(),
+                state_pda,
+                max_entries,
+                &pages,
+            );
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            println!("Registry: {:?}", reg);
+        }
+        Commands::SetProviderConfig {
+            scope,
+            pyth,
+            switchboard,
+            relaxed,
+        } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let program = |program: Option<String>| {
+                program.map_or([0u8; 32], |program| {
+                    Pubkey::from_str(&program).unwrap().to_bytes()
+                })
+            };
+            let providers = ProviderConfig {
+                scope_program: program(scope),
+                pyth_program: program(pyth),
+                switchboard_program: program(switchboard),
+                strict: !relaxed as u8,
+            };
+            let ix = create_set_provider_config_ix(program_id, &fee_payer, state_pda, providers);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            println!("Registry: {:?}", reg);
+        }
+        Commands::DisableProvider { provider, enable } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let bit = SourceType::from_name(&provider)
+                .and_then(SourceType::legacy_bit)
+                .expect("Only scope, pyth and switchboard can be disabled");
+            let disabled = get_registry(&rpc, &state_pda).disabled_providers;
+            let disabled = if enable {
+                disabled & !bit
+            } else {
+                disabled | bit
+            };
+            let ix = create_set_disabled_providers_ix(program_id, &fee_payer, state_pda, disabled);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            println!("Disabled providers: {:#05b}", reg.disabled_providers);
+        }
+        Commands::SetQuoteConversion { mint } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let mint = mint.map_or([0u8; 32], |mint| {
+                Pubkey::from_str(&mint).unwrap().to_bytes()
+            });
+            let pages = page_account_metas(&rpc, &state_pda, false);
+            let ix =
+                create_set_quote_conversion_ix(program_id, &fee_payer, state_pda, mint, &pages);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            println!("SOL/USD mint: {}", Pubkey::from(reg.sol_usd_mint));
+        }
+        Commands::SetScopeFeed { index, feed } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let feed = feed.map_or([0u8; 32], |feed| {
+                Pubkey::from_str(&feed).unwrap().to_bytes()
+            });
+            let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, index, feed);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            for (index, feed) in reg.scope_feeds.iter().enumerate() {
+                println!("Scope feed {}: {}", index, Pubkey::from(*feed));
+            }
+        }
+        Commands::SetDelegate { mint, delegate } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
+            let delegate = delegate.map_or([0u8; 32], |delegate| {
+                Pubkey::from_str(&delegate).unwrap().to_bytes()
+            });
+            let pages = page_account_metas(&rpc, &state_pda, true);
+            let ix = create_set_delegate_ix(
+                program_id, &fee_payer, state_pda, mint_bytes, delegate, &pages,
+            );
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let mapping = get_mapping_by_mint(&rpc, &state_pda, mint_bytes);
+            print_delegate(&mapping);
+        }
+        Commands::UpdateSources {
+            mint,
+            scope,
+            scope_feed,
+            pyth,
+            switchboard,
+            sources,
+            signer,
+            verify_oracles,
+        } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let signer = signer.map(|path| Keypair::read_from_file(path).unwrap());
+            let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
+            let existing = get_mapping_by_mint(&rpc, &state_pda, mint_bytes);
+            let account =
+                |account: Option<String>| account.map(|a| Pubkey::from_str(&a).unwrap().to_bytes());
+            let mut with_sources = MintMapping::new(
+                mint_bytes,
+                scope.map(|scope| [scope[0], scope[1], scope[2]]),
+                account(pyth),
+                account(switchboard),
+                existing.decimals,
+            );
+            with_sources
+                .set_scope_feed(scope_feed)
+                .expect(MAPPING_LIMITS);
+            if let Some(path) = sources {
+                let file = std::fs::File::open(path).expect("Failed to open JSON file");
+                let sources: Vec<SourceInput> =
+                    serde_json::from_reader(file).expect("Invalid JSON");
+                for source in &sources {
+                    with_sources
+                        .add_source(source_entry(source))
+                        .expect(MAPPING_LIMITS);
+                }
+            }
+            // Everything but the sources has to match the live entry
+            let mapping = existing
+                .with_sources_of(&with_sources)
+                .expect(MAPPING_LIMITS);
+            let registry = get_registry(&rpc, &state_pda);
+            let mut remaining = page_account_metas(&rpc, &state_pda, true);
+            remaining.extend(oracle_account_metas(
+                program_id,
+                &registry,
+                &mapping,
+                verify_oracles,
+            ));
+            let mut signers = vec![&fee_payer];
+            if let Some(signer) = signer.as_ref() {
+                signers.push(signer);
+            }
+            let ix = create_update_sources_ix(
+                program_id,
+                signers[signers.len() - 1].pubkey(),
+                state_pda,
+                &remaining,
+                mapping,
+            );
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &signers).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let mapping = get_mapping_by_mint(&rpc, &state_pda, mint_bytes);
+            println!("Mapping: {:?}", mapping);
+        }
+        Commands::SetRateLimit {
+            max_mutations,
+            window_slots,
+            guardian,
+            guardian_keypair,
+        } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let guardian_keypair =
+                guardian_keypair.map(|path| Keypair::read_from_file(path).unwrap());
+            let guardian = guardian.map_or([0u8; 32], |guardian| {
+                Pubkey::from_str(&guardian).unwrap().to_bytes()
+            });
+            let ix = create_set_rate_limit_ix(
+                program_id,
+                &fee_payer,
+                state_pda,
+                guardian_keypair.as_ref().map(Signer::pubkey),
+                SetRateLimitIxData {
+                    max_mutations,
+                    window_slots,
+                    guardian,
+                },
+            );
+            let mut signers = vec![&fee_payer];
+            if let Some(guardian_keypair) = guardian_keypair.as_ref() {
+                signers.push(guardian_keypair);
+            }
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &signers).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &state_pda);
+            println!("Rate limit: {:?}", reg.rate_limit);
+        }
+        Commands::Show { mint } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized.");
+                return;
+            }
+            let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
+            let mapping = get_mapping_by_mint(&rpc, &state_pda, mint_bytes);
+            print_metadata(&mapping);
+            print_delegate(&mapping);
+            println!("Mapping: {:?}", mapping);
+        }
+        Commands::Prove { mint } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized.");
+                return;
+            }
+            let mint_bytes = Pubkey::from_str(&mint).unwrap().to_bytes();
+            // Entries on pages prove against the page root, which proves against the registry
+            let pages = get_registry_pages(&rpc, &state_pda);
+            let page_slices: Vec<&[u8]> = pages.iter().map(Vec::as_slice).collect();
+            let reg = ScopeMappingRegistry::from_account_data(&pages[0]).unwrap();
+            let proof = MappingProof::build_pages(&page_slices, &mint_bytes)
+                .unwrap_or_else(|_| panic!("Mapping not found for mint: {}", mint));
+            let hex = |bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            };
+            println!("Registry version: {}", proof.version);
+            println!("Root: {}", hex(&proof.root));
+            println!("Leaf {} of {}", proof.index, proof.leaf_count);
+            println!("Entry: {}", hex(&proof.entry));
+            for sibling in &proof.siblings {
+                println!("Sibling: {}", hex(sibling));
+            }
+            if let Some(page) = &proof.page {
+                println!("Page {} of {}", page.index + 1, page.page_count);
+                println!("Pages root: {}", hex(&page.root));
+                for sibling in &page.siblings {
+                    println!("Page sibling: {}", hex(sibling));
+                }
+            }
+            println!("Verified: {}", proof.verify_registry(&reg));
+        }
+        Commands::ShowAll {} => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized.");
+                return;
+            }
+            let reg = get_registry(&rpc, &state_pda);
             println!("Registry: {:?}", reg);
-            for i in 0..reg.total_mappings as usize {
-                let mapping = get_mapping_by_index(&rpc, &state_pda, i);
+            let mut i = 0;
+            for (page, data) in get_registry_pages(&rpc, &state_pda).iter().enumerate() {
+                if page > 0 {
+                    let page_reg = ScopeMappingRegistry::from_account_data(data).unwrap();
+                    println!("Page {}: {:?}", page, page_reg);
+                }
+                // Walk the live entries, skipping closed ones
+                for (start, end) in ScopeMappingRegistry::mapping_offsets(data).unwrap() {
+                    let mapping = MintMapping::from_bytes(&data[start..end]).unwrap();
+                    println!("Mapping {}: {:?}", i, Pubkey::from(mapping.mint));
+                    print_metadata(&mapping);
+                    print_delegate(&mapping);
+                    println!("Mapping: {:?}", mapping);
+                    i += 1;
+                }
+            }
+        }
+        Commands::ShowAuditLog {} => {
+            let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+            if !check_audit_log_is_initialized(&rpc, &audit_log_pda) {
+                println!("Audit log is not initialized. Run 'init' first.");
+                return;
+            }
+            let data = rpc.get_account(&audit_log_pda).unwrap().data;
+            let log = AuditLog::from_account_data(&data).unwrap();
+            println!(
+                "Audit log: {} records retained, {} total",
+                log.count, log.sequence
+            );
+            for record in AuditLog::records(&data).unwrap() {
+                println!(
+                    "slot {}: {:?} mint {} by {} hash {}",
+                    record.slot,
+                    record.kind,
+                    Pubkey::from(record.mint),
+                    Pubkey::from(record.signer),
+                    record
+                        .mapping_hash
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>()
+                );
+            }
+        }
+        Commands::RefreshPrices { mints } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized.");
+                return;
+            }
+            let mints: Vec<[u8; 32]> = mints
+                .iter()
+                .map(|mint| Pubkey::from_str(mint).unwrap().to_bytes())
+                .collect();
+            // Continuation pages go ahead of the oracle accounts
+            let pages = get_registry_pages(&rpc, &state_pda);
+            let page_slices: Vec<&[u8]> = pages.iter().map(Vec::as_slice).collect();
+            let registry = ScopeMappingRegistry::from_account_data(page_slices[0]).unwrap();
+            let mut oracles = page_account_metas(&rpc, &state_pda, false);
+            // Every source is passed its own account; the program reads them all
+            let slot = rpc.get_slot().unwrap();
+            for mint in &mints {
+                // Aliases take the sources of the mapping they resolve to
+                let resolved = MintMapping::resolve_pages(&page_slices, mint, slot)
+                    .unwrap_or_else(|_| panic!("Cannot resolve mint {}", Pubkey::from(*mint)));
+                for source in resolved.mapping.oracle_sources() {
+                    let oracle = match source.source_type() {
+                        Some(SourceType::Fixed) => continue,
+                        // Scope sources read the registry feed the mapping references
+                        Some(SourceType::Scope) => registry
+                            .scope_feed(resolved.mapping.scope_feed())
+                            .map(Pubkey::from)
+                            .expect("The registry has no Scope feed at the mapping's index"),
+                        _ => source.oracle_account().map_or(program_id, Pubkey::from),
+                    };
+                    oracles.push(AccountMeta::new_readonly(oracle, false));
+                }
+            }
+            let ix = create_refresh_prices_ix(state_pda, &mints, &oracles);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+        }
+        Commands::ShowPrices {} => {
+            let (price_cache_pda, _) = client::find_price_cache_address(&state_pda);
+            if !check_price_cache_is_initialized(&rpc, &price_cache_pda) {
+                println!("Price cache is not initialized. Run 'init' first.");
+                return;
+            }
+            let data = rpc.get_account(&price_cache_pda).unwrap().data;
+            for cached in PriceCache::prices(&data).unwrap() {
+                println!(
+                    "{}: {} x 10^{} USD (conf {}), {} x 10^{} {} published {} refreshed at slot {}",
+                    Pubkey::from(cached.mint),
+                    cached.price.value,
+                    cached.price.expo,
+                    cached.price.conf,
+                    cached.native.value,
+                    cached.native.expo,
+                    cached.quote.name(),
+                    cached.price.timestamp,
+                    cached.slot
+                );
+            }
+        }
+        Commands::Snapshot { number } => {
+            if !check_registry_is_initialized(&rpc, &state_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let ix = create_snapshot_ix(&fee_payer, state_pda, number);
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &[ix],
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            println!(
+                "Snapshot {}: {}",
+                number,
+                client::find_snapshot_address(&state_pda, number).0
+            );
+        }
+        Commands::ShowSnapshot { number } => {
+            let (snapshot_pda, _) = client::find_snapshot_address(&state_pda, number);
+            let Ok(account) = rpc.get_account(&snapshot_pda) else {
+                println!("Snapshot {} does not exist.", number);
+                return;
+            };
+            let snapshot = RegistrySnapshot::from_account_data(&account.data).unwrap();
+            // Fails if the copy does not match the content hash
+            let data = RegistrySnapshot::registry_data(&account.data).unwrap();
+            println!(
+                "Snapshot {} taken at slot {}, registry version {}, hash {}",
+                snapshot.number,
+                snapshot.slot,
+                snapshot.registry_version,
+                snapshot
+                    .content_hash
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            );
+            println!(
+                "Registry: {:?}",
+                ScopeMappingRegistry::from_account_data(data).unwrap()
+            );
+            for (i, (start, end)) in ScopeMappingRegistry::mapping_offsets(data)
+                .unwrap()
+                .enumerate()
+            {
+                let mapping = MintMapping::from_bytes(&data[start..end]).unwrap();
                 println!("Mapping {}: {:?}", i, Pubkey::from(mapping.mint));
+                print_metadata(&mapping);
+                print_delegate(&mapping);
                 println!("Mapping: {:?}", mapping);
             }
         }
+        Commands::InitDraft {} => {
+            if !check_registry_is_initialized(&rpc, &live_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let (draft_pda, _) = client::find_draft_address(&live_pda);
+            let mut ixs = vec![];
+            if check_registry_is_initialized(&rpc, &draft_pda) {
+                println!("Draft is already initialized");
+            } else {
+                ixs.push(create_initialize_draft_ix(&fee_payer, live_pda));
+            }
+            // Edits to the draft are recorded in an audit log of its own
+            let (audit_log_pda, _) = get_audit_log_pda(program_id, draft_pda);
+            if check_audit_log_is_initialized(&rpc, &audit_log_pda) {
+                println!("Draft audit log is already initialized");
+            } else {
+                ixs.push(create_initialize_audit_log_ix(
+                    program_id, &fee_payer, draft_pda,
+                ));
+            }
+            if ixs.is_empty() {
+                return;
+            }
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &ixs,
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            println!(
+                "Draft {} based on registry version {}",
+                draft_pda,
+                get_registry(&rpc, &live_pda).version
+            );
+        }
+        Commands::Promote { expected_version } => {
+            let (draft_pda, _) = client::find_draft_address(&live_pda);
+            if !check_registry_is_initialized(&rpc, &draft_pda) {
+                println!("Draft is not initialized. Run 'init-draft' first.");
+                return;
+            }
+            // Room past one realloc is reserved up front, 10 KiB per instruction
+            let draft = get_registry(&rpc, &draft_pda);
+            let live = get_registry(&rpc, &live_pda);
+            let mut missing = draft.used_bytes.saturating_sub(live.allocated_bytes);
+            let mut ixs = vec![];
+            while missing > 10 * 1024 {
+                ixs.push(create_reserve_capacity_ix(
+                    program_id,
+                    &fee_payer,
+                    live_pda,
+                    None,
+                    None,
+                    10 * 1024,
+                ));
+                missing -= 10 * 1024;
+            }
+            ixs.push(create_promote_ix(
+                program_id,
+                &fee_payer,
+                live_pda,
+                expected_version,
+            ));
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &ixs,
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            let reg = get_registry(&rpc, &live_pda);
+            println!("Registry: {:?}", reg);
+        }
+        Commands::AddPage {} => {
+            if !check_registry_is_initialized(&rpc, &live_pda) {
+                println!("Registry is not initialized. Run 'init' first.");
+                return;
+            }
+            let index = get_registry(&rpc, &live_pda).page_count + 1;
+            let (page_pda, _) = client::find_page_address(&live_pda, index);
+            let ixs = [create_add_page_ix(
+                program_id,
+                &fee_payer,
+                live_pda,
+                index,
+                &page_account_metas(&rpc, &live_pda, false),
+            )];
+            let msg = v0::Message::try_compile(
+                &fee_payer.pubkey(),
+                &ixs,
+                &[],
+                rpc.get_latest_blockhash().unwrap(),
+            )
+            .unwrap();
+            let tx =
+                VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+            let result = rpc.send_and_confirm_transaction(&tx);
+            println!("result: {:?}", result);
+            assert!(result.is_ok());
+            println!("Page {} at {}", index, page_pda);
+        }
     }
 }
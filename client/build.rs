@@ -0,0 +1,411 @@
+//! Generates the instruction builders, their argument structs and encoding and the error enum
+//! from the checked-in shank IDL.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+use serde_json::Value;
+
+const IDL_PATH: &str = "../idl/oracle_mapping.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={IDL_PATH}");
+    let idl: Value = serde_json::from_str(&fs::read_to_string(IDL_PATH).unwrap()).unwrap();
+
+    let mut out = String::new();
+    let address = idl["metadata"]["address"].as_str().unwrap();
+    writeln!(out, "/// Program id from the IDL.").unwrap();
+    writeln!(
+        out,
+        "pub const ID: Pubkey = Pubkey::from_str_const({address:?});\n"
+    )
+    .unwrap();
+
+    let types: Vec<&Value> = idl["types"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .chain(idl["accounts"].as_array().unwrap())
+        .collect();
+    for ix in idl["instructions"].as_array().unwrap() {
+        write_instruction(&mut out, ix);
+        if let Some(arg) = ix["args"].as_array().unwrap().first() {
+            let args_type = arg["type"]["defined"].as_str().unwrap();
+            write_args_struct(&mut out, args_type, &types);
+            write_args(&mut out, args_type, &types);
+        }
+    }
+    write_errors(&mut out, idl["name"].as_str().unwrap(), &idl["errors"]);
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("generated.rs");
+    fs::write(dest, out).unwrap();
+}
+
+fn write_instruction(out: &mut String, ix: &Value) {
+    let name = ix["name"].as_str().unwrap();
+    let const_name = format!("{}_DISCRIMINATOR", snake_case(name).to_uppercase());
+    let discriminant = ix["discriminant"]["value"].as_u64().unwrap();
+    let args = ix["args"].as_array().unwrap();
+    assert!(
+        args.len() <= 1,
+        "{name}: instructions take a single args struct"
+    );
+    let args_type = args
+        .first()
+        .map(|arg| arg["type"]["defined"].as_str().unwrap().to_string());
+
+    writeln!(out, "/// Discriminator of `{name}`.").unwrap();
+    writeln!(out, "pub const {const_name}: u8 = {discriminant};\n").unwrap();
+
+    // Accounts with a fixed address are filled in by the builder
+    let accounts: Vec<(String, &Value)> = ix["accounts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|acc| (snake_case(acc["name"].as_str().unwrap()), acc))
+        .collect();
+
+    writeln!(out, "/// Accounts of `{name}`.").unwrap();
+    write_docs(out, "", &ix["docs"], true);
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq)]").unwrap();
+    writeln!(out, "pub struct {name} {{").unwrap();
+    for (field, acc) in &accounts {
+        if fixed_address(field).is_some() {
+            continue;
+        }
+        write_docs(out, "    ", &acc["docs"], false);
+        let ty = if is_optional(acc) {
+            "Option<Pubkey>"
+        } else {
+            "Pubkey"
+        };
+        writeln!(out, "    pub {field}: {ty},").unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    let args_param = args_type
+        .as_ref()
+        .map(|ty| format!("args: &{ty}, "))
+        .unwrap_or_default();
+    let args_forward = if args_type.is_some() { "args, " } else { "" };
+
+    writeln!(out, "impl {name} {{").unwrap();
+    writeln!(
+        out,
+        "    pub fn instruction(&self, {}) -> Instruction {{",
+        args_param.trim_end_matches(", ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        self.instruction_with_remaining_accounts({args_forward}&[])"
+    )
+    .unwrap();
+    writeln!(out, "    }}\n").unwrap();
+    writeln!(
+        out,
+        "    /// Trailing absent optional accounts are left off unless `remaining_accounts` follow them."
+    )
+    .unwrap();
+    writeln!(out, "    pub fn instruction_with_remaining_accounts(").unwrap();
+    writeln!(out, "        &self,").unwrap();
+    if let Some(ty) = &args_type {
+        writeln!(out, "        args: &{ty},").unwrap();
+    }
+    writeln!(out, "        remaining_accounts: &[AccountMeta],").unwrap();
+    writeln!(out, "    ) -> Instruction {{").unwrap();
+    writeln!(
+        out,
+        "        let mut accounts = Vec::with_capacity({} + remaining_accounts.len());",
+        accounts.len()
+    )
+    .unwrap();
+    // The program reads optional accounts by position, so they can only trail the required ones
+    let required = accounts
+        .iter()
+        .take_while(|(_, acc)| !is_optional(acc))
+        .count();
+    assert!(
+        accounts[required..].iter().all(|(_, acc)| is_optional(acc)),
+        "{name}: required account after an optional one"
+    );
+    let binding = if required < accounts.len() {
+        "let mut"
+    } else {
+        "let"
+    };
+    writeln!(out, "        {binding} required_len = {required};").unwrap();
+    for (field, acc) in &accounts {
+        let writable = acc["isMut"].as_bool().unwrap();
+        let signer = acc["isSigner"].as_bool().unwrap();
+        let key = match fixed_address(field) {
+            Some(address) => address.to_string(),
+            None => format!("self.{field}"),
+        };
+        if is_optional(acc) {
+            writeln!(out, "        if {key}.is_some() {{").unwrap();
+            writeln!(out, "            required_len = accounts.len() + 1;").unwrap();
+            writeln!(out, "        }}").unwrap();
+            writeln!(
+                out,
+                "        accounts.push(optional_account_meta({key}, {writable}, {signer}));"
+            )
+            .unwrap();
+        } else {
+            let constructor = if writable { "new" } else { "new_readonly" };
+            writeln!(
+                out,
+                "        accounts.push(AccountMeta::{constructor}({key}, {signer}));"
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "        if remaining_accounts.is_empty() {{").unwrap();
+    writeln!(out, "            accounts.truncate(required_len);").unwrap();
+    writeln!(out, "        }} else {{").unwrap();
+    writeln!(
+        out,
+        "            accounts.extend_from_slice(remaining_accounts);"
+    )
+    .unwrap();
+    writeln!(out, "        }}\n").unwrap();
+    writeln!(out, "        let mut data = vec![{const_name}];").unwrap();
+    if args_type.is_some() {
+        writeln!(out, "        args.write_args(&mut data);").unwrap();
+    }
+    writeln!(out, "        Instruction {{").unwrap();
+    writeln!(out, "            program_id: ID,").unwrap();
+    writeln!(out, "            accounts,").unwrap();
+    writeln!(out, "            data,").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+/// An args struct with the fields of the IDL. Other defined types are those of the state crate.
+fn write_args_struct(out: &mut String, args_type: &str, types: &[&Value]) {
+    let defined = find_type(args_type, types);
+    write_docs(out, "", &defined["docs"], false);
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq)]").unwrap();
+    writeln!(out, "pub struct {args_type} {{").unwrap();
+    for field in defined["type"]["fields"].as_array().unwrap() {
+        write_docs(out, "    ", &field["docs"], false);
+        let name = snake_case(field["name"].as_str().unwrap());
+        writeln!(out, "    pub {name}: {},", rust_type(&field["type"])).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn rust_type(ty: &Value) -> String {
+    if let Some(primitive) = ty.as_str() {
+        primitive.to_string()
+    } else if let Some(array) = ty["array"].as_array() {
+        format!("[{}; {}]", rust_type(&array[0]), array[1])
+    } else if let Some(name) = ty["defined"].as_str() {
+        name.to_string()
+    } else {
+        panic!("{ty}: no Rust type for this IDL type");
+    }
+}
+
+/// `InstructionArgs` of an args struct: its fields in IDL order, integers little-endian and
+/// without padding, as the `from_bytes` of the program reads them.
+fn write_args(out: &mut String, args_type: &str, types: &[&Value]) {
+    writeln!(out, "impl InstructionArgs for {args_type} {{").unwrap();
+    writeln!(out, "    fn write_args(&self, data: &mut Vec<u8>) {{").unwrap();
+    let fields = find_type(args_type, types)["type"]["fields"]
+        .as_array()
+        .unwrap();
+    write_fields(out, "self", fields, types, 2);
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_value(out: &mut String, expr: &str, ty: &Value, types: &[&Value], depth: usize) {
+    let indent = "    ".repeat(depth);
+    if let Some(primitive) = ty.as_str() {
+        match primitive {
+            "u8" => writeln!(out, "{indent}data.push({expr});").unwrap(),
+            _ => writeln!(
+                out,
+                "{indent}data.extend_from_slice(&{expr}.to_le_bytes());"
+            )
+            .unwrap(),
+        }
+    } else if let Some(array) = ty["array"].as_array() {
+        if array[0].as_str() == Some("u8") {
+            writeln!(out, "{indent}data.extend_from_slice(&{expr});").unwrap();
+        } else {
+            write_elements(out, &format!("{expr}.iter()"), &array[0], types, depth);
+        }
+    } else if let Some(name) = ty["defined"].as_str() {
+        // Mappings are variable-length: the format version, then only the flagged sections
+        if name == "MintMapping" {
+            // The mapping setters reject entries past `MintMapping::MAX_LEN`, which have no encoding
+            writeln!(out, "{indent}data.push(MintMapping::FORMAT_VERSION);").unwrap();
+            writeln!(
+                out,
+                "{indent}let bytes = {expr}.to_bytes().expect(\"Mapping larger than MintMapping::MAX_LEN\");"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "{indent}data.extend_from_slice(&bytes[..{expr}.serialized_size() as usize]);"
+            )
+            .unwrap();
+            return;
+        }
+        let defined = find_type(name, types);
+        match defined["type"]["kind"].as_str().unwrap() {
+            "enum" => writeln!(out, "{indent}data.push({expr} as u8);").unwrap(),
+            _ => write_fields(
+                out,
+                expr,
+                defined["type"]["fields"].as_array().unwrap(),
+                types,
+                depth,
+            ),
+        }
+    } else {
+        panic!("{ty}: no encoding for this IDL type");
+    }
+}
+
+fn write_fields(out: &mut String, expr: &str, fields: &[Value], types: &[&Value], depth: usize) {
+    let mut count = None;
+    for field in fields {
+        let name = snake_case(field["name"].as_str().unwrap());
+        let field_expr = format!("{expr}.{name}");
+        match (&count, field["type"]["array"].as_array()) {
+            // Counted arrays only carry their `count` leading elements
+            (Some(count), Some(array)) => {
+                let elements = format!("{field_expr}[..{count} as usize].iter()");
+                write_elements(out, &elements, &array[0], types, depth);
+            }
+            _ => write_value(out, &field_expr, &field["type"], types, depth),
+        }
+        count = (name == "count").then_some(field_expr);
+    }
+}
+
+fn write_elements(out: &mut String, elements: &str, ty: &Value, types: &[&Value], depth: usize) {
+    let indent = "    ".repeat(depth);
+    writeln!(out, "{indent}for item{depth} in {elements} {{").unwrap();
+    write_value(out, &format!("(*item{depth})"), ty, types, depth + 1);
+    writeln!(out, "{indent}}}").unwrap();
+}
+
+fn find_type<'a>(name: &str, types: &[&'a Value]) -> &'a Value {
+    types
+        .iter()
+        .find(|defined| defined["name"].as_str() == Some(name))
+        .unwrap_or_else(|| panic!("{name}: type missing from the IDL"))
+}
+
+fn write_errors(out: &mut String, program: &str, errors: &Value) {
+    let name = format!("{}Error", upper_camel_case(program));
+    let errors = errors.as_array().unwrap();
+
+    writeln!(
+        out,
+        "/// Custom program errors, by `ProgramError::Custom` code."
+    )
+    .unwrap();
+    writeln!(out, "#[repr(u32)]").unwrap();
+    writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq)]").unwrap();
+    writeln!(out, "pub enum {name} {{").unwrap();
+    for error in errors {
+        let msg = error["msg"].as_str().unwrap();
+        writeln!(out, "    /// {msg}").unwrap();
+        writeln!(
+            out,
+            "    {} = {},",
+            error["name"].as_str().unwrap(),
+            error["code"]
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "impl {name} {{").unwrap();
+    writeln!(out, "    pub fn from_code(code: u32) -> Option<Self> {{").unwrap();
+    writeln!(out, "        match code {{").unwrap();
+    for error in errors {
+        let variant = error["name"].as_str().unwrap();
+        writeln!(
+            out,
+            "            {} => Some(Self::{variant}),",
+            error["code"]
+        )
+        .unwrap();
+    }
+    writeln!(out, "            _ => None,").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}\n").unwrap();
+    writeln!(out, "    pub fn message(self) -> &'static str {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for error in errors {
+        let variant = error["name"].as_str().unwrap();
+        let msg = error["msg"].as_str().unwrap();
+        writeln!(out, "            Self::{variant} => {msg:?},").unwrap();
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+}
+
+fn write_docs(out: &mut String, indent: &str, docs: &Value, separate: bool) {
+    let Some(lines) = docs.as_array() else {
+        return;
+    };
+    if separate {
+        writeln!(out, "{indent}///").unwrap();
+    }
+    for line in lines {
+        let line = line.as_str().unwrap();
+        if line.is_empty() {
+            writeln!(out, "{indent}///").unwrap();
+        } else {
+            writeln!(out, "{indent}/// {line}").unwrap();
+        }
+    }
+}
+
+fn is_optional(acc: &Value) -> bool {
+    acc["isOptional"].as_bool().unwrap_or(false)
+}
+
+/// Accounts whose address never changes, by field name.
+fn fixed_address(field: &str) -> Option<&'static str> {
+    match field {
+        "system_program_acc" => Some("SYSTEM_PROGRAM_ID"),
+        "sysvar_rent_acc" => Some("SYSVAR_RENT_ID"),
+        _ => None,
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
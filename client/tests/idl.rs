@@ -0,0 +1,279 @@
+use std::fs;
+
+use oracle_mapping::instruction as program;
+use oracle_mapping_client::{self as client, InstructionArgs};
+use oracle_mapping_state::{MintMapping, ProviderConfig, QuoteCurrency, TokenMetadata};
+use serde_json::Value;
+
+const IDL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../idl/oracle_mapping.json");
+const IDL_GEN_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../program/src/instruction/mod.rs"
+);
+
+/// An instruction as the `idl_gen` enum declares it.
+#[derive(Debug, Default, PartialEq)]
+struct Declared {
+    name: String,
+    docs: Vec<String>,
+    accounts: Vec<DeclaredAccount>,
+    args: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct DeclaredAccount {
+    index: usize,
+    name: String,
+    writable: bool,
+    signer: bool,
+    optional: bool,
+    desc: String,
+}
+
+/// Reads the variants of the `idl_gen` enum: `///` docs, `#[account(..)]` attributes and
+/// `Name(ArgsType),` lines.
+fn declared_instructions() -> Vec<Declared> {
+    let source = fs::read_to_string(IDL_GEN_PATH).unwrap();
+    let start = source.find("enum _InstructionSet {").unwrap();
+    let mut rest = &source[start + "enum _InstructionSet {".len()..];
+    let mut declared = Vec::new();
+    let mut current = Declared::default();
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("///") {
+            let (line, after) = after.split_once('\n').unwrap();
+            current.docs.push(line.trim().to_string());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("#[account(") {
+            let end = attribute_end(after);
+            current.accounts.push(parse_account(&after[..end]));
+            rest = after[end..].strip_prefix(")]").unwrap();
+        } else if rest.starts_with('}') {
+            return declared;
+        } else {
+            let (variant, after) = rest.split_once(',').unwrap();
+            let (name, args) = variant.trim_end_matches(')').split_once('(').unwrap();
+            current.name = name.to_string();
+            current.args = args.to_string();
+            declared.push(core::mem::take(&mut current));
+            rest = after;
+        }
+    }
+}
+
+/// Position of the `)` closing an attribute, skipping string literals.
+fn attribute_end(attribute: &str) -> usize {
+    let mut quoted = false;
+    for (i, c) in attribute.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ')' if !quoted => return i,
+            _ => {}
+        }
+    }
+    panic!("unterminated account attribute: {attribute}");
+}
+
+fn parse_account(attribute: &str) -> DeclaredAccount {
+    let mut account = DeclaredAccount::default();
+    let mut quoted = false;
+    let parts = attribute.split(|c| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ',' && !quoted
+    });
+    for (i, part) in parts
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .enumerate()
+    {
+        match part.split_once('=') {
+            Some((key, value)) => {
+                let value = value.trim().trim_matches('"').to_string();
+                match key.trim() {
+                    "name" => account.name = value,
+                    "desc" => account.desc = value,
+                    key => panic!("unknown account attribute key {key}"),
+                }
+            }
+            None if i == 0 => account.index = part.parse().unwrap(),
+            None => match part {
+                "writable" => account.writable = true,
+                "signer" => account.signer = true,
+                "optional" => account.optional = true,
+                flag => panic!("unknown account flag {flag}"),
+            },
+        }
+    }
+    account
+}
+
+fn lower_camel_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for (i, c) in name.chars().enumerate() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else if i == 0 {
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[test]
+fn test_idl_matches_idl_gen() {
+    let idl: Value = serde_json::from_str(&fs::read_to_string(IDL_PATH).unwrap()).unwrap();
+    let instructions = idl["instructions"].as_array().unwrap();
+    let declared = declared_instructions();
+    assert_eq!(instructions.len(), declared.len(), "instruction count");
+
+    for (discriminant, (ix, declared)) in instructions.iter().zip(&declared).enumerate() {
+        let name = &declared.name;
+        assert_eq!(ix["name"].as_str(), Some(name.as_str()));
+        assert_eq!(
+            ix["discriminant"]["value"].as_u64(),
+            Some(discriminant as u64),
+            "{name}: discriminant"
+        );
+
+        let docs: Vec<&str> = ix["docs"]
+            .as_array()
+            .map(|docs| docs.iter().map(|doc| doc.as_str().unwrap()).collect())
+            .unwrap_or_default();
+        assert_eq!(docs, declared.docs, "{name}: docs");
+
+        let accounts = ix["accounts"].as_array().unwrap();
+        assert_eq!(accounts.len(), declared.accounts.len(), "{name}: accounts");
+        for (i, (acc, declared)) in accounts.iter().zip(&declared.accounts).enumerate() {
+            assert_eq!(declared.index, i, "{name}: account order");
+            let acc_name = lower_camel_case(&declared.name);
+            assert_eq!(acc["name"].as_str(), Some(acc_name.as_str()), "{name}");
+            assert_eq!(
+                acc["isMut"].as_bool(),
+                Some(declared.writable),
+                "{name}.{acc_name}: isMut"
+            );
+            assert_eq!(
+                acc["isSigner"].as_bool(),
+                Some(declared.signer),
+                "{name}.{acc_name}: isSigner"
+            );
+            assert_eq!(
+                acc["isOptional"].as_bool().unwrap_or(false),
+                declared.optional,
+                "{name}.{acc_name}: isOptional"
+            );
+            assert_eq!(
+                acc["docs"]
+                    .as_array()
+                    .and_then(|docs| docs.first())
+                    .and_then(Value::as_str),
+                Some(declared.desc.as_str()),
+                "{name}.{acc_name}: docs"
+            );
+        }
+
+        let args = ix["args"].as_array().unwrap();
+        assert_eq!(args.len(), 1, "{name}: args");
+        let args_name = lower_camel_case(&declared.args);
+        assert_eq!(args[0]["name"].as_str(), Some(args_name.as_str()), "{name}");
+        assert_eq!(
+            args[0]["type"]["defined"].as_str(),
+            Some(declared.args.as_str()),
+            "{name}: args type"
+        );
+    }
+}
+
+fn write_args(args: &impl InstructionArgs) -> Vec<u8> {
+    let mut data = Vec::new();
+    args.write_args(&mut data);
+    data
+}
+
+#[test]
+fn test_generated_args_decode() {
+    let mint = [7u8; 32];
+    let mut mapping = MintMapping::new(mint, Some([0, 3, u16::MAX]), Some([8; 32]), None, 6);
+    mapping
+        .set_metadata(Some(TokenMetadata::new("USDC", "USD Coin", 0).unwrap()))
+        .unwrap();
+
+    // The client and program structs share their field names, so one literal builds both
+    macro_rules! assert_decodes {
+        ($ty:ident $fields:tt) => {
+            let args = client::$ty $fields;
+            let expected = program::$ty $fields;
+            assert_eq!(program::$ty::from_bytes(&write_args(&args)).unwrap(), expected);
+        };
+    }
+
+    assert_decodes!(InitializeRegistryIxData {
+        bump: 254,
+        max_mappings: 1000,
+    });
+    assert_decodes!(AddMappingIxData { mapping });
+    // Read in place with `load_ix_data`, so its `to_bytes` is the wire format
+    let close = client::CloseMappingIxData { mint, bump: 1 };
+    let expected = program::CloseMappingIxData { mint, bump: 1 };
+    assert_eq!(write_args(&close), expected.to_bytes());
+    assert_decodes!(InitializeAuditLogIxData { bump: 2 });
+    assert_decodes!(ReserveCapacityIxData {
+        additional_bytes: 300,
+    });
+    assert_decodes!(CompactIxData { retain_bytes: 40 });
+    assert_decodes!(SetProviderConfigIxData {
+        providers: ProviderConfig {
+            scope_program: [1; 32],
+            pyth_program: [2; 32],
+            switchboard_program: [3; 32],
+            strict: 1,
+        },
+    });
+    assert_decodes!(GetMappingIxData { mint });
+    assert_decodes!(InitializePriceCacheIxData { bump: 3 });
+    let mints = [mint, [9; 32]];
+    let args = client::RefreshPricesIxData::new(&mints).unwrap();
+    let expected = program::RefreshPricesIxData::new(&mints).unwrap();
+    assert_eq!(
+        program::RefreshPricesIxData::from_bytes(&write_args(&args)).unwrap(),
+        expected
+    );
+    assert!(client::RefreshPricesIxData::new(&[]).is_none());
+    assert!(client::RefreshPricesIxData::new(&[mint; 17]).is_none());
+    assert_decodes!(SetQuoteConversionIxData {
+        quote: QuoteCurrency::Sol,
+        mint,
+    });
+    assert_decodes!(PruneExpiredIxData { max_entries: 4 });
+    assert_decodes!(SetDelegateIxData {
+        mint,
+        delegate: [5; 32],
+    });
+    assert_decodes!(UpdateSourcesIxData { mapping });
+    assert_decodes!(SetRateLimitIxData {
+        max_mutations: 10,
+        window_slots: 1500,
+        guardian: [6; 32],
+    });
+    assert_decodes!(SetDisabledProvidersIxData {
+        disabled_providers: 0b010,
+    });
+    assert_decodes!(SnapshotIxData { number: 3, bump: 5 });
+    assert_decodes!(InitializeDraftIxData { bump: 6 });
+    assert_decodes!(PromoteIxData {
+        expected_version: 12,
+    });
+    assert_decodes!(AddPageIxData { bump: 7 });
+    assert_decodes!(SetScopeFeedIxData {
+        index: 1,
+        feed: [8; 32],
+    });
+}
@@ -0,0 +1,88 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::{get_return_data, slice_invoke},
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    instruction::{GetMappingIxData, InstructionSet},
+    state::{
+        error::MappingProgramError,
+        mint_alias::ALIAS_MULTIPLIER_ONE,
+        mint_mapping::{MintMapping, ResolvedMapping},
+        scope_mapping_registry::MAX_REGISTRY_PAGES,
+        utils::DataLen,
+    },
+};
+
+/// Calls `GetMapping` on `registry_acc` and decodes the returned mapping. For an alias this is
+/// the entry it resolves to, with the alias multiplier to apply to its prices.
+///
+/// The oracle mapping program must be one of the accounts of the transaction. A mint without a
+/// mapping fails the CPI with `MintNotFound`, which aborts the calling instruction as well.
+pub fn get_mapping(
+    registry_acc: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<ResolvedMapping, ProgramError> {
+    get_mapping_pages(registry_acc, &[], mint)
+}
+
+/// `get_mapping` on a registry with continuation pages, passed in page order in `page_accs`.
+pub fn get_mapping_pages(
+    registry_acc: &AccountInfo,
+    page_accs: &[&AccountInfo],
+    mint: &Pubkey,
+) -> Result<ResolvedMapping, ProgramError> {
+    if page_accs.len() > MAX_REGISTRY_PAGES as usize {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut data = [0u8; 1 + GetMappingIxData::LEN];
+    data[0] = InstructionSet::GetMapping as u8;
+    data[1..].copy_from_slice(&GetMappingIxData { mint: *mint }.to_bytes());
+
+    // The registry first, then its pages
+    let account_infos: [&AccountInfo; MAX_REGISTRY_PAGES as usize + 1] =
+        core::array::from_fn(|i| match i {
+            0 => registry_acc,
+            _ => page_accs.get(i - 1).copied().unwrap_or(registry_acc),
+        });
+    let account_infos = &account_infos[..page_accs.len() + 1];
+    let account_metas: [AccountMeta; MAX_REGISTRY_PAGES as usize + 1] = core::array::from_fn(|i| {
+        AccountMeta::readonly(account_infos.get(i).unwrap_or(&registry_acc).key())
+    });
+    let instruction = Instruction {
+        program_id: &crate::ID,
+        data: &data,
+        accounts: &account_metas[..account_infos.len()],
+    };
+    slice_invoke(&instruction, account_infos)?;
+
+    let return_data = get_return_data().ok_or(MappingProgramError::InvalidAccountData)?;
+    if return_data.program_id() != &crate::ID {
+        return Err(MappingProgramError::InvalidAccountData.into());
+    }
+    decode_mapping(return_data.as_slice())
+}
+
+/// Decodes the return data of `GetMapping`.
+pub fn decode_mapping(return_data: &[u8]) -> Result<ResolvedMapping, ProgramError> {
+    if return_data.len() < MintMapping::HEADER_LEN
+        || (return_data[32] as usize) < MintMapping::HEADER_LEN
+        || return_data[32] as usize > return_data.len()
+    {
+        return Err(MappingProgramError::InvalidAccountData.into());
+    }
+    let (entry, multiplier) = return_data.split_at(return_data[32] as usize);
+    let multiplier = match multiplier.len() {
+        0 => ALIAS_MULTIPLIER_ONE,
+        8 => u64::from_le_bytes(multiplier.try_into().unwrap()),
+        _ => return Err(MappingProgramError::InvalidAccountData.into()),
+    };
+    Ok(ResolvedMapping {
+        mapping: MintMapping::from_bytes(entry)?,
+        multiplier,
+    })
+}
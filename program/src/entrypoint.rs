@@ -1,7 +1,12 @@
 #![allow(unexpected_cfgs)]
 
 use crate::instruction::{
-    process_add_mapping, process_close_mapping, process_initialize_state, InstructionSet,
+    process_add_mapping, process_add_page, process_close_mapping, process_compact,
+    process_get_mapping, process_initialize_audit_log, process_initialize_draft,
+    process_initialize_price_cache, process_initialize_state, process_promote,
+    process_prune_expired, process_refresh_prices, process_reserve_capacity, process_set_delegate,
+    process_set_disabled_providers, process_set_provider_config, process_set_quote_conversion,
+    process_set_rate_limit, process_snapshot, process_update_sources, InstructionSet,
 };
 use pinocchio::{
     account_info::AccountInfo, default_panic_handler, msg, no_allocator, program_entrypoint,
@@ -38,5 +43,77 @@ fn process_instruction(
             msg!("Closing mapping");
             process_close_mapping(accounts, instruction_data)
         }
+        InstructionSet::InitializeAuditLog => {
+            msg!("Initializing audit log");
+            process_initialize_audit_log(accounts, instruction_data)
+        }
+        InstructionSet::ReserveCapacity => {
+            msg!("Reserving capacity");
+            process_reserve_capacity(accounts, instruction_data)
+        }
+        InstructionSet::Compact => {
+            msg!("Compacting registry");
+            process_compact(accounts, instruction_data)
+        }
+        InstructionSet::SetProviderConfig => {
+            msg!("Setting provider config");
+            process_set_provider_config(accounts, instruction_data)
+        }
+        InstructionSet::GetMapping => {
+            msg!("Getting mapping");
+            process_get_mapping(accounts, instruction_data)
+        }
+        InstructionSet::InitializePriceCache => {
+            msg!("Initializing price cache");
+            process_initialize_price_cache(accounts, instruction_data)
+        }
+        InstructionSet::RefreshPrices => {
+            msg!("Refreshing prices");
+            process_refresh_prices(accounts, instruction_data)
+        }
+        InstructionSet::SetQuoteConversion => {
+            msg!("Setting quote conversion");
+            process_set_quote_conversion(accounts, instruction_data)
+        }
+        InstructionSet::PruneExpired => {
+            msg!("Pruning expired mappings");
+            process_prune_expired(accounts, instruction_data)
+        }
+        InstructionSet::SetDelegate => {
+            msg!("Setting delegate");
+            process_set_delegate(accounts, instruction_data)
+        }
+        InstructionSet::UpdateSources => {
+            msg!("Updating sources");
+            process_update_sources(accounts, instruction_data)
+        }
+        InstructionSet::SetRateLimit => {
+            msg!("Setting rate limit");
+            process_set_rate_limit(accounts, instruction_data)
+        }
+        InstructionSet::SetDisabledProviders => {
+            msg!("Setting disabled providers");
+            process_set_disabled_providers(accounts, instruction_data)
+        }
+        InstructionSet::Snapshot => {
+            msg!("Taking registry snapshot");
+            process_snapshot(accounts, instruction_data)
+        }
+        InstructionSet::InitializeDraft => {
+            msg!("Initializing draft registry");
+            process_initialize_draft(accounts, instruction_data)
+        }
+        InstructionSet::Promote => {
+            msg!("Promoting draft registry");
+            process_promote(accounts, instruction_data)
+        }
+        InstructionSet::AddPage => {
+            msg!("Adding registry page");
+            process_add_page(accounts, instruction_data)
+        }
+        InstructionSet::SetScopeFeed => {
+            msg!("Setting scope feed");
+            process_set_scope_feed(accounts, instruction_data)
+        }
     }
 }
@@ -1,74 +1,113 @@
 use pinocchio::{
     account_info::AccountInfo,
     program_error::ProgramError,
-    sysvars::{rent::Rent, Sysvar},
+    sysvars::{clock::Clock, Sysvar},
     ProgramResult,
 };
-use pinocchio_system::instructions::Transfer;
 
 use crate::{
-    instruction::{IntoBytes, OWNER_PUB_KEY},
+    instruction::{
+        append_audit_record, chain_account_of, chain_account_with_room, chain_data,
+        edit_chain_account, grow_registry, optional_account, record_mutation,
+        registry_page_accounts, verify_mint_account, verify_oracle_accounts, OWNER_PUB_KEY,
+    },
     state::{
-        error::MappingProgramError,
-        mint_mapping::MintMapping,
-        scope_mapping_registry::ScopeMappingRegistry,
-        utils::{load_ix_data, DataLen},
+        attributes::QuoteCurrency, audit_log::AuditKind, error::MappingProgramError,
+        mint_mapping::MintMapping, scope_mapping_registry::ScopeMappingRegistry, utils::DataLen,
         Initialized,
     },
 };
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Instruction data of `AddMapping`, encoded as
+/// `format_version(1) + MintMapping::to_bytes()[..serialized_size]`.
+/// All integers in the mapping are little-endian.
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
 pub struct AddMappingIxData {
     pub mapping: MintMapping,
 }
 
-impl DataLen for AddMappingIxData {
-    const LEN: usize = core::mem::size_of::<AddMappingIxData>(); // 32 bytes for data
-}
+impl AddMappingIxData {
+    pub const FORMAT_VERSION: u8 = MintMapping::FORMAT_VERSION;
+    pub const MAX_LEN: usize = 1 + MintMapping::MAX_LEN;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        let Some((&version, mapping_bytes)) = bytes.split_first() else {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        };
+        if version != Self::FORMAT_VERSION {
+            return Err(MappingProgramError::UnsupportedFormatVersion.into());
+        }
+        if mapping_bytes.len() < MintMapping::HEADER_LEN
+            || mapping_bytes[32] as usize != mapping_bytes.len()
+        {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+
+        let mapping = MintMapping::from_bytes(mapping_bytes)
+            .map_err(|_| MappingProgramError::InvalidInstructionData)?;
+        // Every byte must belong to a section flagged in `mapping_details`
+        if mapping.serialized_size() as usize != mapping_bytes.len() {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
 
-impl IntoBytes for AddMappingIxData {
-    fn into_bytes(&self) -> Result<&[u8], ProgramError> {
-        Ok(unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, Self::LEN) })
+        Ok(Self { mapping })
+    }
+
+    /// Only the first `serialized_size()` bytes are used. Fails when the mapping is larger than
+    /// `MintMapping::MAX_LEN`.
+    pub fn to_bytes(&self) -> Result<[u8; Self::MAX_LEN], ProgramError> {
+        let mut bytes = [0u8; Self::MAX_LEN];
+        bytes[0] = Self::FORMAT_VERSION;
+        bytes[1..].copy_from_slice(&self.mapping.to_bytes()?);
+        Ok(bytes)
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.mapping.serialized_size() as usize
     }
 }
 
+/// Adds the entry of a mint that has none in the registry or its continuation pages. The entry
+/// lands in the first account of the chain with room under the entry cap of the registry.
 pub fn process_add_mapping(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [payer_acc, state_acc, ..] = accounts else {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
     // Hardcoded authority check
-    if payer_acc.key().as_ref() != OWNER_PUB_KEY {
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
         return Err(MappingProgramError::InvalidOwner.into());
     }
 
-    if !payer_acc.is_signer() {
+    if !authority_acc.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    let end_offset = state_acc.data_len();
 
-    let ix_data = unsafe { load_ix_data::<AddMappingIxData>(data)? };
+    // Rent for the realloc comes from the optional funder, falling back to the authority
+    let funder_acc = match optional_account(accounts, 4) {
+        Some(funder_acc) => {
+            if !funder_acc.is_signer() {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            funder_acc
+        }
+        None => authority_acc,
+    };
+
+    let ix_data = AddMappingIxData::from_bytes(data)?;
 
     let mapping = ix_data.mapping;
-    let mapping_size = mapping.serialized_size();
-
-    let new_account_size = end_offset + mapping_size as usize;
+    if !mapping.is_valid() {
+        return Err(MappingProgramError::InvalidInstructionData.into());
+    }
 
-    state_acc.resize(new_account_size)?;
-    let cost = Rent::get()?.minimum_balance(new_account_size);
-    if cost > 0 {
-        Transfer {
-            from: payer_acc,
-            to: state_acc,
-            lamports: cost - state_acc.lamports(),
-        }
-        .invoke()?;
+    // Check the decimals against the mint when the client passes it
+    if let Some(mint_acc) = optional_account(accounts, 5) {
+        verify_mint_account(mint_acc, &mapping.mint, mapping.decimals)?;
     }
+    let mapping_size = mapping.serialized_size();
 
-    // Get the full account data as a mutable slice
-    let mut acc_data = state_acc.try_borrow_mut_data()?;
-    let mut registry = unsafe { *(acc_data.as_ptr() as *const ScopeMappingRegistry) };
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
 
     // CHECK if registry is initialized
     if !registry.is_initialized() {
@@ -76,27 +115,133 @@ pub fn process_add_mapping(accounts: &[AccountInfo], data: &[u8]) -> ProgramResu
     }
 
     // Validate PDA
-    ScopeMappingRegistry::validate_pda(
-        registry.bump,
-        state_acc.key(),
-        payer_acc.key(),
-        &crate::ID,
-    )?;
+    registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
 
-    if registry.owner.ne(payer_acc.key()) {
+    if registry.owner.ne(authority_acc.key()) {
         return Err(MappingProgramError::InvalidOwner.into());
     }
 
-    let old_last_mapping_offset = registry.last_mapping_offset + ScopeMappingRegistry::LEN as u16;
-    registry.add(mapping_size)?;
+    // Continuation pages follow the optional funder and mint accounts, then the oracle accounts
+    let page_accs = registry_page_accounts(accounts, 6, state_acc, &registry)?;
+    verify_oracle_accounts(accounts, 6 + page_accs.len(), &registry, &mapping)?;
+
+    match chain_account_of(state_acc, page_accs, &mapping.mint) {
+        Ok(_) => return Err(MappingProgramError::MintAlreadyMapped.into()),
+        Err(err) if err == MappingProgramError::MintNotFound.into() => {}
+        Err(err) => return Err(err),
+    }
+
+    // An already expired mapping would only wait to be pruned
+    let slot = Clock::get()?.slot;
+    if mapping.validity_window().is_expired(slot) {
+        return Err(MappingProgramError::MappingNotActive.into());
+    }
+
+    verify_mapping_references(state_acc, page_accs, &registry, &mapping, slot)?;
+
+    record_mutation(accounts, &mut registry)?;
+    let max_mappings = registry.max_mappings;
+    let entry_acc = chain_account_with_room(state_acc, page_accs, &registry)?;
+    edit_chain_account(state_acc, page_accs, &mut registry, entry_acc, |header| {
+        append_mapping(entry_acc, funder_acc, header, &mapping, max_mappings)
+    })?;
+
+    let mapping_bytes = mapping.to_bytes()?;
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::AddMapping,
+        &mapping.mint,
+        authority_acc.key(),
+        &mapping_bytes[..mapping_size as usize],
+    )
+}
+
+/// Checks that the mints `mapping` points at, its alias target and stake pool quote mints,
+/// resolve to live entries of the registry or its `page_accs` at `slot`, and that its quote
+/// currency can be converted to USD.
+pub(crate) fn verify_mapping_references(
+    state_acc: &AccountInfo,
+    page_accs: &[AccountInfo],
+    registry: &ScopeMappingRegistry,
+    mapping: &MintMapping,
+    slot: u64,
+) -> ProgramResult {
+    {
+        let registry_data = state_acc.try_borrow_data()?;
+        let (pages, count) = chain_data(&registry_data, page_accs);
+        if let Some(alias) = mapping.alias {
+            MintMapping::resolve_offset_pages(&pages[..count], &alias.target, slot)?;
+        }
+        for quote_mint in mapping
+            .oracle_sources()
+            .filter_map(|source| source.quote_mint())
+        {
+            MintMapping::resolve_offset_pages(&pages[..count], &quote_mint, slot)?;
+        }
+    }
+
+    // Prices quoted in another currency than USD need the registry's conversion mint
+    let quote = mapping.quote_currency();
+    if quote != QuoteCurrency::Usd && registry.conversion_mint(quote).is_none() {
+        return Err(MappingProgramError::InvalidQuoteConversion.into());
+    }
+    Ok(())
+}
+
+/// Writes `mapping` after the last written entry and stores the updated header, with at most
+/// `max_mappings` live entries in the account. Reallocs with rent from `funder_acc` only when
+/// the reserved capacity can't hold the entry.
+pub(crate) fn append_mapping(
+    state_acc: &AccountInfo,
+    funder_acc: &AccountInfo,
+    registry: &mut ScopeMappingRegistry,
+    mapping: &MintMapping,
+    max_mappings: u32,
+) -> ProgramResult {
+    let mapping_size = mapping.serialized_size();
+
+    let free_bytes = registry.free_bytes();
+    if free_bytes < mapping_size as u32 {
+        grow_registry(
+            state_acc,
+            funder_acc,
+            registry,
+            mapping_size as u32 - free_bytes,
+        )?;
+    }
+
+    let old_last_mapping_offset = ScopeMappingRegistry::LEN + registry.last_mapping_offset as usize;
+    registry.add(mapping_size, max_mappings)?;
+
+    // Get the full account data as a mutable slice
+    let mut acc_data = state_acc.try_borrow_mut_data()?;
 
     // Write the updated registry back to account data
     let reg_bytes = registry.to_bytes();
     acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&reg_bytes);
 
-    let mapping_bytes = mapping.to_bytes();
-    acc_data[old_last_mapping_offset as usize..(old_last_mapping_offset + mapping_size) as usize]
+    let mapping_bytes = mapping.to_bytes()?;
+    acc_data[old_last_mapping_offset..old_last_mapping_offset + mapping_size as usize]
         .copy_from_slice(&mapping_bytes[..mapping_size as usize]);
 
-    Ok(())
+    registry.refresh_root(&mut acc_data)
+}
+
+/// Replaces the live entry of `mapping.mint`. The old entry is left closed for `Compact` to
+/// reclaim and the new one is appended, since entries can change size.
+pub(crate) fn replace_mapping(
+    state_acc: &AccountInfo,
+    funder_acc: &AccountInfo,
+    registry: &mut ScopeMappingRegistry,
+    mapping: &MintMapping,
+    max_mappings: u32,
+) -> ProgramResult {
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let (start, end) = MintMapping::get_mapping_offset(&acc_data, &mapping.mint)?;
+        registry.close_mapping(&mut acc_data, start, end)?;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+    append_mapping(state_acc, funder_acc, registry, mapping, max_mappings)
 }
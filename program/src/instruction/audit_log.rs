@@ -0,0 +1,157 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    instruction::OWNER_PUB_KEY,
+    state::{
+        audit_log::{AuditKind, AuditLog, AuditRecord},
+        error::MappingProgramError,
+        scope_mapping_registry::ScopeMappingRegistry,
+        utils::{hash, load_ix_data, DataLen},
+        Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct InitializeAuditLogIxData {
+    pub bump: u8,
+}
+
+impl DataLen for InitializeAuditLogIxData {
+    const LEN: usize = 1;
+}
+
+impl InitializeAuditLogIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self { bump: bytes[0] })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.bump]
+    }
+}
+
+pub fn process_initialize_audit_log(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [payer_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Hardcoded authority check
+    if payer_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !audit_log_acc.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if registry.owner.ne(payer_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    let ix_data = unsafe { load_ix_data::<InitializeAuditLogIxData>(data)? };
+
+    // Validate both PDAs; continuation pages record into the log of their registry
+    registry.validate_address(state_acc.key(), payer_acc.key(), &crate::ID)?;
+    AuditLog::validate_pda(
+        ix_data.bump,
+        audit_log_acc.key(),
+        state_acc.key(),
+        &crate::ID,
+    )?;
+
+    // Signer seeds
+    let pda_bump_bytes = [ix_data.bump];
+    let signer_seeds = [
+        Seed::from(AuditLog::SEED.as_bytes()),
+        Seed::from(state_acc.key().as_ref()),
+        Seed::from(&pda_bump_bytes[..]),
+    ];
+    let signers = [Signer::from(&signer_seeds[..])];
+
+    let space = AuditLog::account_size();
+    CreateAccount {
+        from: payer_acc,
+        to: audit_log_acc,
+        space: space as u64,
+        owner: &crate::ID,
+        lamports: Rent::get()?.minimum_balance(space),
+    }
+    .invoke_signed(&signers)?;
+
+    let audit_log = AuditLog::new(*state_acc.key(), ix_data.bump);
+
+    unsafe {
+        audit_log_acc.borrow_mut_data_unchecked()[..AuditLog::LEN]
+            .copy_from_slice(&audit_log.to_bytes());
+    }
+
+    Ok(())
+}
+
+/// Appends a record for a change to the registry at `registry_key`.
+/// `mapping_bytes` is the new serialized mapping, empty when the mapping was removed.
+pub fn append_audit_record(
+    audit_log_acc: &AccountInfo,
+    registry_key: &Pubkey,
+    kind: AuditKind,
+    mint: &[u8; 32],
+    signer: &Pubkey,
+    mapping_bytes: &[u8],
+) -> ProgramResult {
+    if audit_log_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut log_data = audit_log_acc.try_borrow_mut_data()?;
+    let mut audit_log = AuditLog::from_account_data(&log_data)?;
+
+    if audit_log.registry.ne(registry_key) {
+        return Err(MappingProgramError::PdaMismatch.into());
+    }
+    AuditLog::validate_pda(
+        audit_log.bump,
+        audit_log_acc.key(),
+        registry_key,
+        &crate::ID,
+    )?;
+
+    let mapping_hash = if mapping_bytes.is_empty() {
+        [0u8; 32]
+    } else {
+        hash(mapping_bytes)
+    };
+
+    let record = AuditRecord {
+        slot: Clock::get()?.slot,
+        kind,
+        mint: *mint,
+        signer: *signer,
+        mapping_hash,
+    };
+
+    audit_log.append(&mut log_data, &record)
+}
@@ -1,6 +1,10 @@
 use crate::{
-    instruction::{IntoBytes, OWNER_PUB_KEY},
+    instruction::{
+        append_audit_record, chain_account_of, edit_chain_account, optional_account,
+        record_mutation, registry_page_accounts, IntoBytes, OWNER_PUB_KEY,
+    },
     state::{
+        audit_log::AuditKind,
         error::MappingProgramError,
         mint_mapping::MintMapping,
         scope_mapping_registry::ScopeMappingRegistry,
@@ -16,7 +20,7 @@ use pinocchio::{
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
 pub struct CloseMappingIxData {
     pub mint: [u8; 32],
     pub bump: u8,
@@ -26,18 +30,28 @@ impl DataLen for CloseMappingIxData {
     const LEN: usize = core::mem::size_of::<CloseMappingIxData>(); // 32 bytes for owner + 1 byte for bump
 }
 
+impl CloseMappingIxData {
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[..32].copy_from_slice(&self.mint);
+        bytes[32] = self.bump;
+        bytes
+    }
+}
+
 impl IntoBytes for CloseMappingIxData {
     fn into_bytes(&self) -> Result<&[u8], ProgramError> {
         Ok(unsafe { core::slice::from_raw_parts(self as *const Self as *const u8, Self::LEN) })
     }
 }
 
+/// Closes the entry of a mint held by the registry or any of its continuation pages.
 pub fn process_close_mapping(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [payer_acc, state_acc, _remaining @ ..] = accounts else {
+    let [authority_acc, state_acc, audit_log_acc, _remaining @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
-    if !payer_acc.is_signer() {
+    if !authority_acc.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
@@ -51,71 +65,92 @@ pub fn process_close_mapping(accounts: &[AccountInfo], data: &[u8]) -> ProgramRe
     }
     let ix_data = unsafe { load_ix_data::<CloseMappingIxData>(data)? };
 
-    let new_size;
-    {
-        // Validate that the account contains a valid registry
-        let mut acc_data = state_acc.try_borrow_mut_data()?;
-        let mut registry = unsafe { *(acc_data.as_ptr() as *const ScopeMappingRegistry) };
-        if !registry.is_initialized() {
-            return Err(ProgramError::UninitializedAccount);
-        }
+    // Validate that the account contains a valid registry
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
 
-        // Hardcoded authority check
-        if payer_acc.key().as_ref() != OWNER_PUB_KEY {
-            return Err(MappingProgramError::InvalidOwner.into());
-        }
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
 
-        // Validate that the account is owned by our program
-        if state_acc.owner() != &crate::ID {
-            return Err(ProgramError::IncorrectProgramId);
-        }
+    // Validate that the account is owned by our program
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-        // Validate the PDA
-        ScopeMappingRegistry::validate_pda(
+    // Validate the PDA, live or draft
+    match registry.draft_of() {
+        Some(live_registry) => ScopeMappingRegistry::validate_draft_pda(
             ix_data.bump,
             state_acc.key(),
-            payer_acc.key(),
+            live_registry,
             &crate::ID,
-        )?;
-
-        // Find the mapping offsets using only the mutable borrow
-        let (mint_mapping_offset, mint_mapping_end_offset) =
-            MintMapping::get_mapping_offset(&acc_data, &ix_data.mint)?;
+        )?,
+        None => ScopeMappingRegistry::validate_pda(
+            ix_data.bump,
+            state_acc.key(),
+            authority_acc.key(),
+            &crate::ID,
+        )?,
+    }
 
-        // remove the mapping by removing the data from the acc_data
-        let remove_len = mint_mapping_end_offset - mint_mapping_offset;
-        new_size = acc_data.len() - remove_len;
+    // With a refund destination the closed entry is compacted away right away and its rent
+    // released to it; without one the space stays allocated until the next `Compact`
+    let refund_acc = optional_account(accounts, 3);
 
-        acc_data.copy_within(mint_mapping_end_offset.., mint_mapping_offset);
+    // Continuation pages follow the optional refund account
+    let page_accs = registry_page_accounts(accounts, 4, state_acc, &registry)?;
+    let entry_acc = chain_account_of(state_acc, page_accs, &ix_data.mint)?;
 
-        // Zero out the trailing bytes to avoid data leakage
-        for b in &mut acc_data[new_size..] {
-            *b = 0;
-        }
+    record_mutation(accounts, &mut registry)?;
 
-        registry.subtract_mapping(remove_len as u16)?;
+    let mut new_size = None;
+    edit_chain_account(state_acc, page_accs, &mut registry, entry_acc, |header| {
+        let mut acc_data = entry_acc.try_borrow_mut_data()?;
+        let (mint_mapping_offset, mint_mapping_end_offset) =
+            MintMapping::get_mapping_offset(&acc_data, &ix_data.mint)?;
 
-        let reg_bytes = registry.to_bytes();
-        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&reg_bytes);
-    }
+        // Leave a closed entry behind instead of shifting the tail; `Compact` reclaims the space
+        header.close_mapping(&mut acc_data, mint_mapping_offset, mint_mapping_end_offset)?;
+
+        if refund_acc.is_some() {
+            let freed_bytes = (mint_mapping_end_offset - mint_mapping_offset) as u32;
+            let used_bytes = header.compact_mappings(&mut acc_data)?;
+            header.allocated_bytes = header
+                .allocated_bytes
+                .saturating_sub(freed_bytes)
+                .max(used_bytes);
+            new_size = Some(ScopeMappingRegistry::LEN + header.allocated_bytes as usize);
+        }
 
-    if new_size < ScopeMappingRegistry::LEN {
-        return Err(MappingProgramError::InvalidAccountData.into());
-    }
+        // Writes the header along with the new root
+        header.refresh_root(&mut acc_data)
+    })?;
 
-    // Calculate the new minimum balance for rent exemption
-    let min_balance = Rent::get()?.minimum_balance(new_size);
-    let current_balance = state_acc.lamports();
-    let excess = current_balance.saturating_sub(min_balance);
+    if let (Some(refund_acc), Some(new_size)) = (refund_acc, new_size) {
+        let min_balance = Rent::get()?.minimum_balance(new_size);
+        let current_balance = entry_acc.lamports();
+        let excess = current_balance.saturating_sub(min_balance);
 
-    if excess > 0 {
-        unsafe {
-            *state_acc.borrow_mut_lamports_unchecked() = current_balance - excess;
-            *payer_acc.borrow_mut_lamports_unchecked() = payer_acc.lamports() + excess;
+        if excess > 0 {
+            unsafe {
+                *entry_acc.borrow_mut_lamports_unchecked() = current_balance - excess;
+                *refund_acc.borrow_mut_lamports_unchecked() = refund_acc.lamports() + excess;
+            }
         }
-    }
 
-    state_acc.resize(new_size)?;
+        entry_acc.resize(new_size)?;
+    }
 
-    Ok(())
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::CloseMapping,
+        &ix_data.mint,
+        authority_acc.key(),
+        &[],
+    )
 }
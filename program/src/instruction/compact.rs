@@ -0,0 +1,111 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::{append_audit_record, optional_account, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct CompactIxData {
+    pub retain_bytes: u16, // Free bytes to keep allocated after the live entries
+}
+
+impl DataLen for CompactIxData {
+    const LEN: usize = 2;
+}
+
+impl CompactIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+        Ok(Self {
+            retain_bytes: u16::from_le_bytes([bytes[0], bytes[1]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        self.retain_bytes.to_le_bytes()
+    }
+}
+
+pub fn process_compact(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = CompactIxData::from_bytes(data)?;
+
+    let new_size;
+    let audit_key;
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_entries_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+        // A continuation page is recorded in the audit log of its registry
+        audit_key = *registry.page_of().unwrap_or(state_acc.key());
+
+        let used_bytes = registry.compact_mappings(&mut acc_data)?;
+        registry.allocated_bytes = registry
+            .allocated_bytes
+            .min(used_bytes.saturating_add(ix_data.retain_bytes.into()));
+        new_size = ScopeMappingRegistry::LEN + registry.allocated_bytes as usize;
+
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    // Freed rent goes to the optional refund destination, falling back to the authority
+    let min_balance = Rent::get()?.minimum_balance(new_size);
+    let current_balance = state_acc.lamports();
+    let excess = current_balance.saturating_sub(min_balance);
+    let refund_acc = optional_account(accounts, 3).unwrap_or(authority_acc);
+
+    if excess > 0 {
+        unsafe {
+            *state_acc.borrow_mut_lamports_unchecked() = current_balance - excess;
+            *refund_acc.borrow_mut_lamports_unchecked() = refund_acc.lamports() + excess;
+        }
+    }
+
+    state_acc.resize(new_size)?;
+
+    append_audit_record(
+        audit_log_acc,
+        &audit_key,
+        AuditKind::Compact,
+        &[0u8; 32],
+        authority_acc.key(),
+        &[],
+    )
+}
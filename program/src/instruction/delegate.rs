@@ -0,0 +1,218 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::{
+        append_audit_record, chain_account_of, edit_chain_account, record_mutation,
+        registry_page_accounts, replace_mapping, verify_mapping_references, verify_oracle_accounts,
+        AddMappingIxData, OWNER_PUB_KEY,
+    },
+    state::{
+        audit_log::AuditKind, error::MappingProgramError, mint_mapping::MintMapping,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+/// Instruction data of `SetDelegate`: mint(32) + delegate(32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetDelegateIxData {
+    pub mint: [u8; 32],
+    pub delegate: [u8; 32], // All-zero revokes the delegation
+}
+
+impl DataLen for SetDelegateIxData {
+    const LEN: usize = 64;
+}
+
+impl SetDelegateIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut ix_data = Self {
+            mint: [0; 32],
+            delegate: [0; 32],
+        };
+        ix_data.mint.copy_from_slice(&bytes[0..32]);
+        ix_data.delegate.copy_from_slice(&bytes[32..64]);
+        Ok(ix_data)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..32].copy_from_slice(&self.mint);
+        bytes[32..64].copy_from_slice(&self.delegate);
+        bytes
+    }
+}
+
+/// Instruction data of `UpdateSources`, in the `AddMapping` format. The mapping must match the
+/// live entry of its mint in everything but the oracle sources.
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct UpdateSourcesIxData {
+    pub mapping: MintMapping,
+}
+
+impl UpdateSourcesIxData {
+    pub const MAX_LEN: usize = AddMappingIxData::MAX_LEN;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        let AddMappingIxData { mapping } = AddMappingIxData::from_bytes(bytes)?;
+        Ok(Self { mapping })
+    }
+
+    /// Only the first `serialized_size()` bytes are used. Fails when the mapping is larger than
+    /// `MintMapping::MAX_LEN`.
+    pub fn to_bytes(&self) -> Result<[u8; Self::MAX_LEN], ProgramError> {
+        AddMappingIxData {
+            mapping: self.mapping,
+        }
+        .to_bytes()
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.mapping.serialized_size() as usize
+    }
+}
+
+/// Assigns the key allowed to update the oracle sources of one mint, or revokes it. The entry
+/// may be held by the registry or any of its continuation pages.
+pub fn process_set_delegate(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetDelegateIxData::from_bytes(data)?;
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Validate PDA
+    registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    // Continuation pages follow the system program account
+    let page_accs = registry_page_accounts(accounts, 4, state_acc, &registry)?;
+    let entry_acc = chain_account_of(state_acc, page_accs, &ix_data.mint)?;
+    let mut mapping = {
+        let acc_data = entry_acc.try_borrow_data()?;
+        let (start, end) = MintMapping::get_mapping_offset(&acc_data, &ix_data.mint)?;
+        MintMapping::from_bytes(&acc_data[start..end])?
+    };
+
+    let delegate = Some(ix_data.delegate).filter(|delegate| *delegate != [0u8; 32]);
+    mapping.set_delegate(delegate)?;
+    // The delegate attribute must still fit the entry
+    if !mapping.is_valid() {
+        return Err(MappingProgramError::InvalidMappingSize.into());
+    }
+
+    record_mutation(accounts, &mut registry)?;
+    let max_mappings = registry.max_mappings;
+    edit_chain_account(state_acc, page_accs, &mut registry, entry_acc, |header| {
+        replace_mapping(entry_acc, authority_acc, header, &mapping, max_mappings)
+    })?;
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetDelegate,
+        &ix_data.mint,
+        authority_acc.key(),
+        &ix_data.delegate,
+    )
+}
+
+/// Replaces the oracle sources of a mint. Signed by the registry authority or the delegate of
+/// the mint; any other change to the entry is rejected.
+pub fn process_update_sources(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [signer_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !signer_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = UpdateSourcesIxData::from_bytes(data)?;
+    let mapping = ix_data.mapping;
+    if !mapping.is_valid() {
+        return Err(MappingProgramError::InvalidInstructionData.into());
+    }
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Validate PDA
+    registry.validate_address(state_acc.key(), &registry.owner, &crate::ID)?;
+
+    // Continuation pages follow the system program account, ahead of the oracle accounts
+    let page_accs = registry_page_accounts(accounts, 4, state_acc, &registry)?;
+    let entry_acc = chain_account_of(state_acc, page_accs, &mapping.mint)?;
+    let existing = {
+        let acc_data = entry_acc.try_borrow_data()?;
+        let (start, end) = MintMapping::get_mapping_offset(&acc_data, &mapping.mint)?;
+        MintMapping::from_bytes(&acc_data[start..end])?
+    };
+
+    let is_authority =
+        signer_acc.key().as_ref() == OWNER_PUB_KEY && registry.owner.eq(signer_acc.key());
+    if !is_authority && existing.delegate() != Some(*signer_acc.key()) {
+        return Err(MappingProgramError::InvalidDelegate.into());
+    }
+
+    // Decimals, metadata, aliases and attributes, the delegate included, stay as they are
+    if existing.with_sources_of(&mapping)? != mapping {
+        return Err(MappingProgramError::InvalidInstructionData.into());
+    }
+
+    verify_oracle_accounts(accounts, 4 + page_accs.len(), &registry, &mapping)?;
+
+    let slot = Clock::get()?.slot;
+    verify_mapping_references(state_acc, page_accs, &registry, &mapping, slot)?;
+
+    record_mutation(accounts, &mut registry)?;
+    let max_mappings = registry.max_mappings;
+    edit_chain_account(state_acc, page_accs, &mut registry, entry_acc, |header| {
+        replace_mapping(entry_acc, signer_acc, header, &mapping, max_mappings)
+    })?;
+
+    let mapping_bytes = mapping.to_bytes()?;
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::UpdateSources,
+        &mapping.mint,
+        signer_acc.key(),
+        &mapping_bytes[..mapping.serialized_size() as usize],
+    )
+}
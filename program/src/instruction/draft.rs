@@ -0,0 +1,277 @@
+use pinocchio::{
+    account_info::{AccountInfo, MAX_PERMITTED_DATA_INCREASE},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    instruction::{append_audit_record, grow_registry, record_mutation, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct InitializeDraftIxData {
+    pub bump: u8,
+}
+
+impl DataLen for InitializeDraftIxData {
+    const LEN: usize = 1;
+}
+
+impl InitializeDraftIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self { bump: bytes[0] })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.bump]
+    }
+}
+
+/// Instruction data of `Promote`: expected_version(4).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct PromoteIxData {
+    pub expected_version: u32, // Live registry version the draft was based on
+}
+
+impl DataLen for PromoteIxData {
+    const LEN: usize = 4;
+}
+
+impl PromoteIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            expected_version: u32::from_le_bytes(bytes.try_into().unwrap()),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        self.expected_version.to_le_bytes()
+    }
+}
+
+/// Creates the draft PDA (`["Draft", registry]`) as a copy of the live registry, allocation
+/// included. Every instruction that edits a registry also takes the draft in its place. A
+/// registry with continuation pages fails with `RegistryHasPages`, since the draft would miss
+/// their entries.
+pub fn process_initialize_draft(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, draft_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !draft_acc.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = InitializeDraftIxData::from_bytes(data)?;
+
+    let registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+    if registry.page_count > 0 {
+        return Err(MappingProgramError::RegistryHasPages.into());
+    }
+
+    // Validate both PDAs; a draft has no draft of its own
+    ScopeMappingRegistry::validate_pda(
+        registry.bump,
+        state_acc.key(),
+        authority_acc.key(),
+        &crate::ID,
+    )?;
+    ScopeMappingRegistry::validate_draft_pda(
+        ix_data.bump,
+        draft_acc.key(),
+        state_acc.key(),
+        &crate::ID,
+    )?;
+
+    // Signer seeds
+    let pda_bump_bytes = [ix_data.bump];
+    let signer_seeds = [
+        Seed::from(ScopeMappingRegistry::DRAFT_SEED.as_bytes()),
+        Seed::from(state_acc.key().as_ref()),
+        Seed::from(&pda_bump_bytes[..]),
+    ];
+    let signers = [Signer::from(&signer_seeds[..])];
+
+    let space = state_acc.data_len();
+    CreateAccount {
+        from: authority_acc,
+        to: draft_acc,
+        space: space as u64,
+        owner: &crate::ID,
+        lamports: Rent::get()?.minimum_balance(space),
+    }
+    .invoke_signed(&signers)?;
+
+    let mut draft = registry;
+    draft.bump = ix_data.bump;
+    draft.draft_of = *state_acc.key();
+
+    let acc_data = state_acc.try_borrow_data()?;
+    let mut draft_data = draft_acc.try_borrow_mut_data()?;
+    draft_data.copy_from_slice(&acc_data);
+    draft_data[..ScopeMappingRegistry::LEN].copy_from_slice(&draft.to_bytes());
+
+    Ok(())
+}
+
+/// Replaces the live registry with its draft in one step, then closes the draft and returns its
+/// rent to the authority. Fails with `VersionMismatch` when the live registry changed since the
+/// version the caller expects, so edits made to it in the meantime aren't overwritten. The live
+/// rate limit stays in place and counts the promotion, with the guardian co-signing past it.
+/// Only the live entries of the draft are copied. The live registry grows to fit them by at
+/// most one realloc; a larger draft fails with `InsufficientCapacity` until `ReserveCapacity`
+/// made the room. A live registry that gained continuation pages fails with `RegistryHasPages`.
+pub fn process_promote(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, draft_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID || draft_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = PromoteIxData::from_bytes(data)?;
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Validate PDA
+    ScopeMappingRegistry::validate_pda(
+        registry.bump,
+        state_acc.key(),
+        authority_acc.key(),
+        &crate::ID,
+    )?;
+
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if registry.version != ix_data.expected_version {
+        return Err(MappingProgramError::VersionMismatch.into());
+    }
+    if registry.page_count > 0 {
+        return Err(MappingProgramError::RegistryHasPages.into());
+    }
+
+    let draft = ScopeMappingRegistry::from_account_data(&draft_acc.try_borrow_data()?)?;
+    if !draft.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if draft.draft_of() != Some(state_acc.key()) {
+        return Err(MappingProgramError::PdaMismatch.into());
+    }
+    ScopeMappingRegistry::validate_draft_pda(
+        draft.bump,
+        draft_acc.key(),
+        state_acc.key(),
+        &crate::ID,
+    )?;
+
+    // The live registry needs room for the live entries of the draft, in one realloc at most
+    if draft.used_bytes > registry.allocated_bytes {
+        let additional_bytes = draft.used_bytes - registry.allocated_bytes;
+        if additional_bytes as usize > MAX_PERMITTED_DATA_INCREASE {
+            return Err(MappingProgramError::InsufficientCapacity.into());
+        }
+        grow_registry(state_acc, authority_acc, &mut registry, additional_bytes)?;
+    }
+
+    let mut promoted = draft;
+    promoted.bump = registry.bump;
+    promoted.draft_of = [0; 32];
+    promoted.version = registry.version + 1;
+    promoted.allocated_bytes = registry.allocated_bytes;
+    promoted.last_mapping_offset = draft.used_bytes;
+    // The rate limit is the live registry's, so a draft can't lift it, and promoting counts as
+    // one change against it
+    promoted.rate_limit = registry.rate_limit;
+    record_mutation(accounts, &mut promoted)?;
+
+    let end = ScopeMappingRegistry::LEN + promoted.last_mapping_offset as usize;
+    {
+        let draft_data = draft_acc.try_borrow_data()?;
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+
+        // Closed entries are skipped, which compacts the draft on the way; the entries keep
+        // their order, so the Merkle root still holds
+        let mut write = ScopeMappingRegistry::LEN;
+        for (start, mapping_end) in ScopeMappingRegistry::mapping_offsets(&draft_data)? {
+            let next = write + (mapping_end - start);
+            acc_data
+                .get_mut(write..next)
+                .ok_or(ProgramError::InvalidAccountData)?
+                .copy_from_slice(&draft_data[start..mapping_end]);
+            write = next;
+        }
+        if write != end {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&promoted.to_bytes());
+        // Zero out the rest to avoid data leakage
+        acc_data[end..].fill(0);
+    }
+
+    // The draft is spent; its rent goes back to the authority
+    unsafe {
+        *authority_acc.borrow_mut_lamports_unchecked() =
+            authority_acc.lamports() + draft_acc.lamports();
+        *draft_acc.borrow_mut_lamports_unchecked() = 0;
+    }
+    draft_acc.close()?;
+
+    // The record hashes the promoted registry data, like a snapshot's content hash
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::Promote,
+        &[0u8; 32],
+        authority_acc.key(),
+        &state_acc.try_borrow_data()?[..end],
+    )
+}
@@ -0,0 +1,77 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::registry_pages,
+    state::{
+        mint_alias::ALIAS_MULTIPLIER_ONE, mint_mapping::MintMapping,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct GetMappingIxData {
+    pub mint: [u8; 32],
+}
+
+impl DataLen for GetMappingIxData {
+    const LEN: usize = 32;
+}
+
+impl GetMappingIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        let mint = bytes
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        Ok(Self { mint })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        self.mint
+    }
+}
+
+/// Looks up a mint in the registry and returns its serialized entry, in the same format as the
+/// mapping part of the `AddMapping` data, as return data. Aliases are resolved to the entry they
+/// point at, followed by the alias multiplier (u64 LE, 9 decimals) unless it is 1x. Fails with
+/// `MintNotFound` when the registry has no live entry for the mint, and `MappingNotActive` when
+/// the current slot is outside its validity window. Sources of disabled providers are left out.
+/// A registry with continuation pages takes them after the state account and is searched in page
+/// order.
+pub fn process_get_mapping(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [state_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = GetMappingIxData::from_bytes(data)?;
+
+    let acc_data = state_acc.try_borrow_data()?;
+    let registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let slot = Clock::get()?.slot;
+    let (pages, count) = registry_pages(accounts, 1, state_acc, &acc_data)?;
+    let resolved = MintMapping::resolve_pages(&pages[..count], &ix_data.mint, slot)?;
+    let mut return_data = [0u8; MintMapping::MAX_LEN + 8];
+    let mut len = resolved.mapping.serialized_size() as usize;
+    return_data[..MintMapping::MAX_LEN].copy_from_slice(&resolved.mapping.to_bytes()?);
+    if resolved.multiplier != ALIAS_MULTIPLIER_ONE {
+        return_data[len..len + 8].copy_from_slice(&resolved.multiplier.to_le_bytes());
+        len += 8;
+    }
+    set_return_data(&return_data[..len]);
+
+    Ok(())
+}
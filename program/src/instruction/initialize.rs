@@ -8,22 +8,27 @@ use pinocchio::{
 use pinocchio_system::instructions::CreateAccount;
 
 use crate::{
-    instruction::OWNER_PUB_KEY,
+    instruction::{
+        OWNER_PUB_KEY, PYTH_RECEIVER_PROGRAM_ID, SCOPE_PROGRAM_ID, SWITCHBOARD_PROGRAM_ID,
+    },
     state::{
         error::MappingProgramError,
-        scope_mapping_registry::ScopeMappingRegistry,
-        utils::{load_ix_data, DataLen},
+        provider_config::ProviderConfig,
+        scope_mapping_registry::{ScopeMappingRegistry, MAX_MAPPINGS},
+        utils::DataLen,
     },
 };
 
+/// Instruction data of `InitializeState`: bump(1) + max_mappings(4).
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
 pub struct InitializeRegistryIxData {
     pub bump: u8,
+    pub max_mappings: u32, // Live entries the registry and each of its pages can hold, 0 = 512
 }
 
 impl DataLen for InitializeRegistryIxData {
-    const LEN: usize = 1;
+    const LEN: usize = 5;
 }
 
 impl InitializeRegistryIxData {
@@ -31,11 +36,17 @@ impl InitializeRegistryIxData {
         if bytes.len() != Self::LEN {
             return Err(ProgramError::InvalidInstructionData);
         }
-        Ok(Self { bump: bytes[0] })
+        Ok(Self {
+            bump: bytes[0],
+            max_mappings: u32::from_le_bytes(bytes[1..5].try_into().unwrap()),
+        })
     }
 
     pub fn to_bytes(&self) -> [u8; Self::LEN] {
-        [self.bump]
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.bump;
+        bytes[1..5].copy_from_slice(&self.max_mappings.to_le_bytes());
+        bytes
     }
 }
 
@@ -54,7 +65,7 @@ pub fn process_initialize_state(accounts: &[AccountInfo], data: &[u8]) -> Progra
 
     let rent = Rent::from_account_info(sysvar_rent_acc)?;
 
-    let ix_data = unsafe { load_ix_data::<InitializeRegistryIxData>(data)? };
+    let ix_data = InitializeRegistryIxData::from_bytes(data)?;
 
     // Hardcoded authority check
     if payer_acc.key().as_ref() != OWNER_PUB_KEY {
@@ -85,7 +96,18 @@ pub fn process_initialize_state(accounts: &[AccountInfo], data: &[u8]) -> Progra
     .invoke_signed(&signers)?;
 
     // Initialize the account data using the proper method
-    let scope_reg_data = ScopeMappingRegistry::new(*payer_acc.key(), ix_data.bump);
+    let max_mappings = match ix_data.max_mappings {
+        0 => MAX_MAPPINGS,
+        max_mappings => max_mappings,
+    };
+    let mut scope_reg_data =
+        ScopeMappingRegistry::new(*payer_acc.key(), ix_data.bump, max_mappings);
+    scope_reg_data.providers = ProviderConfig {
+        scope_program: SCOPE_PROGRAM_ID,
+        pyth_program: PYTH_RECEIVER_PROGRAM_ID,
+        switchboard_program: SWITCHBOARD_PROGRAM_ID,
+        strict: 1,
+    };
 
     unsafe {
         state_acc
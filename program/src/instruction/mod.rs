@@ -1,13 +1,43 @@
-use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
 
 pub mod add_mapping;
+pub mod audit_log;
 pub mod close;
+pub mod compact;
+pub mod delegate;
+pub mod draft;
+pub mod get_mapping;
 pub mod initialize;
+pub mod page;
+pub mod price_cache;
+pub mod provider_config;
+pub mod prune;
+pub mod quote_conversion;
+pub mod rate_limit;
+pub mod reserve;
+pub mod scope_feed;
+pub mod snapshot;
+pub mod verify;
 
 pub use add_mapping::*;
+pub use audit_log::*;
 pub use close::*;
+pub use compact::*;
+pub use delegate::*;
+pub use draft::*;
+pub use get_mapping::*;
 pub use initialize::*;
+pub use page::*;
 use pinocchio_pubkey::pubkey;
+pub use price_cache::*;
+pub use provider_config::*;
+pub use prune::*;
+pub use quote_conversion::*;
+pub use rate_limit::*;
+pub use reserve::*;
+pub use scope_feed::*;
+pub use snapshot::*;
+pub use verify::*;
 
 #[cfg(feature = "test-owner")]
 const OWNER_PUB_KEY: Pubkey = pubkey!("aaaykznHWqbsb643Uc6xeDgtmng3rh22jNfNYP5YuLq");
@@ -19,6 +49,30 @@ pub enum InstructionSet {
     InitializeState,
     AddMapping,
     CloseMapping,
+    InitializeAuditLog,
+    ReserveCapacity,
+    Compact,
+    SetProviderConfig,
+    GetMapping,
+    InitializePriceCache,
+    RefreshPrices,
+    SetQuoteConversion,
+    PruneExpired,
+    SetDelegate,
+    UpdateSources,
+    SetRateLimit,
+    SetDisabledProviders,
+    Snapshot,
+    InitializeDraft,
+    Promote,
+    AddPage,
+    SetScopeFeed,
+}
+
+/// Optional accounts are either left off the end of the account list or passed as the
+/// program id to keep the positions of the accounts after them.
+pub(crate) fn optional_account(accounts: &[AccountInfo], index: usize) -> Option<&AccountInfo> {
+    accounts.get(index).filter(|acc| acc.key() != &crate::ID)
 }
 
 pub trait IntoBytes {
@@ -34,13 +88,38 @@ impl TryFrom<&u8> for InstructionSet {
             0 => Ok(InstructionSet::InitializeState),
             1 => Ok(InstructionSet::AddMapping),
             2 => Ok(InstructionSet::CloseMapping),
+            3 => Ok(InstructionSet::InitializeAuditLog),
+            4 => Ok(InstructionSet::ReserveCapacity),
+            5 => Ok(InstructionSet::Compact),
+            6 => Ok(InstructionSet::SetProviderConfig),
+            7 => Ok(InstructionSet::GetMapping),
+            8 => Ok(InstructionSet::InitializePriceCache),
+            9 => Ok(InstructionSet::RefreshPrices),
+            10 => Ok(InstructionSet::SetQuoteConversion),
+            11 => Ok(InstructionSet::PruneExpired),
+            12 => Ok(InstructionSet::SetDelegate),
+            13 => Ok(InstructionSet::UpdateSources),
+            14 => Ok(InstructionSet::SetRateLimit),
+            15 => Ok(InstructionSet::SetDisabledProviders),
+            16 => Ok(InstructionSet::Snapshot),
+            17 => Ok(InstructionSet::InitializeDraft),
+            18 => Ok(InstructionSet::Promote),
+            19 => Ok(InstructionSet::AddPage),
+            20 => Ok(InstructionSet::SetScopeFeed),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
 }
 
 mod idl_gen {
-    use super::InitializeRegistryIxData;
+    use super::{
+        AddMappingIxData, AddPageIxData, CloseMappingIxData, CompactIxData, GetMappingIxData,
+        InitializeAuditLogIxData, InitializeDraftIxData, InitializePriceCacheIxData,
+        InitializeRegistryIxData, PromoteIxData, PruneExpiredIxData, RefreshPricesIxData,
+        ReserveCapacityIxData, SetDelegateIxData, SetDisabledProvidersIxData,
+        SetProviderConfigIxData, SetQuoteConversionIxData, SetRateLimitIxData, SetScopeFeedIxData,
+        SnapshotIxData, UpdateSourcesIxData,
+    };
 
     #[derive(shank::ShankInstruction)]
     enum _InstructionSet {
@@ -49,11 +128,226 @@ mod idl_gen {
         #[account(2, name = "sysvar_rent_acc", desc = "Sysvar rent account")]
         #[account(3, name = "system_program_acc", desc = "System program account")]
         InitializeState(InitializeRegistryIxData),
+        #[account(
+            0,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority, pays realloc rent without a funder if writable"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        #[account(
+            4,
+            optional,
+            writable,
+            signer,
+            name = "funder_acc",
+            desc = "Pays realloc rent instead of the authority"
+        )]
+        #[account(
+            5,
+            optional,
+            name = "mint_acc",
+            desc = "Mint account to check the mapping decimals against"
+        )]
+        /// Followed by the continuation pages of the registry, in page order, then one optional
+        /// oracle account per account-based source of the mapping, in
+        /// `MintMapping::oracle_sources` order; Scope sources take the registry feed they read
+        AddMapping(AddMappingIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(
+            3,
+            optional,
+            writable,
+            name = "refund_acc",
+            desc = "Receives the closed entry's rent right away instead of leaving it to Compact"
+        )]
+        /// Followed by the continuation pages of the registry, in page order
+        CloseMapping(CloseMappingIxData),
         #[account(0, writable, signer, name = "payer_acc", desc = "Fee payer account")]
+        #[account(1, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "New audit log account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        InitializeAuditLog(InitializeAuditLogIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        #[account(
+            4,
+            optional,
+            writable,
+            signer,
+            name = "funder_acc",
+            desc = "Pays realloc rent instead of the authority"
+        )]
+        ReserveCapacity(ReserveCapacityIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority"
+        )]
         #[account(1, writable, name = "state_acc", desc = "State account")]
-        AddMapping,
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(
+            3,
+            optional,
+            writable,
+            name = "refund_acc",
+            desc = "Receives freed rent instead of the authority"
+        )]
+        Compact(CompactIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        SetProviderConfig(SetProviderConfigIxData),
+        #[account(0, name = "state_acc", desc = "State account")]
+        /// Returns the serialized entry of the mint as return data, or fails with `MintNotFound`.
+        /// Followed by the continuation pages of the registry, in page order
+        GetMapping(GetMappingIxData),
         #[account(0, writable, signer, name = "payer_acc", desc = "Fee payer account")]
+        #[account(1, name = "state_acc", desc = "State account")]
+        #[account(
+            2,
+            writable,
+            name = "price_cache_acc",
+            desc = "New price cache account"
+        )]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        InitializePriceCache(InitializePriceCacheIxData),
+        #[account(0, name = "state_acc", desc = "State account")]
+        #[account(1, writable, name = "price_cache_acc", desc = "Price cache account")]
+        /// Followed by the continuation pages of the registry, in page order, then one optional
+        /// oracle account per source of each mint, in `MintMapping::oracle_sources` order
+        RefreshPrices(RefreshPricesIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        /// Followed by the continuation pages of the registry, in page order
+        SetQuoteConversion(SetQuoteConversionIxData),
+        #[account(0, writable, name = "state_acc", desc = "State account")]
+        #[account(1, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(
+            2,
+            writable,
+            name = "authority_acc",
+            desc = "Registry authority, receives the freed rent"
+        )]
+        /// Followed by the continuation pages of the registry, in page order
+        PruneExpired(PruneExpiredIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        /// Followed by the continuation pages of the registry, in page order
+        SetDelegate(SetDelegateIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "signer_acc",
+            desc = "Registry authority or delegate of the mint, pays realloc rent"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        /// Followed by the continuation pages of the registry, in page order, then one optional
+        /// oracle account per account-based source of the mapping, in
+        /// `MintMapping::oracle_sources` order; Scope sources take the registry feed they read
+        UpdateSources(UpdateSourcesIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(
+            3,
+            optional,
+            signer,
+            name = "guardian_acc",
+            desc = "Current guardian, required once one is set"
+        )]
+        SetRateLimit(SetRateLimitIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        SetDisabledProviders(SetDisabledProvidersIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority, pays the snapshot rent"
+        )]
+        #[account(1, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "snapshot_acc", desc = "New snapshot account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        Snapshot(SnapshotIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority, pays the draft rent"
+        )]
+        #[account(1, name = "state_acc", desc = "Live state account")]
+        #[account(2, writable, name = "draft_acc", desc = "New draft account")]
+        #[account(3, name = "system_program_acc", desc = "System program account")]
+        InitializeDraft(InitializeDraftIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "Live state account")]
+        #[account(2, writable, name = "draft_acc", desc = "Draft account, closed")]
+        #[account(
+            3,
+            writable,
+            name = "audit_log_acc",
+            desc = "Audit log account of the live registry"
+        )]
+        #[account(4, name = "system_program_acc", desc = "System program account")]
+        Promote(PromoteIxData),
+        #[account(
+            0,
+            writable,
+            signer,
+            name = "authority_acc",
+            desc = "Registry authority, pays the page rent"
+        )]
+        #[account(1, writable, name = "state_acc", desc = "State account")]
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(3, writable, name = "page_acc", desc = "New continuation page account")]
+        #[account(4, name = "system_program_acc", desc = "System program account")]
+        /// Followed by the existing continuation pages of the registry, in page order
+        AddPage(AddPageIxData),
+        #[account(0, signer, name = "authority_acc", desc = "Registry authority")]
         #[account(1, writable, name = "state_acc", desc = "State account")]
-        CloseMapping,
+        #[account(2, writable, name = "audit_log_acc", desc = "Audit log account")]
+        #[account(
+            3,
+            optional,
+            name = "feed_acc",
+            desc = "Scope OraclePrices account, required unless clearing the feed"
+        )]
+        SetScopeFeed(SetScopeFeedIxData),
     }
 }
@@ -0,0 +1,287 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    instruction::{append_audit_record, record_mutation, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind,
+        error::MappingProgramError,
+        merkle::pages_root,
+        mint_mapping::MintMapping,
+        scope_mapping_registry::{ScopeMappingRegistry, MAX_REGISTRY_PAGES},
+        utils::DataLen,
+        Initialized,
+    },
+};
+
+/// Account data of a registry followed by up to `MAX_REGISTRY_PAGES` continuation pages.
+pub(crate) type RegistryPages<'a> = [&'a [u8]; MAX_REGISTRY_PAGES as usize + 1];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct AddPageIxData {
+    pub bump: u8,
+}
+
+impl DataLen for AddPageIxData {
+    const LEN: usize = 1;
+}
+
+impl AddPageIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self { bump: bytes[0] })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.bump]
+    }
+}
+
+/// Continuation pages of the registry at `state_acc`, which follow in `accounts` from
+/// `first_page` in page order. Pages are checked against their PDA, so none of them aliases
+/// another account of the instruction.
+pub(crate) fn registry_page_accounts<'a>(
+    accounts: &'a [AccountInfo],
+    first_page: usize,
+    state_acc: &AccountInfo,
+    registry: &ScopeMappingRegistry,
+) -> Result<&'a [AccountInfo], ProgramError> {
+    if registry.page_count > MAX_REGISTRY_PAGES {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let page_accs = accounts
+        .get(first_page..first_page + registry.page_count as usize)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    for (page_acc, index) in page_accs.iter().zip(1..) {
+        if page_acc.owner() != &crate::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let page = ScopeMappingRegistry::from_account_data(&page_acc.try_borrow_data()?)?;
+        if page.page_of() != Some(state_acc.key()) || page.page_index != index {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        ScopeMappingRegistry::validate_page_pda(
+            page.bump,
+            page_acc.key(),
+            state_acc.key(),
+            index,
+            &crate::ID,
+        )?;
+    }
+    Ok(page_accs)
+}
+
+/// `registry_data` followed by the account data of `page_accs`, along with how many of the
+/// returned slots are filled.
+pub(crate) fn chain_data<'a>(
+    registry_data: &'a [u8],
+    page_accs: &'a [AccountInfo],
+) -> (RegistryPages<'a>, usize) {
+    let mut pages: RegistryPages = [&[]; MAX_REGISTRY_PAGES as usize + 1];
+    pages[0] = registry_data;
+    for (data, page_acc) in pages[1..].iter_mut().zip(page_accs) {
+        // Pages are only read while the returned slices are in use
+        *data = unsafe { page_acc.borrow_data_unchecked() };
+    }
+    (pages, page_accs.len() + 1)
+}
+
+/// `registry_data` and the account data of its continuation pages, which follow in `accounts`
+/// from `first_page` in page order, along with how many of the returned slots are filled.
+pub(crate) fn registry_pages<'a>(
+    accounts: &'a [AccountInfo],
+    first_page: usize,
+    state_acc: &AccountInfo,
+    registry_data: &'a [u8],
+) -> Result<(RegistryPages<'a>, usize), ProgramError> {
+    let registry = ScopeMappingRegistry::from_account_data(registry_data)?;
+    let page_accs = registry_page_accounts(accounts, first_page, state_acc, &registry)?;
+    Ok(chain_data(registry_data, page_accs))
+}
+
+/// Recomputes the `pages_root` of `registry` from the headers of its `page_accs`.
+pub(crate) fn refresh_pages_root(
+    registry: &mut ScopeMappingRegistry,
+    page_accs: &[AccountInfo],
+) -> ProgramResult {
+    let (pages, count) = chain_data(&[], page_accs);
+    registry.pages_root = pages_root(&pages[1..count])?;
+    Ok(())
+}
+
+/// Account of the chain, the registry at `state_acc` or one of its `page_accs`, that holds the
+/// entry of `mint`.
+pub(crate) fn chain_account_of<'a>(
+    state_acc: &'a AccountInfo,
+    page_accs: &'a [AccountInfo],
+    mint: &[u8; 32],
+) -> Result<&'a AccountInfo, ProgramError> {
+    for acc in core::iter::once(state_acc).chain(page_accs) {
+        match MintMapping::get_mapping_offset(&acc.try_borrow_data()?, mint) {
+            Ok(_) => return Ok(acc),
+            Err(MappingProgramError::MintNotFound) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(MappingProgramError::MintNotFound.into())
+}
+
+/// First account of the chain with room for another live entry under the entry cap of
+/// `registry`, the header of `state_acc`.
+pub(crate) fn chain_account_with_room<'a>(
+    state_acc: &'a AccountInfo,
+    page_accs: &'a [AccountInfo],
+    registry: &ScopeMappingRegistry,
+) -> Result<&'a AccountInfo, ProgramError> {
+    if registry.total_mappings < registry.max_mappings {
+        return Ok(state_acc);
+    }
+    for page_acc in page_accs {
+        let page = ScopeMappingRegistry::from_account_data(&page_acc.try_borrow_data()?)?;
+        if page.total_mappings < registry.max_mappings {
+            return Ok(page_acc);
+        }
+    }
+    Err(MappingProgramError::MaxMappingsReached.into())
+}
+
+/// Runs `edit` on the header of `entry_acc`, an account of the chain of the registry at
+/// `state_acc` and its `page_accs`, and has the registry header stored. `edit` writes the header
+/// it is given; for an edit to a page, the registry header, whose rate limit counted the change,
+/// is written here with its version bumped and its `pages_root` refreshed.
+pub(crate) fn edit_chain_account(
+    state_acc: &AccountInfo,
+    page_accs: &[AccountInfo],
+    registry: &mut ScopeMappingRegistry,
+    entry_acc: &AccountInfo,
+    edit: impl FnOnce(&mut ScopeMappingRegistry) -> ProgramResult,
+) -> ProgramResult {
+    if entry_acc.key() == state_acc.key() {
+        return edit(registry);
+    }
+
+    let mut page = ScopeMappingRegistry::from_account_data(&entry_acc.try_borrow_data()?)?;
+    edit(&mut page)?;
+    registry.version += 1;
+    refresh_pages_root(registry, page_accs)?;
+    state_acc.try_borrow_mut_data()?[..ScopeMappingRegistry::LEN]
+        .copy_from_slice(&registry.to_bytes());
+    Ok(())
+}
+
+/// Chains a new continuation page (`["RegistryPage", registry, index]`) onto the registry,
+/// numbered after the last one, which follow the system program in page order. The page starts
+/// without entries and holds only its own bookkeeping; entries land on it through the registry
+/// once the accounts before it are full. Adding a page counts against the rate limit of the
+/// registry.
+pub fn process_add_page(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, page_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !page_acc.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = AddPageIxData::from_bytes(data)?;
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+    if registry.page_count >= MAX_REGISTRY_PAGES {
+        return Err(MappingProgramError::MaxPagesReached.into());
+    }
+
+    // Validate both PDAs; only a live registry chains into pages
+    ScopeMappingRegistry::validate_pda(
+        registry.bump,
+        state_acc.key(),
+        authority_acc.key(),
+        &crate::ID,
+    )?;
+    let page = registry.next_page(*state_acc.key(), ix_data.bump);
+    ScopeMappingRegistry::validate_page_pda(
+        ix_data.bump,
+        page_acc.key(),
+        state_acc.key(),
+        page.page_index,
+        &crate::ID,
+    )?;
+
+    // The existing pages follow the system program, so the registry can commit to all of them
+    let page_accs = registry_page_accounts(accounts, 5, state_acc, &registry)?;
+
+    record_mutation(accounts, &mut registry)?;
+
+    // Signer seeds
+    let index_bytes = page.page_index.to_le_bytes();
+    let pda_bump_bytes = [ix_data.bump];
+    let signer_seeds = [
+        Seed::from(ScopeMappingRegistry::PAGE_SEED.as_bytes()),
+        Seed::from(state_acc.key().as_ref()),
+        Seed::from(&index_bytes[..]),
+        Seed::from(&pda_bump_bytes[..]),
+    ];
+    let signers = [Signer::from(&signer_seeds[..])];
+
+    CreateAccount {
+        from: authority_acc,
+        to: page_acc,
+        space: ScopeMappingRegistry::LEN as u64,
+        owner: &crate::ID,
+        lamports: Rent::get()?.minimum_balance(ScopeMappingRegistry::LEN),
+    }
+    .invoke_signed(&signers)?;
+
+    page_acc
+        .try_borrow_mut_data()?
+        .copy_from_slice(&page.to_bytes());
+
+    registry.page_count = page.page_index;
+    registry.version += 1;
+    {
+        let (mut pages, count) = chain_data(&[], page_accs);
+        let page_data = page_acc.try_borrow_data()?;
+        pages[count] = &page_data;
+        registry.pages_root = pages_root(&pages[1..=count])?;
+    }
+    state_acc.try_borrow_mut_data()?[..ScopeMappingRegistry::LEN]
+        .copy_from_slice(&registry.to_bytes());
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::AddPage,
+        &[0u8; 32],
+        authority_acc.key(),
+        &index_bytes,
+    )
+}
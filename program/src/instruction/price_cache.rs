@@ -0,0 +1,394 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    instruction::{optional_account, registry_pages, verify_oracle_owner, OWNER_PUB_KEY},
+    state::{
+        error::MappingProgramError,
+        mint_alias::apply_multiplier,
+        mint_mapping::MintMapping,
+        price::{
+            aggregate, parse_pyth_selected_price, parse_scope_chain, parse_stake_pool_rate,
+            parse_switchboard_price, to_usd, Price, MAX_AGGREGATED_SOURCES,
+        },
+        price_cache::{CachedPrice, PriceCache},
+        scope_mapping_registry::ScopeMappingRegistry,
+        source_type::{SourceEntry, SourceType},
+        utils::{load_ix_data, DataLen},
+        Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct InitializePriceCacheIxData {
+    pub bump: u8,
+}
+
+impl DataLen for InitializePriceCacheIxData {
+    const LEN: usize = 1;
+}
+
+impl InitializePriceCacheIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self { bump: bytes[0] })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.bump]
+    }
+}
+
+/// Instruction data of `RefreshPrices`: count(1) + count * mint(32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct RefreshPricesIxData {
+    pub count: u8,
+    pub mints: [[u8; 32]; 16],
+}
+
+impl RefreshPricesIxData {
+    pub const MAX_MINTS: usize = 16;
+    pub const MAX_LEN: usize = 1 + Self::MAX_MINTS * 32;
+
+    pub fn new(mints: &[[u8; 32]]) -> Result<Self, ProgramError> {
+        if mints.is_empty() || mints.len() > Self::MAX_MINTS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut ix_data = Self {
+            count: mints.len() as u8,
+            mints: [[0u8; 32]; 16],
+        };
+        ix_data.mints[..mints.len()].copy_from_slice(mints);
+        Ok(ix_data)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        let Some((&count, mint_bytes)) = bytes.split_first() else {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+        let count = count as usize;
+        if count == 0 || count > Self::MAX_MINTS || mint_bytes.len() != count * 32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut ix_data = Self {
+            count: count as u8,
+            mints: [[0u8; 32]; 16],
+        };
+        for (mint, bytes) in ix_data.mints.iter_mut().zip(mint_bytes.chunks_exact(32)) {
+            mint.copy_from_slice(bytes);
+        }
+        Ok(ix_data)
+    }
+
+    /// Only the first `serialized_size()` bytes are used.
+    pub fn to_bytes(&self) -> [u8; Self::MAX_LEN] {
+        let mut bytes = [0u8; Self::MAX_LEN];
+        bytes[0] = self.count;
+        for (chunk, mint) in bytes[1..].chunks_exact_mut(32).zip(self.mints()) {
+            chunk.copy_from_slice(mint);
+        }
+        bytes
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.mints().len() * 32
+    }
+
+    pub fn mints(&self) -> &[[u8; 32]] {
+        &self.mints[..(self.count as usize).min(Self::MAX_MINTS)]
+    }
+}
+
+pub fn process_initialize_price_cache(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [payer_acc, state_acc, price_cache_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Hardcoded authority check
+    if payer_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !price_cache_acc.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if registry.owner.ne(payer_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    let ix_data = unsafe { load_ix_data::<InitializePriceCacheIxData>(data)? };
+
+    // Validate both PDAs
+    ScopeMappingRegistry::validate_pda(
+        registry.bump,
+        state_acc.key(),
+        payer_acc.key(),
+        &crate::ID,
+    )?;
+    PriceCache::validate_pda(
+        ix_data.bump,
+        price_cache_acc.key(),
+        state_acc.key(),
+        &crate::ID,
+    )?;
+
+    // Signer seeds
+    let pda_bump_bytes = [ix_data.bump];
+    let signer_seeds = [
+        Seed::from(PriceCache::SEED.as_bytes()),
+        Seed::from(state_acc.key().as_ref()),
+        Seed::from(&pda_bump_bytes[..]),
+    ];
+    let signers = [Signer::from(&signer_seeds[..])];
+
+    let space = PriceCache::account_size();
+    CreateAccount {
+        from: payer_acc,
+        to: price_cache_acc,
+        space: space as u64,
+        owner: &crate::ID,
+        lamports: Rent::get()?.minimum_balance(space),
+    }
+    .invoke_signed(&signers)?;
+
+    let price_cache = PriceCache::new(*state_acc.key(), ix_data.bump);
+
+    unsafe {
+        price_cache_acc.borrow_mut_data_unchecked()[..PriceCache::LEN]
+            .copy_from_slice(&price_cache.to_bytes());
+    }
+
+    Ok(())
+}
+
+/// Reads the price of one source of `mapping` from its oracle account, `None` for source types
+/// without a parser. Every account is tied to the mapping: account-based sources must be passed
+/// the account they store, and Scope sources the registry feed the mapping references. Owners are
+/// checked against the provider config, and a source type whose program is unset is rejected
+/// rather than read from an account of any owner. Pyth sources read the price picked by the
+/// mapping. Stake pool rates are multiplied by `quote`, and are unavailable without it.
+pub(crate) fn read_source_price(
+    oracle_acc: &AccountInfo,
+    source: &SourceEntry,
+    registry: &ScopeMappingRegistry,
+    mapping: &MintMapping,
+    quote: Option<Price>,
+) -> Result<Option<Price>, ProgramError> {
+    let Some(source_type) = source
+        .source_type()
+        .filter(|source_type| is_priced(*source_type))
+    else {
+        return Ok(None);
+    };
+
+    let expected = match source_type {
+        SourceType::Scope => registry
+            .scope_feed(mapping.scope_feed())
+            .ok_or(MappingProgramError::ScopeFeedNotSet)?,
+        _ => source
+            .oracle_account()
+            .ok_or(MappingProgramError::InvalidOracleAccount)?,
+    };
+    if oracle_acc.key() != &expected {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    if source_type != SourceType::StakePool
+        && registry.providers.expected_owner(source_type).is_none()
+    {
+        return Err(MappingProgramError::OracleOwnerMismatch.into());
+    }
+    verify_oracle_owner(oracle_acc, source_type, &registry.providers)?;
+
+    let data = oracle_acc.try_borrow_data()?;
+    let price = match source_type {
+        SourceType::Scope => {
+            let chain = source
+                .scope_chain()
+                .ok_or(MappingProgramError::InvalidMappingSize)?;
+            parse_scope_chain(&data, &chain)?
+        }
+        SourceType::Pyth => parse_pyth_selected_price(&data, mapping.price_selection())?,
+        SourceType::Switchboard => parse_switchboard_price(&data)?,
+        SourceType::StakePool => {
+            let quote = quote.ok_or(MappingProgramError::PriceUnavailable)?;
+            parse_stake_pool_rate(&data)?
+                .mul(&quote)
+                .ok_or(MappingProgramError::InvalidOracleAccount)?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(price))
+}
+
+/// Whether `RefreshPrices` has a parser for sources of `source_type`.
+fn is_priced(source_type: SourceType) -> bool {
+    matches!(
+        source_type,
+        SourceType::Scope | SourceType::Pyth | SourceType::Switchboard | SourceType::StakePool
+    )
+}
+
+/// The price cached for `mint` in `slot`, so nothing is priced off a stale quote.
+fn price_cached_in_slot(cache_data: &[u8], mint: &[u8; 32], slot: u64) -> Option<Price> {
+    PriceCache::get_price(cache_data, mint)
+        .ok()
+        .filter(|cached| cached.slot == slot)
+        .map(|cached| cached.price)
+}
+
+/// Evaluates the price of one mint from the oracle accounts starting at `*next_account`, which
+/// is advanced past the accounts taken. The aggregate is in the quote currency of the mapping
+/// and converted to USD with the price cached for the registry's conversion mint in `slot`.
+fn evaluate_price(
+    accounts: &[AccountInfo],
+    next_account: &mut usize,
+    registry: &ScopeMappingRegistry,
+    pages: &[&[u8]],
+    cache_data: &[u8],
+    mint: &[u8; 32],
+    slot: u64,
+) -> Result<CachedPrice, ProgramError> {
+    let resolved = MintMapping::resolve_pages(pages, mint, slot)?;
+
+    let mut source_prices = [None; MintMapping::MAX_SOURCES];
+    for (position, source) in resolved.mapping.oracle_sources().enumerate() {
+        // Fixed prices read no account
+        if source.source_type() == Some(SourceType::Fixed) {
+            continue;
+        }
+        let index = *next_account;
+        *next_account += 1;
+        // Every priced source is read, so a crank can't leave out the ones it dislikes
+        let Some(oracle_acc) = optional_account(accounts, index) else {
+            if source.source_type().is_some_and(is_priced) {
+                return Err(MappingProgramError::InvalidOracleAccount.into());
+            }
+            continue;
+        };
+        let quote = source
+            .quote_mint()
+            .and_then(|quote_mint| price_cached_in_slot(cache_data, &quote_mint, slot));
+        source_prices[position] =
+            read_source_price(oracle_acc, &source, registry, &resolved.mapping, quote)?;
+    }
+
+    // Fixed prices count while their reference source is within the guard band
+    let mut prices = [Price::default(); MAX_AGGREGATED_SOURCES];
+    let mut count = 0;
+    for (position, source) in resolved.mapping.oracle_sources().enumerate() {
+        let price = match source.fixed_price() {
+            Some(fixed) => fixed.guarded_price(
+                fixed
+                    .guard()
+                    .and_then(|(reference, _)| source_prices.get(reference as usize)?.as_ref()),
+            ),
+            None => source_prices[position],
+        };
+        if let Some(price) = price.filter(|_| count < MAX_AGGREGATED_SOURCES) {
+            prices[count] = price;
+            count += 1;
+        }
+    }
+
+    let native = aggregate(&prices[..count])
+        .and_then(|price| apply_multiplier(&price, resolved.multiplier))
+        .ok_or(MappingProgramError::PriceUnavailable)?;
+
+    let quote = resolved.mapping.quote_currency();
+    let conversion = registry
+        .conversion_mint(quote)
+        .and_then(|conversion_mint| price_cached_in_slot(cache_data, &conversion_mint, slot));
+    let price =
+        to_usd(&native, quote, conversion.as_ref()).ok_or(MappingProgramError::PriceUnavailable)?;
+
+    Ok(CachedPrice {
+        mint: *mint,
+        price,
+        slot,
+        native,
+        quote,
+    })
+}
+
+/// Permissionless crank. For each mint in the instruction data, takes one oracle account per
+/// non-fixed source of its mapping, in `MintMapping::oracle_sources` order, and caches the
+/// aggregate of the prices read. Aliases take the sources of the mapping they resolve to and
+/// cache its aggregate scaled by their multiplier. Fixed prices take no account and only count
+/// while the source their guard band references is within it. Stake pool sources use the price
+/// cached for their quote mint in the current slot, and prices quoted in SOL the price cached
+/// for the registry's SOL/USD mint, so list those mints first. Every source with a parser must be
+/// passed its own account and produce a price; only sources without one may be passed as the
+/// program id. A registry with continuation pages takes them after the price cache, in page
+/// order, ahead of the oracle accounts.
+pub fn process_refresh_prices(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [state_acc, price_cache_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if state_acc.owner() != &crate::ID || price_cache_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = RefreshPricesIxData::from_bytes(data)?;
+
+    let registry_data = state_acc.try_borrow_data()?;
+    let registry = ScopeMappingRegistry::from_account_data(&registry_data)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let mut cache_data = price_cache_acc.try_borrow_mut_data()?;
+    let mut cache = PriceCache::from_account_data(&cache_data)?;
+    if cache.registry.ne(state_acc.key()) {
+        return Err(MappingProgramError::PdaMismatch.into());
+    }
+    PriceCache::validate_pda(
+        cache.bump,
+        price_cache_acc.key(),
+        state_acc.key(),
+        &crate::ID,
+    )?;
+
+    // Continuation pages come first, then the oracle accounts
+    let (pages, count) = registry_pages(accounts, 2, state_acc, &registry_data)?;
+
+    let slot = Clock::get()?.slot;
+    let mut next_account = 2 + registry.page_count as usize;
+    for mint in ix_data.mints() {
+        let cached = evaluate_price(
+            accounts,
+            &mut next_account,
+            &registry,
+            &pages[..count],
+            &cache_data,
+            mint,
+            slot,
+        )?;
+        cache.upsert(&mut cache_data, &cached)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{
+    instruction::{append_audit_record, record_mutation, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError, mint_mapping::MAPPING_PROVIDERS,
+        provider_config::ProviderConfig, scope_mapping_registry::ScopeMappingRegistry,
+        utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetProviderConfigIxData {
+    pub providers: ProviderConfig,
+}
+
+impl DataLen for SetProviderConfigIxData {
+    const LEN: usize = ProviderConfig::LEN;
+}
+
+impl SetProviderConfigIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            providers: ProviderConfig::from_bytes(bytes)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        self.providers.to_bytes()
+    }
+}
+
+/// Instruction data of `SetDisabledProviders`: the new mask, `MAPPING_PROVIDERS` bits only.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetDisabledProvidersIxData {
+    pub disabled_providers: u8, // 0b001 scope, 0b010 pyth, 0b100 switchboard
+}
+
+impl DataLen for SetDisabledProvidersIxData {
+    const LEN: usize = 1;
+}
+
+impl SetDisabledProvidersIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        match bytes {
+            [disabled_providers] if disabled_providers & !MAPPING_PROVIDERS == 0 => Ok(Self {
+                disabled_providers: *disabled_providers,
+            }),
+            _ => Err(MappingProgramError::InvalidInstructionData.into()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.disabled_providers]
+    }
+}
+
+pub fn process_set_provider_config(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetProviderConfigIxData::from_bytes(data)?;
+
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+
+        record_mutation(accounts, &mut registry)?;
+        registry.providers = ix_data.providers;
+        registry.version += 1;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetProviderConfig,
+        &[0u8; 32],
+        authority_acc.key(),
+        &ix_data.to_bytes(),
+    )
+}
+
+/// Kill switch for whole providers: lookups and price aggregation treat the sources of disabled
+/// providers as absent, without rewriting any mapping.
+pub fn process_set_disabled_providers(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetDisabledProvidersIxData::from_bytes(data)?;
+
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+
+        record_mutation(accounts, &mut registry)?;
+        registry.disabled_providers = ix_data.disabled_providers;
+        registry.version += 1;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetDisabledProviders,
+        &[0u8; 32],
+        authority_acc.key(),
+        &ix_data.to_bytes(),
+    )
+}
@@ -0,0 +1,147 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::{append_audit_record, refresh_pages_root, registry_page_accounts},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct PruneExpiredIxData {
+    pub max_entries: u8, // At most `MAX_ENTRIES` entries are pruned per call
+}
+
+impl DataLen for PruneExpiredIxData {
+    const LEN: usize = 1;
+}
+
+impl PruneExpiredIxData {
+    pub const MAX_ENTRIES: usize = 8;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+        if bytes[0] == 0 || bytes[0] as usize > Self::MAX_ENTRIES {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+        Ok(Self {
+            max_entries: bytes[0],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        [self.max_entries]
+    }
+}
+
+/// Permissionless crank that removes mappings whose validity window has ended, from the
+/// registry and then its continuation pages, which follow the authority account in page order.
+/// Each account is compacted and shrunk by the bytes its pruned entries held, and the freed rent
+/// goes to the registry authority. Pruning entries of a page bumps the registry version and
+/// refreshes its `pages_root`. Each pruned mint gets a `PruneExpired` audit record.
+pub fn process_prune_expired(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [state_acc, audit_log_acc, authority_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = PruneExpiredIxData::from_bytes(data)?;
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // The refund can only go to the authority the registry was derived from
+    registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    let page_accs = registry_page_accounts(accounts, 3, state_acc, &registry)?;
+
+    let slot = Clock::get()?.slot;
+    let mut mints = [[0u8; 32]; PruneExpiredIxData::MAX_ENTRIES];
+    let mut count = 0;
+    let mut pages_pruned = false;
+    for acc in core::iter::once(state_acc).chain(page_accs) {
+        if count == ix_data.max_entries as usize {
+            break;
+        }
+
+        let new_size;
+        {
+            let mut acc_data = acc.try_borrow_mut_data()?;
+            let mut header = ScopeMappingRegistry::from_account_data(&acc_data)?;
+            let (pruned, freed_bytes) = header.close_expired(
+                &mut acc_data,
+                slot,
+                &mut mints[count..ix_data.max_entries as usize],
+            )?;
+            if pruned == 0 {
+                continue;
+            }
+            count += pruned;
+
+            let used_bytes = header.compact_mappings(&mut acc_data)?;
+            header.allocated_bytes = header
+                .allocated_bytes
+                .saturating_sub(freed_bytes)
+                .max(used_bytes);
+            new_size = ScopeMappingRegistry::LEN + header.allocated_bytes as usize;
+
+            // Writes the header along with the new root
+            header.refresh_root(&mut acc_data)?;
+            if acc.key() == state_acc.key() {
+                registry = header;
+            } else {
+                pages_pruned = true;
+            }
+        }
+
+        let min_balance = Rent::get()?.minimum_balance(new_size);
+        let current_balance = acc.lamports();
+        let excess = current_balance.saturating_sub(min_balance);
+
+        if excess > 0 {
+            unsafe {
+                *acc.borrow_mut_lamports_unchecked() = current_balance - excess;
+                *authority_acc.borrow_mut_lamports_unchecked() = authority_acc.lamports() + excess;
+            }
+        }
+
+        acc.resize(new_size)?;
+    }
+
+    if pages_pruned {
+        registry.version += 1;
+        refresh_pages_root(&mut registry, page_accs)?;
+        state_acc.try_borrow_mut_data()?[..ScopeMappingRegistry::LEN]
+            .copy_from_slice(&registry.to_bytes());
+    }
+
+    // No signer: the crank can be run by anyone
+    for mint in &mints[..count] {
+        append_audit_record(
+            audit_log_acc,
+            state_acc.key(),
+            AuditKind::PruneExpired,
+            mint,
+            &[0u8; 32],
+            &[],
+        )?;
+    }
+    Ok(())
+}
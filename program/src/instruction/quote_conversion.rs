@@ -0,0 +1,111 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::{append_audit_record, record_mutation, registry_pages, OWNER_PUB_KEY},
+    state::{
+        attributes::QuoteCurrency, audit_log::AuditKind, error::MappingProgramError,
+        mint_mapping::MintMapping, scope_mapping_registry::ScopeMappingRegistry, utils::DataLen,
+        Initialized,
+    },
+};
+
+/// Instruction data of `SetQuoteConversion`: quote(1) + mint(32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetQuoteConversionIxData {
+    pub quote: QuoteCurrency,
+    pub mint: [u8; 32], // All-zero clears the conversion
+}
+
+impl DataLen for SetQuoteConversionIxData {
+    const LEN: usize = 33;
+}
+
+impl SetQuoteConversionIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut ix_data = Self {
+            quote: QuoteCurrency::try_from(bytes[0])
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+            mint: [0; 32],
+        };
+        ix_data.mint.copy_from_slice(&bytes[1..33]);
+        Ok(ix_data)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.quote as u8;
+        bytes[1..33].copy_from_slice(&self.mint);
+        bytes
+    }
+}
+
+/// Names the mint whose USD price converts mappings quoted in another currency. The mint must
+/// resolve to a mapping quoted in USD itself, held by the registry or one of its continuation
+/// pages, which follow the audit log account in page order.
+pub fn process_set_quote_conversion(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetQuoteConversionIxData::from_bytes(data)?;
+
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+
+        // A conversion quoted in anything but USD would need converting itself
+        if ix_data.mint != [0u8; 32] {
+            let slot = Clock::get()?.slot;
+            let (pages, count) = registry_pages(accounts, 3, state_acc, &acc_data)?;
+            let resolved = MintMapping::resolve_pages(&pages[..count], &ix_data.mint, slot)?;
+            if resolved.mapping.quote_currency() != QuoteCurrency::Usd {
+                return Err(MappingProgramError::InvalidQuoteConversion.into());
+            }
+        }
+
+        record_mutation(accounts, &mut registry)?;
+        registry.set_conversion_mint(ix_data.quote, ix_data.mint)?;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetQuoteConversion,
+        &ix_data.mint,
+        authority_acc.key(),
+        &ix_data.to_bytes(),
+    )
+}
@@ -0,0 +1,136 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    instruction::{append_audit_record, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError, rate_limit::RateLimit,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+/// Instruction data of `SetRateLimit`: max_mutations(2) + window_slots(8) + guardian(32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetRateLimitIxData {
+    pub max_mutations: u16, // 0 lifts the limit
+    pub window_slots: u64,  // 0 for one window per epoch
+    pub guardian: [u8; 32], // All-zero for no guardian
+}
+
+impl DataLen for SetRateLimitIxData {
+    const LEN: usize = 42;
+}
+
+impl SetRateLimitIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+        let mut ix_data = Self {
+            max_mutations: u16::from_le_bytes([bytes[0], bytes[1]]),
+            window_slots: u64::from_le_bytes(bytes[2..10].try_into().unwrap()),
+            guardian: [0; 32],
+        };
+        ix_data.guardian.copy_from_slice(&bytes[10..42]);
+        Ok(ix_data)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..2].copy_from_slice(&self.max_mutations.to_le_bytes());
+        bytes[2..10].copy_from_slice(&self.window_slots.to_le_bytes());
+        bytes[10..42].copy_from_slice(&self.guardian);
+        bytes
+    }
+}
+
+/// Whether the guardian of `limit` signed the transaction, passed as any account.
+pub(crate) fn guardian_signed(accounts: &[AccountInfo], limit: &RateLimit) -> bool {
+    limit.guardian().is_some_and(|guardian| {
+        accounts
+            .iter()
+            .any(|acc| acc.is_signer() && acc.key() == guardian)
+    })
+}
+
+/// Counts a mapping change against the rate limit of `registry`. The caller writes the header.
+pub(crate) fn record_mutation(
+    accounts: &[AccountInfo],
+    registry: &mut ScopeMappingRegistry,
+) -> ProgramResult {
+    let clock = Clock::get()?;
+    let guardian_signed = guardian_signed(accounts, &registry.rate_limit);
+    registry
+        .rate_limit
+        .record_mutation(clock.slot, clock.epoch, guardian_signed)
+}
+
+/// Configures the mapping change limit of the registry. Once a guardian is set, it has to
+/// co-sign every change to the limit, so a leaked authority key alone can't lift it.
+pub fn process_set_rate_limit(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetRateLimitIxData::from_bytes(data)?;
+
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+
+        if registry.rate_limit.guardian().is_some()
+            && !guardian_signed(accounts, &registry.rate_limit)
+        {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let clock = Clock::get()?;
+        let settings = RateLimit::new(
+            ix_data.max_mutations,
+            ix_data.window_slots,
+            ix_data.guardian,
+        );
+        registry
+            .rate_limit
+            .reconfigure(&settings, clock.slot, clock.epoch);
+        registry.version += 1;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetRateLimit,
+        &[0u8; 32],
+        authority_acc.key(),
+        &ix_data.to_bytes(),
+    )
+}
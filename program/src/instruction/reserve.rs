@@ -0,0 +1,136 @@
+use pinocchio::{
+    account_info::{AccountInfo, MAX_PERMITTED_DATA_INCREASE},
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::Transfer;
+
+use crate::{
+    instruction::{append_audit_record, optional_account, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError,
+        scope_mapping_registry::ScopeMappingRegistry, utils::DataLen, Initialized,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct ReserveCapacityIxData {
+    pub additional_bytes: u16,
+}
+
+impl DataLen for ReserveCapacityIxData {
+    const LEN: usize = 2;
+}
+
+impl ReserveCapacityIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(MappingProgramError::InvalidInstructionData.into());
+        }
+        Ok(Self {
+            additional_bytes: u16::from_le_bytes([bytes[0], bytes[1]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        self.additional_bytes.to_le_bytes()
+    }
+}
+
+/// Grows the mapping region of the registry by `additional_bytes` and tops the account up to
+/// rent exemption from `funder_acc`.
+pub(crate) fn grow_registry(
+    state_acc: &AccountInfo,
+    funder_acc: &AccountInfo,
+    registry: &mut ScopeMappingRegistry,
+    additional_bytes: u32,
+) -> ProgramResult {
+    if additional_bytes as usize > MAX_PERMITTED_DATA_INCREASE {
+        return Err(MappingProgramError::ReallocTooLarge.into());
+    }
+    let allocated_bytes = registry
+        .allocated_bytes
+        .checked_add(additional_bytes)
+        .ok_or(MappingProgramError::WriteOverflow)?;
+    let new_account_size = ScopeMappingRegistry::LEN + allocated_bytes as usize;
+
+    state_acc.resize(new_account_size)?;
+    let cost = Rent::get()?.minimum_balance(new_account_size);
+    let top_up = cost.saturating_sub(state_acc.lamports());
+    if top_up > 0 {
+        Transfer {
+            from: funder_acc,
+            to: state_acc,
+            lamports: top_up,
+        }
+        .invoke()?;
+    }
+
+    registry.allocated_bytes = allocated_bytes;
+    Ok(())
+}
+
+pub fn process_reserve_capacity(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let funder_acc = match optional_account(accounts, 4) {
+        Some(funder_acc) => {
+            if !funder_acc.is_signer() {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            funder_acc
+        }
+        None => authority_acc,
+    };
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = ReserveCapacityIxData::from_bytes(data)?;
+
+    let mut registry = ScopeMappingRegistry::from_account_data(&state_acc.try_borrow_data()?)?;
+    if !registry.is_initialized() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Validate PDA
+    registry.validate_entries_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+    if registry.owner.ne(authority_acc.key()) {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    grow_registry(
+        state_acc,
+        funder_acc,
+        &mut registry,
+        ix_data.additional_bytes.into(),
+    )?;
+
+    state_acc.try_borrow_mut_data()?[..ScopeMappingRegistry::LEN]
+        .copy_from_slice(&registry.to_bytes());
+
+    // A continuation page is recorded in the audit log of its registry
+    append_audit_record(
+        audit_log_acc,
+        registry.page_of().unwrap_or(state_acc.key()),
+        AuditKind::ReserveCapacity,
+        &[0u8; 32],
+        authority_acc.key(),
+        &[],
+    )
+}
@@ -0,0 +1,108 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{
+    instruction::{append_audit_record, record_mutation, verify_oracle_owner, OWNER_PUB_KEY},
+    state::{
+        audit_log::AuditKind, error::MappingProgramError, price::SCOPE_PRICES_OFFSET,
+        scope_mapping_registry::ScopeMappingRegistry, source_type::SourceType, utils::DataLen,
+        Initialized,
+    },
+};
+
+/// Instruction data of `SetScopeFeed`: index(1) + feed(32).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SetScopeFeedIxData {
+    pub index: u8,      // Below `MAX_SCOPE_FEEDS`
+    pub feed: [u8; 32], // All-zero clears the feed
+}
+
+impl DataLen for SetScopeFeedIxData {
+    const LEN: usize = 33;
+}
+
+impl SetScopeFeedIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut ix_data = Self {
+            index: bytes[0],
+            feed: [0; 32],
+        };
+        ix_data.feed.copy_from_slice(&bytes[1..33]);
+        Ok(ix_data)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.index;
+        bytes[1..33].copy_from_slice(&self.feed);
+        bytes
+    }
+}
+
+/// Names the Scope `OraclePrices` account at one index of the registry. Mappings reference a
+/// feed by index, and the chain indices of their Scope sources are checked against its price
+/// array. The feed account is passed so its owner can be checked against the provider config.
+pub fn process_set_scope_feed(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, audit_log_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SetScopeFeedIxData::from_bytes(data)?;
+
+    {
+        let mut acc_data = state_acc.try_borrow_mut_data()?;
+        let mut registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        // Validate PDA
+        registry.validate_address(state_acc.key(), authority_acc.key(), &crate::ID)?;
+
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+
+        if ix_data.feed != [0u8; 32] {
+            let feed_acc = accounts
+                .get(3)
+                .ok_or(MappingProgramError::InvalidOracleAccount)?;
+            if feed_acc.key() != &ix_data.feed {
+                return Err(MappingProgramError::InvalidOracleAccount.into());
+            }
+            verify_oracle_owner(feed_acc, SourceType::Scope, &registry.providers)?;
+            if feed_acc.data_len() < SCOPE_PRICES_OFFSET {
+                return Err(MappingProgramError::InvalidOracleAccount.into());
+            }
+        }
+
+        record_mutation(accounts, &mut registry)?;
+        registry.set_scope_feed(ix_data.index, ix_data.feed)?;
+        acc_data[..ScopeMappingRegistry::LEN].copy_from_slice(&registry.to_bytes());
+    }
+
+    append_audit_record(
+        audit_log_acc,
+        state_acc.key(),
+        AuditKind::SetScopeFeed,
+        &ix_data.feed,
+        authority_acc.key(),
+        &ix_data.to_bytes(),
+    )
+}
@@ -0,0 +1,140 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::{
+    instruction::OWNER_PUB_KEY,
+    state::{
+        error::MappingProgramError, scope_mapping_registry::ScopeMappingRegistry,
+        snapshot::RegistrySnapshot, utils::DataLen, Initialized,
+    },
+};
+
+/// Instruction data of `Snapshot`: number(8) + bump(1).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub struct SnapshotIxData {
+    pub number: u64,
+    pub bump: u8,
+}
+
+impl DataLen for SnapshotIxData {
+    const LEN: usize = 9;
+}
+
+impl SnapshotIxData {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            number: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            bump: bytes[8],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.number.to_le_bytes());
+        bytes[8] = self.bump;
+        bytes
+    }
+}
+
+/// Copies the registry into a new snapshot PDA, sized to the written entries. Snapshot numbers
+/// are picked by the authority and each can only be taken once. A registry with continuation
+/// pages fails with `RegistryHasPages`, since the snapshot would miss their entries.
+pub fn process_snapshot(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority_acc, state_acc, snapshot_acc, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority_acc.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Hardcoded authority check
+    if authority_acc.key().as_ref() != OWNER_PUB_KEY {
+        return Err(MappingProgramError::InvalidOwner.into());
+    }
+
+    if !snapshot_acc.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if state_acc.owner() != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ix_data = SnapshotIxData::from_bytes(data)?;
+
+    let snapshot = {
+        let acc_data = state_acc.try_borrow_data()?;
+        let registry = ScopeMappingRegistry::from_account_data(&acc_data)?;
+        if !registry.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if registry.owner.ne(authority_acc.key()) {
+            return Err(MappingProgramError::InvalidOwner.into());
+        }
+        if registry.page_count > 0 {
+            return Err(MappingProgramError::RegistryHasPages.into());
+        }
+
+        // Validate both PDAs
+        ScopeMappingRegistry::validate_pda(
+            registry.bump,
+            state_acc.key(),
+            authority_acc.key(),
+            &crate::ID,
+        )?;
+        RegistrySnapshot::validate_pda(
+            ix_data.bump,
+            snapshot_acc.key(),
+            state_acc.key(),
+            ix_data.number,
+            &crate::ID,
+        )?;
+
+        RegistrySnapshot::new(
+            *state_acc.key(),
+            ix_data.number,
+            ix_data.bump,
+            Clock::get()?.slot,
+            RegistrySnapshot::registry_content(&acc_data)?,
+        )?
+    };
+
+    // Signer seeds
+    let number_bytes = ix_data.number.to_le_bytes();
+    let pda_bump_bytes = [ix_data.bump];
+    let signer_seeds = [
+        Seed::from(RegistrySnapshot::SEED.as_bytes()),
+        Seed::from(state_acc.key().as_ref()),
+        Seed::from(&number_bytes[..]),
+        Seed::from(&pda_bump_bytes[..]),
+    ];
+    let signers = [Signer::from(&signer_seeds[..])];
+
+    let space = snapshot.account_size();
+    CreateAccount {
+        from: authority_acc,
+        to: snapshot_acc,
+        space: space as u64,
+        owner: &crate::ID,
+        lamports: Rent::get()?.minimum_balance(space),
+    }
+    .invoke_signed(&signers)?;
+
+    let acc_data = state_acc.try_borrow_data()?;
+    let mut snapshot_data = snapshot_acc.try_borrow_mut_data()?;
+    snapshot_data[..RegistrySnapshot::LEN].copy_from_slice(&snapshot.to_bytes());
+    snapshot_data[RegistrySnapshot::LEN..].copy_from_slice(&acc_data[..snapshot.data_len as usize]);
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey, ProgramResult};
+use pinocchio_pubkey::pubkey;
+
+use crate::{
+    instruction::optional_account,
+    state::{
+        error::MappingProgramError, mint_mapping::MintMapping, price::verify_scope_chain,
+        provider_config::ProviderConfig, scope_mapping_registry::ScopeMappingRegistry,
+        source_type::SourceType,
+    },
+};
+
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+// Default provider programs written at registry initialization
+pub const SCOPE_PROGRAM_ID: Pubkey = pubkey!("HFn8GnPADiny6XqUoWE8uRPPxb29ikn4yTuPa9MF2fWJ");
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+pub const SWITCHBOARD_PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+// SPL stake pool program and the Sanctum deployments of it
+pub const STAKE_POOL_PROGRAM_IDS: [Pubkey; 3] = [
+    pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy"),
+    pubkey!("SP12tWFxD9oJsVWNavTTBZvMbA6gkAmxtVgxdqvyvhY"),
+    pubkey!("SPMBzsVUuoHA4Jm6KunbsotaahvVikZs1JyTW6iJvbn"),
+];
+
+/// Size of the base SPL mint layout, Token-2022 extensions come after it
+const MINT_LEN: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+
+/// Checks that `mint_acc` is the initialized SPL Token or Token-2022 mint `mint` and that its
+/// decimals match the ones stored in the mapping.
+pub(crate) fn verify_mint_account(
+    mint_acc: &AccountInfo,
+    mint: &[u8; 32],
+    decimals: u8,
+) -> ProgramResult {
+    if mint_acc.key() != mint {
+        return Err(MappingProgramError::InvalidMintAccount.into());
+    }
+
+    if mint_acc.owner() != &TOKEN_PROGRAM_ID && mint_acc.owner() != &TOKEN_2022_PROGRAM_ID {
+        return Err(MappingProgramError::InvalidMintAccount.into());
+    }
+
+    let data = mint_acc.try_borrow_data()?;
+    if data.len() < MINT_LEN || data[MINT_IS_INITIALIZED_OFFSET] != 1 {
+        return Err(MappingProgramError::InvalidMintAccount.into());
+    }
+
+    if data[MINT_DECIMALS_OFFSET] != decimals {
+        return Err(MappingProgramError::DecimalsMismatch.into());
+    }
+
+    Ok(())
+}
+
+/// Checks that `oracle_acc` is owned by the program expected for `source_type`: the one set in
+/// the provider config, or one of the stake pool programs.
+pub(crate) fn verify_oracle_owner(
+    oracle_acc: &AccountInfo,
+    source_type: SourceType,
+    providers: &ProviderConfig,
+) -> ProgramResult {
+    let owner_matches = match source_type {
+        SourceType::StakePool => STAKE_POOL_PROGRAM_IDS.contains(oracle_acc.owner()),
+        _ => match providers.expected_owner(source_type) {
+            Some(expected_owner) => oracle_acc.owner() == expected_owner,
+            None => true,
+        },
+    };
+    if !owner_matches {
+        return Err(MappingProgramError::OracleOwnerMismatch.into());
+    }
+    Ok(())
+}
+
+/// Checks the oracle accounts passed from `first_index` onwards, one per account-based source in
+/// `MintMapping::oracle_sources` order, against the mapping and the registry's provider config.
+/// Scope sources take the registry feed the mapping references, once the registry names feeds,
+/// and their chain indices must fall inside its price array. Other missing accounts are only an
+/// error in strict mode, which registries start out in.
+pub(crate) fn verify_oracle_accounts(
+    accounts: &[AccountInfo],
+    first_index: usize,
+    registry: &ScopeMappingRegistry,
+    mapping: &MintMapping,
+) -> ProgramResult {
+    let has_scope = mapping
+        .oracle_sources()
+        .any(|source| source.source_type() == Some(SourceType::Scope));
+    let scope_feed = if has_scope {
+        registry.mapping_scope_feed(mapping.scope_feed())?
+    } else {
+        None
+    };
+
+    let oracles = mapping
+        .oracle_sources()
+        .filter_map(|source| match source.source_type()? {
+            SourceType::Scope => Some((SourceType::Scope, scope_feed?, source.scope_chain())),
+            source_type => Some((source_type, source.oracle_account()?, None)),
+        });
+
+    for (index, (source_type, oracle, chain)) in oracles.enumerate() {
+        let Some(oracle_acc) = optional_account(accounts, first_index + index) else {
+            // The chain indices can't be checked without the feed
+            if registry.providers.is_strict() || chain.is_some() {
+                return Err(MappingProgramError::InvalidOracleAccount.into());
+            }
+            continue;
+        };
+
+        if oracle_acc.key() != &oracle {
+            return Err(MappingProgramError::InvalidOracleAccount.into());
+        }
+
+        verify_oracle_owner(oracle_acc, source_type, &registry.providers)?;
+
+        if let Some(chain) = chain {
+            verify_scope_chain(&oracle_acc.try_borrow_data()?, &chain)?;
+        }
+    }
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ mod entrypoint;
 #[cfg(feature = "std")]
 extern crate std;
 
+pub mod cpi;
 pub mod instruction;
 pub use oracle_mapping_state as state;
 
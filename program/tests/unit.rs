@@ -1,17 +1,32 @@
-use litesvm::LiteSVM;
+use litesvm::{types::TransactionResult, LiteSVM};
 use oracle_mapping::{
-    instruction::{AddMappingIxData, InitializeRegistryIxData, IntoBytes},
-    state::{DataLen, MintMapping, ScopeMappingRegistry},
+    cpi::decode_mapping,
+    state::{
+        error::MappingProgramError, hash, leaf_hash, mappings_root, node_hash, pages_root,
+        verify_inclusion, Attribute, AttributeList, AttributeTag, AuditKind, AuditLog, DataLen,
+        MappingProof, MintAlias, MintMapping, PriceCache, PriceSelection, ProviderConfig,
+        QuoteCurrency, RateLimit, RegistrySnapshot, ScopeMappingRegistry, SourceEntry, SourceList,
+        SourceType, TokenMetadata, ValidityWindow, ALIAS_MULTIPLIER_ONE, MAPPING_CLOSED,
+        RISK_TIER_SHIFT, TAG_WRAPPED,
+    },
+};
+use oracle_mapping_client::{
+    self as client, AddMapping, AddMappingIxData, AddPageIxData, CloseMappingIxData, Compact,
+    CompactIxData, GetMappingIxData, InitializeAuditLogIxData, InitializeDraftIxData,
+    InitializePriceCacheIxData, InitializeRegistryIxData, PromoteIxData, PruneExpiredIxData,
+    RefreshPricesIxData, ReserveCapacityIxData, SetDelegateIxData, SetDisabledProvidersIxData,
+    SetProviderConfigIxData, SetQuoteConversionIxData, SetRateLimitIxData, SetScopeFeedIxData,
+    SnapshotIxData, UpdateSourcesIxData, ADD_MAPPING_DISCRIMINATOR,
 };
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
+    account::Account,
+    instruction::{AccountMeta, Instruction, InstructionError},
     message::{v0, VersionedMessage},
     pubkey::Pubkey,
     signature::Keypair,
     signer::{EncodableKey, Signer},
     system_program,
-    sysvar::rent,
-    transaction::VersionedTransaction,
+    transaction::{TransactionError, VersionedTransaction},
 };
 use std::str::FromStr;
 
@@ -24,33 +39,52 @@ fn setup_svm_and_program() -> (LiteSVM, Keypair, Pubkey, Pubkey, u8) {
     let program_id = Pubkey::from(oracle_mapping::ID);
     svm.add_program_from_file(program_id, "../target/deploy/oracle_mapping.so")
         .unwrap();
-    let (state_pda, bump) = Pubkey::find_program_address(
-        &[b"ScopeMappingRegistry", fee_payer.pubkey().as_ref()],
-        &program_id,
-    );
+    let (state_pda, bump) = client::find_registry_address(&fee_payer.pubkey());
     (svm, fee_payer, program_id, state_pda, bump)
 }
 
 fn create_initialize_registry_ix(
-    program_id: Pubkey,
+    _program_id: Pubkey,
     fee_payer: &Keypair,
     state_pda: Pubkey,
     bump: u8,
     _owner: [u8; 32],
 ) -> Instruction {
-    let binding = InitializeRegistryIxData { bump };
-    let ix_data = binding.to_bytes();
-    let mut ix_data_with_discriminator = vec![0];
-    ix_data_with_discriminator.extend_from_slice(&ix_data);
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data_with_discriminator.try_into().unwrap(),
+    client::InitializeState {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+    }
+    .instruction(&InitializeRegistryIxData {
+        bump,
+        max_mappings: 0,
+    })
+}
+
+fn get_audit_log_pda(_program_id: Pubkey, state_pda: Pubkey) -> (Pubkey, u8) {
+    client::find_audit_log_address(&state_pda)
+}
+
+fn create_initialize_audit_log_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+) -> Instruction {
+    let (audit_log_pda, bump) = get_audit_log_pda(program_id, state_pda);
+    client::InitializeAuditLog {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: audit_log_pda,
+    }
+    .instruction(&InitializeAuditLogIxData { bump })
+}
+
+fn add_mapping_accounts(program_id: Pubkey, fee_payer: &Keypair, state_pda: Pubkey) -> AddMapping {
+    AddMapping {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        funder_acc: None,
+        mint_acc: None,
     }
 }
 
@@ -60,20 +94,8 @@ fn create_add_mapping_ix(
     state_pda: Pubkey,
     mapping: MintMapping,
 ) -> Instruction {
-    let add_mapping_ix_data = AddMappingIxData { mapping };
-    let mut ix_data_with_discriminator = vec![1];
-    ix_data_with_discriminator.extend_from_slice(add_mapping_ix_data.into_bytes().unwrap());
-    let ix_data: [u8; 1 + AddMappingIxData::LEN] = ix_data_with_discriminator.try_into().unwrap();
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data.into(),
-    }
+    add_mapping_accounts(program_id, fee_payer, state_pda)
+        .instruction(&AddMappingIxData { mapping })
 }
 
 fn create_close_mapping_ix(
@@ -83,23 +105,48 @@ fn create_close_mapping_ix(
     mint: [u8; 32],
     bump: u8,
 ) -> Instruction {
-    use oracle_mapping::instruction::CloseMappingIxData;
-    let close_mapping_ix_data = CloseMappingIxData { mint, bump };
-    let mut ix_data_with_discriminator = vec![2];
-    ix_data_with_discriminator.extend_from_slice(close_mapping_ix_data.into_bytes().unwrap());
-    let ix_data: [u8; 1 + CloseMappingIxData::LEN] = ix_data_with_discriminator.try_into().unwrap();
-    Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(fee_payer.pubkey(), true),
-            AccountMeta::new(state_pda, false),
-            AccountMeta::new_readonly(rent::id(), false),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: ix_data.into(),
+    client::CloseMapping {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        refund_acc: None,
+    }
+    .instruction(&CloseMappingIxData { mint, bump })
+}
+
+fn create_reserve_capacity_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    additional_bytes: u16,
+) -> Instruction {
+    client::ReserveCapacity {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        funder_acc: None,
+    }
+    .instruction(&ReserveCapacityIxData { additional_bytes })
+}
+
+fn compact_accounts(program_id: Pubkey, fee_payer: &Keypair, state_pda: Pubkey) -> Compact {
+    Compact {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        refund_acc: None,
     }
 }
 
+fn create_compact_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    retain_bytes: u16,
+) -> Instruction {
+    compact_accounts(program_id, fee_payer, state_pda).instruction(&CompactIxData { retain_bytes })
+}
+
 fn get_registry(svm: &LiteSVM, state_pda: &Pubkey) -> ScopeMappingRegistry {
     let data = svm.get_account(state_pda).unwrap().data;
     ScopeMappingRegistry::from_slice(&data[..ScopeMappingRegistry::LEN]).unwrap()
@@ -107,25 +154,12 @@ fn get_registry(svm: &LiteSVM, state_pda: &Pubkey) -> ScopeMappingRegistry {
 
 fn get_mapping(svm: &LiteSVM, state_pda: &Pubkey, index: usize) -> MintMapping {
     let data = svm.get_account(state_pda).unwrap().data;
-    // Calculate the starting offset for this mapping
-    let mut current_offset = ScopeMappingRegistry::LEN;
-    // Skip previous mappings to find the start of this mapping
-    for i in 0..index {
-        if current_offset + 35 > data.len() {
-            panic!("Mapping index {} not found", index);
-        }
-        // Read the offset byte at position 32 of each mapping to get its actual size
-        let mapping_offset = data[current_offset + 32] as usize;
-        current_offset += mapping_offset;
-    }
-    // Now read the current mapping
-    if current_offset + 35 > data.len() {
-        panic!("Mapping index {} not found", index);
-    }
-    // Read the offset byte to determine the actual size of this mapping
-    let mapping_size = data[current_offset + 32] as usize;
-    let mapping_data = &data[current_offset..current_offset + mapping_size];
-    MintMapping::from_bytes(mapping_data).unwrap()
+    // Walk the live entries, skipping closed ones
+    let (start, end) = ScopeMappingRegistry::mapping_offsets(&data)
+        .unwrap()
+        .nth(index)
+        .unwrap_or_else(|| panic!("Mapping index {} not found", index));
+    MintMapping::from_bytes(&data[start..end]).unwrap()
 }
 
 #[test]
@@ -139,8 +173,14 @@ fn test_initialize_and_add_mapping() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
-    let msg =
-        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     let result = svm.send_transaction(tx);
     println!("result: {:?}", result);
@@ -162,6 +202,10 @@ fn test_initialize_and_add_mapping() {
         scope_details: Some([0, u16::MAX, u16::MAX]),
         pyth_account: None,
         switch_board: None,
+        sources: SourceList::default(),
+        metadata: None,
+        alias: None,
+        attributes: AttributeList::default(),
     };
     // mint_mapping.set_pyth_account(None);
     // mint_mapping.set_switch_board(None);
@@ -239,8 +283,14 @@ fn test_add_mapping_wrong_signer() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
-    let msg =
-        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
     // Try add mapping with wrong signer
@@ -269,10 +319,18 @@ fn test_add_mapping_with_pyth_and_switchboard() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
-    let msg =
-        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
     // Add mapping with pyth and switchboard
     let mut mint_mapping = MintMapping::default();
     mint_mapping.mint = Pubkey::from_str("So11111111111111111111111111111111111111115")
@@ -294,7 +352,7 @@ fn test_add_mapping_with_pyth_and_switchboard() {
 }
 
 #[test]
-fn test_add_multiple_mappings() {
+fn test_add_mapping_with_metadata() {
     let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
     // Initialize
     let ix = create_initialize_registry_ix(
@@ -304,31 +362,150 @@ fn test_add_multiple_mappings() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Add a mapping carrying symbol, name and tags
+    let mut mint_mapping =
+        MintMapping::new([3u8; 32], Some([0, u16::MAX, u16::MAX]), None, None, 9);
+    let tags = TAG_WRAPPED | (2 << RISK_TIER_SHIFT);
+    mint_mapping
+        .set_metadata(Some(
+            TokenMetadata::new("SOL", "Wrapped SOL", tags).unwrap(),
+        ))
+        .unwrap();
+    assert!(mint_mapping.has_metadata());
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
     let msg =
         v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
-    // Add multiple mappings
-    for i in 0..3 {
-        let mut mint_mapping = MintMapping::default();
-        mint_mapping.mint = [i as u8; 32];
-        mint_mapping.decimals = i as u8;
-        mint_mapping.mapping_details = 0b000; // No components enabled for simple mappings
-        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+
+    let reg = get_registry(&svm, &state_pda);
+    // header(35) + scope(6) + metadata(1 + 3 + 1 + 11 + 2)
+    assert_eq!(reg.last_mapping_offset, 35 + 6 + 18);
+    let mapping = get_mapping(&svm, &state_pda, 0);
+    assert_eq!(mapping.scope_details, Some([0, u16::MAX, u16::MAX]));
+    let metadata = mapping.get_metadata().unwrap();
+    assert_eq!(metadata.symbol(), "SOL");
+    assert_eq!(metadata.name(), "Wrapped SOL");
+    assert!(metadata.has_tag(TAG_WRAPPED));
+    assert_eq!(metadata.risk_tier(), 2);
+}
+
+fn set_mint_account(svm: &mut LiteSVM, mint: Pubkey, owner: Pubkey, decimals: u8) {
+    let mut data = vec![0u8; 82];
+    data[44] = decimals;
+    data[45] = 1;
+    svm.set_account(
+        mint,
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_add_mapping_verifies_mint_decimals() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let token_program = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+    let token_2022_program =
+        Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
+    let mint = Pubkey::new_unique();
+    let mint_2022 = Pubkey::new_unique();
+    let fake_mint = Pubkey::new_unique();
+    set_mint_account(&mut svm, mint, token_program, 6);
+    set_mint_account(&mut svm, mint_2022, token_2022_program, 9);
+    set_mint_account(&mut svm, fake_mint, system_program::id(), 6);
+
+    // No funder, so the program id holds its slot before the mint account
+    let add_with_mint = |svm: &mut LiteSVM, mint: Pubkey, decimals: u8| {
+        let mint_mapping = MintMapping::new(mint.to_bytes(), None, Some([1u8; 32]), None, decimals);
+        let ix = AddMapping {
+            mint_acc: Some(mint),
+            ..add_mapping_accounts(program_id, &fee_payer, state_pda)
+        }
+        .instruction(&AddMappingIxData {
+            mapping: mint_mapping,
+        });
         let msg = v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash())
             .unwrap();
         let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
-        svm.send_transaction(tx).unwrap();
-        let mapping = get_mapping(&svm, &state_pda, i as usize);
-        assert_eq!(mapping.mint, [i as u8; 32]);
-        assert_eq!(mapping.decimals, i as u8);
+        svm.send_transaction(tx)
+    };
+
+    // Wrong decimals and a mint not owned by a token program are rejected
+    assert!(add_with_mint(&mut svm, mint, 9).is_err());
+    assert!(add_with_mint(&mut svm, fake_mint, 6).is_err());
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 0);
+
+    assert!(add_with_mint(&mut svm, mint, 6).is_ok());
+    assert!(add_with_mint(&mut svm, mint_2022, 9).is_ok());
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 2);
+}
+
+fn create_set_provider_config_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    providers: ProviderConfig,
+) -> Instruction {
+    client::SetProviderConfig {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
     }
-    let reg = get_registry(&svm, &state_pda);
-    assert_eq!(reg.total_mappings, 3);
+    .instruction(&SetProviderConfigIxData { providers })
+}
+
+/// Leaves oracle accounts optional, for mappings whose oracle keys are placeholders.
+fn relax_oracle_checks(
+    svm: &mut LiteSVM,
+    fee_payer: &Keypair,
+    program_id: Pubkey,
+    state_pda: Pubkey,
+) {
+    let mut providers = get_registry(svm, &state_pda).providers;
+    providers.strict = 0;
+    let ix = create_set_provider_config_ix(program_id, fee_payer, state_pda, providers);
+    send_ixs(svm, fee_payer, &[ix]).unwrap();
 }
 
 #[test]
-fn test_add_and_remove_middle_mapping() {
+fn test_add_mapping_verifies_oracle_owners() {
     let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
     // Initialize
     let ix = create_initialize_registry_ix(
@@ -338,52 +515,91 @@ fn test_add_and_remove_middle_mapping() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
-    let msg =
-        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
-    // Add 3 mappings
-    let mut mints = [[0u8; 32]; 3];
-    for i in 0..3 {
-        mints[i][0] = i as u8 + 1; // Unique first byte for each mint
-        let scope_details = Some([(i + 1) as u16; 3]);
-        let pyth_account = Some([(i + 1) as u8 * 10; 32]);
-        let switch_board = Some([(i + 1) as u8 * 11; 32]);
-        let mint_mapping =
-            MintMapping::new(mints[i], scope_details, pyth_account, switch_board, i as u8);
-        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+
+    // The registry starts out expecting the Pyth receiver program
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.providers.pyth_program, pyth_receiver.to_bytes());
+    assert_eq!(reg.providers.strict, 1);
+
+    let set_oracle = |svm: &mut LiteSVM, owner: Pubkey| {
+        let oracle = Pubkey::new_unique();
+        svm.set_account(
+            oracle,
+            Account {
+                lamports: 1_000_000,
+                data: vec![0u8; 134],
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+        .unwrap();
+        oracle
+    };
+    // Program id placeholders keep the funder and mint slots
+    let add_with_pyth = |svm: &mut LiteSVM, mint: u8, pyth: Option<Pubkey>, stored: Pubkey| {
+        let mint_mapping = MintMapping::new([mint; 32], None, Some(stored.to_bytes()), None, 6);
+        let oracles: Vec<_> = pyth
+            .into_iter()
+            .map(|pyth| AccountMeta::new_readonly(pyth, false))
+            .collect();
+        let ix = add_mapping_accounts(program_id, &fee_payer, state_pda)
+            .instruction_with_remaining_accounts(
+                &AddMappingIxData {
+                    mapping: mint_mapping,
+                },
+                &oracles,
+            );
         let msg = v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash())
             .unwrap();
         let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
-        svm.send_transaction(tx).unwrap();
-    }
-    let reg = get_registry(&svm, &state_pda);
-    assert_eq!(reg.total_mappings, 3);
-    // Remove the middle mapping (index 1)
-    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mints[1], bump);
+        svm.send_transaction(tx)
+    };
+
+    let pyth_price = set_oracle(&mut svm, pyth_receiver);
+    let attacker_price = set_oracle(&mut svm, Pubkey::new_unique());
+    // Wrong owner, and an account that is not the one stored in the mapping
+    assert!(add_with_pyth(&mut svm, 1, Some(attacker_price), attacker_price).is_err());
+    assert!(add_with_pyth(&mut svm, 1, Some(pyth_price), attacker_price).is_err());
+    assert!(add_with_pyth(&mut svm, 1, Some(pyth_price), pyth_price).is_ok());
+    // Strict mode is the default, so the account can't be left out
+    assert!(add_with_pyth(&mut svm, 2, None, attacker_price).is_err());
+    // Without strict mode the account may be left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    assert!(add_with_pyth(&mut svm, 2, None, attacker_price).is_ok());
+
+    // Switch to strict mode with a custom Pyth owner
+    let custom_owner = Pubkey::new_unique();
+    let mut providers = reg.providers;
+    providers.pyth_program = custom_owner.to_bytes();
+    providers.strict = 1;
+    let ix = create_set_provider_config_ix(program_id, &fee_payer, state_pda, providers);
     let msg =
         v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
-    let reg = get_registry(&svm, &state_pda);
-    assert_eq!(reg.total_mappings, 2);
-    // Check the remaining mappings are the first and last
-    let mapping0 = get_mapping(&svm, &state_pda, 0);
-    let mapping1 = get_mapping(&svm, &state_pda, 1);
-    assert_eq!(mapping0.mint, mints[0]);
-    assert_eq!(mapping1.mint, mints[2]);
-    // Optionally, check decimals or other fields
-    assert_eq!(mapping0.decimals, 0);
-    assert_eq!(mapping1.decimals, 2);
-    // Check extra data
-    assert_eq!(mapping0.get_switch_board(), Some([11u8; 32]));
-    assert_eq!(mapping0.scope_details, Some([1u16; 3]));
-    assert_eq!(mapping1.get_pyth_account(), Some([30u8; 32]));
-    assert_eq!(mapping1.scope_details, Some([3u16; 3]));
+    assert_eq!(get_registry(&svm, &state_pda).providers, providers);
+
+    let custom_price = set_oracle(&mut svm, custom_owner);
+    assert!(add_with_pyth(&mut svm, 3, None, custom_price).is_err());
+    assert!(add_with_pyth(&mut svm, 3, Some(pyth_price), pyth_price).is_err());
+    assert!(add_with_pyth(&mut svm, 3, Some(custom_price), custom_price).is_ok());
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 3);
 }
 
 #[test]
-fn test_add_mapping_and_remove_mapping_verify_registry() {
+fn test_add_mapping_with_source_list() {
     let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
     // Initialize
     let ix = create_initialize_registry_ix(
@@ -393,47 +609,2933 @@ fn test_add_mapping_and_remove_mapping_verify_registry() {
         bump,
         fee_payer.pubkey().to_bytes(),
     );
-    let msg =
-        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
     svm.send_transaction(tx).unwrap();
-    // Add mapping
-    let mint_mapping = MintMapping::new(
-        Pubkey::from_str("So11111111111111111111111111111111111111111")
-            .unwrap()
-            .to_bytes(),
-        Some([0, u16::MAX, u16::MAX]),
-        Some(
-            Pubkey::from_str("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE")
-                .unwrap()
-                .to_bytes(),
-        ),
-        Some(
-            Pubkey::from_str("3PiwrLLyiuWaxS7zJL5znGR9iYD3KWubZThdQzsCdg2e")
-                .unwrap()
-                .to_bytes(),
-        ),
-        9,
-    );
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    // A fixed Pyth feed plus a second Pyth feed and a Stork feed in the tagged list
+    let mut mint_mapping = MintMapping::new([4u8; 32], None, Some([1u8; 32]), None, 6);
+    mint_mapping
+        .add_source(SourceEntry::account(SourceType::Pyth, [2u8; 32]))
+        .unwrap();
+    mint_mapping
+        .add_source(SourceEntry::account(SourceType::Stork, [3u8; 32]))
+        .unwrap();
     let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
     let msg =
         v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
-    let result = svm.send_transaction(tx);
-    assert!(result.is_ok());
+    svm.send_transaction(tx).unwrap();
 
     let reg = get_registry(&svm, &state_pda);
-    assert_eq!(reg.total_mappings, 1);
-    assert_eq!(reg.last_mapping_offset, 105);
+    // header(35) + pyth(32) + count(1) + 2 * (tag(1) + len(1) + 32)
+    assert_eq!(reg.last_mapping_offset, 35 + 32 + 1 + 2 * 34);
+    let mapping = get_mapping(&svm, &state_pda, 0);
+    assert_eq!(mapping, mint_mapping);
+    let sources: Vec<_> = mapping
+        .oracle_sources()
+        .map(|source| {
+            (
+                source.source_type().unwrap(),
+                source.oracle_account().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        sources,
+        vec![
+            (SourceType::Pyth, [1u8; 32]),
+            (SourceType::Pyth, [2u8; 32]),
+            (SourceType::Stork, [3u8; 32]),
+        ]
+    );
 
-    // Remove mapping
-    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping.mint, bump);
+    // Unknown tags are rejected
+    let mut mint_mapping = MintMapping::new([5u8; 32], None, None, None, 6);
+    mint_mapping
+        .add_source(SourceEntry::new(0x7f, &[1u8; 32]).unwrap())
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
     let msg =
         v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
     let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
-    let result = svm.send_transaction(tx);
-    assert!(result.is_ok());
-    let reg = get_registry(&svm, &state_pda);
-    assert_eq!(reg.total_mappings, 0);
-    assert_eq!(reg.last_mapping_offset, 0);
+    assert!(svm.send_transaction(tx).is_err());
+
+    // So is the closed flag, which only the program sets
+    let mut closed = MintMapping::new([6u8; 32], None, Some([1u8; 32]), None, 6);
+    closed.mapping_details |= MAPPING_CLOSED;
+    assert!(!closed.is_valid());
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, closed);
+    assert!(send_ixs(&mut svm, &fee_payer, &[ix]).is_err());
+}
+
+#[test]
+fn test_add_mapping_wire_format() {
+    use oracle_mapping::instruction::AddMappingIxData;
+
+    let mut mapping = MintMapping::new(
+        [7u8; 32],
+        Some([0x0102, u16::MAX, u16::MAX]),
+        Some([0xAA; 32]),
+        None,
+        9,
+    );
+    mapping
+        .add_source(SourceEntry::account(SourceType::Stork, [0x55; 32]))
+        .unwrap();
+    mapping
+        .set_metadata(Some(TokenMetadata::new("AB", "C", 0x0102).unwrap()))
+        .unwrap();
+    let ix_data = AddMappingIxData { mapping };
+
+    let mut expected = vec![1u8]; // format version
+    expected.extend_from_slice(&[7u8; 32]); // mint
+    expected.extend_from_slice(&[115, 0b1_1011, 9]); // entry size, mapping_details, decimals
+    expected.extend_from_slice(&[0x02, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]); // scope chain
+    expected.extend_from_slice(&[0xAA; 32]); // pyth
+    expected.extend_from_slice(&[1, 4, 32]); // one Stork entry
+    expected.extend_from_slice(&[0x55; 32]);
+    expected.extend_from_slice(&[2, b'A', b'B', 1, b'C', 0x02, 0x01]); // metadata
+    assert_eq!(ix_data.serialized_size(), expected.len());
+    assert_eq!(
+        &ix_data.to_bytes().unwrap()[..ix_data.serialized_size()],
+        &expected[..]
+    );
+    assert_eq!(AddMappingIxData::from_bytes(&expected).unwrap(), ix_data);
+
+    // Unknown versions, truncated and padded payloads are rejected
+    let mut bad_version = expected.clone();
+    bad_version[0] = 2;
+    assert!(AddMappingIxData::from_bytes(&bad_version).is_err());
+    assert!(AddMappingIxData::from_bytes(&expected[..expected.len() - 1]).is_err());
+    let mut padded = expected.clone();
+    padded.push(0);
+    assert!(AddMappingIxData::from_bytes(&padded).is_err());
+
+    // The program accepts exactly these bytes
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let mut ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+    ix.data = [vec![ADD_MAPPING_DISCRIMINATOR], bad_version].concat();
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix.clone()],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    assert!(svm.send_transaction(tx).is_err());
+
+    ix.data = [vec![ADD_MAPPING_DISCRIMINATOR], expected].concat();
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    assert_eq!(get_mapping(&svm, &state_pda, 0), mapping);
+}
+
+#[test]
+fn test_client_matches_program() {
+    use oracle_mapping_client::OracleMappingError;
+
+    assert_eq!(client::ID, Pubkey::from(oracle_mapping::ID));
+    assert_eq!(
+        OracleMappingError::from_code(MappingProgramError::UnsupportedFormatVersion as u32),
+        Some(OracleMappingError::UnsupportedFormatVersion)
+    );
+
+    // Absent trailing optional accounts are left off, earlier ones hold their slot
+    let fee_payer = Keypair::new();
+    let (state_pda, _) = client::find_registry_address(&fee_payer.pubkey());
+    let mapping = MintMapping::new([1u8; 32], None, None, None, 6);
+    let ix = create_add_mapping_ix(client::ID, &fee_payer, state_pda, mapping);
+    assert_eq!(ix.accounts.len(), 4);
+    assert_eq!(ix.data[0], ADD_MAPPING_DISCRIMINATOR);
+
+    let mint = Pubkey::new_unique();
+    let ix = AddMapping {
+        mint_acc: Some(mint),
+        ..add_mapping_accounts(client::ID, &fee_payer, state_pda)
+    }
+    .instruction(&AddMappingIxData { mapping });
+    assert_eq!(ix.accounts.len(), 6);
+    assert_eq!(ix.accounts[4].pubkey, client::ID);
+    assert_eq!(ix.accounts[5].pubkey, mint);
+}
+
+#[test]
+fn test_add_multiple_mappings() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Add multiple mappings
+    for i in 0..3 {
+        let mut mint_mapping = MintMapping::default();
+        mint_mapping.mint = [i as u8; 32];
+        mint_mapping.decimals = i as u8;
+        mint_mapping.mapping_details = 0b000; // No components enabled for simple mappings
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+        let msg = v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash())
+            .unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+        svm.send_transaction(tx).unwrap();
+        let mapping = get_mapping(&svm, &state_pda, i as usize);
+        assert_eq!(mapping.mint, [i as u8; 32]);
+        assert_eq!(mapping.decimals, i as u8);
+    }
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 3);
+}
+
+#[test]
+fn test_add_and_remove_middle_mapping() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    // Add 3 mappings
+    let mut mints = [[0u8; 32]; 3];
+    for i in 0..3 {
+        mints[i][0] = i as u8 + 1; // Unique first byte for each mint
+        let scope_details = Some([(i + 1) as u16; 3]);
+        let pyth_account = Some([(i + 1) as u8 * 10; 32]);
+        let switch_board = Some([(i + 1) as u8 * 11; 32]);
+        let mint_mapping =
+            MintMapping::new(mints[i], scope_details, pyth_account, switch_board, i as u8);
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+        let msg = v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash())
+            .unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+        svm.send_transaction(tx).unwrap();
+    }
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 3);
+    // Remove the middle mapping (index 1)
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mints[1], bump);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 2);
+    // Check the remaining mappings are the first and last
+    let mapping0 = get_mapping(&svm, &state_pda, 0);
+    let mapping1 = get_mapping(&svm, &state_pda, 1);
+    assert_eq!(mapping0.mint, mints[0]);
+    assert_eq!(mapping1.mint, mints[2]);
+    // Optionally, check decimals or other fields
+    assert_eq!(mapping0.decimals, 0);
+    assert_eq!(mapping1.decimals, 2);
+    // Check extra data
+    assert_eq!(mapping0.get_switch_board(), Some([11u8; 32]));
+    assert_eq!(mapping0.scope_details, Some([1u16; 3]));
+    assert_eq!(mapping1.get_pyth_account(), Some([30u8; 32]));
+    assert_eq!(mapping1.scope_details, Some([3u16; 3]));
+}
+
+#[test]
+fn test_add_mapping_and_remove_mapping_verify_registry() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    // Add mapping
+    let mint_mapping = MintMapping::new(
+        Pubkey::from_str("So11111111111111111111111111111111111111111")
+            .unwrap()
+            .to_bytes(),
+        Some([0, u16::MAX, u16::MAX]),
+        Some(
+            Pubkey::from_str("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE")
+                .unwrap()
+                .to_bytes(),
+        ),
+        Some(
+            Pubkey::from_str("3PiwrLLyiuWaxS7zJL5znGR9iYD3KWubZThdQzsCdg2e")
+                .unwrap()
+                .to_bytes(),
+        ),
+        9,
+    );
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    let result = svm.send_transaction(tx);
+    assert!(result.is_ok());
+
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 1);
+    assert_eq!(reg.last_mapping_offset, 105);
+
+    // Remove mapping
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping.mint, bump);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    let result = svm.send_transaction(tx);
+    assert!(result.is_ok());
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 0);
+    assert_eq!(reg.last_mapping_offset, 0);
+}
+
+#[test]
+fn test_audit_log_records_changes() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Add and then close a mapping
+    let mint_mapping = MintMapping::new([9u8; 32], Some([4, u16::MAX, u16::MAX]), None, None, 6);
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping.mint, bump);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let data = svm.get_account(&audit_log_pda).unwrap().data;
+    let log = AuditLog::from_account_data(&data).unwrap();
+    assert_eq!(log.registry, state_pda.to_bytes());
+    assert_eq!(log.sequence, 2);
+    let records = AuditLog::records(&data).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].kind, AuditKind::AddMapping);
+    assert_eq!(records[0].mint, mint_mapping.mint);
+    assert_eq!(records[0].signer, fee_payer.pubkey().to_bytes());
+    let size = mint_mapping.serialized_size() as usize;
+    assert_eq!(
+        records[0].mapping_hash,
+        hash(&mint_mapping.to_bytes().unwrap()[..size])
+    );
+    assert_eq!(records[1].kind, AuditKind::CloseMapping);
+    assert_eq!(records[1].mapping_hash, [0u8; 32]);
+}
+
+#[test]
+fn test_separate_rent_funder_and_refund_destination() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    // The funder pays both the fee and the realloc rent, the authority only signs
+    let funder = Keypair::new();
+    svm.airdrop(&funder.pubkey(), 100000000).unwrap();
+    let authority_balance = svm.get_balance(&fee_payer.pubkey()).unwrap();
+    let mint_mapping = MintMapping::new([5u8; 32], None, Some([6u8; 32]), None, 9);
+    let ix = AddMapping {
+        funder_acc: Some(funder.pubkey()),
+        ..add_mapping_accounts(program_id, &fee_payer, state_pda)
+    }
+    .instruction(&AddMappingIxData {
+        mapping: mint_mapping,
+    });
+    let msg =
+        v0::Message::try_compile(&funder.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx =
+        VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&funder, &fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    assert_eq!(
+        svm.get_balance(&fee_payer.pubkey()).unwrap(),
+        authority_balance
+    );
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 1);
+
+    // Closing refunds the freed rent to the treasury instead of the authority
+    let treasury = Pubkey::new_unique();
+    svm.airdrop(&treasury, 100000000).unwrap();
+    let mut ix =
+        create_close_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping.mint, bump);
+    ix.accounts.push(AccountMeta::new(treasury, false));
+    let msg =
+        v0::Message::try_compile(&funder.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx =
+        VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&funder, &fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    assert!(svm.get_balance(&treasury).unwrap() > 100000000);
+    assert_eq!(
+        svm.get_balance(&fee_payer.pubkey()).unwrap(),
+        authority_balance
+    );
+}
+
+#[test]
+fn test_reserve_close_and_compact() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    // Initialize
+    let ix = create_initialize_registry_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    let msg = v0::Message::try_compile(
+        &fee_payer.pubkey(),
+        &[ix, audit_ix],
+        &[],
+        svm.latest_blockhash(),
+    )
+    .unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    // Reserve room for three full entries up front
+    let ix = create_reserve_capacity_ix(program_id, &fee_payer, state_pda, 3 * 105);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    let reserved_len = svm.get_account(&state_pda).unwrap().data.len();
+    assert_eq!(reserved_len, ScopeMappingRegistry::LEN + 3 * 105);
+
+    // Adds fill the reserved space without growing the account
+    let mut mints = [[0u8; 32]; 3];
+    for i in 0..3 {
+        mints[i][0] = i as u8 + 1;
+        let mint_mapping = MintMapping::new(
+            mints[i],
+            Some([i as u16; 3]),
+            Some([7u8; 32]),
+            Some([8u8; 32]),
+            6,
+        );
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+        let msg = v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash())
+            .unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+        svm.send_transaction(tx).unwrap();
+    }
+    assert_eq!(
+        svm.get_account(&state_pda).unwrap().data.len(),
+        reserved_len
+    );
+
+    // Closing the middle entry leaves a closed entry behind
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mints[1], bump);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 2);
+    assert_eq!(reg.used_bytes, 2 * 105);
+    assert_eq!(reg.last_mapping_offset, 3 * 105);
+    assert_eq!(reg.slack_bytes(), 105);
+    assert_eq!(get_mapping(&svm, &state_pda, 1).mint, mints[2]);
+
+    // Compacting drops the closed entry and shrinks the account
+    let lamports_before = svm.get_balance(&fee_payer.pubkey()).unwrap();
+    let ix = create_compact_ix(program_id, &fee_payer, state_pda, 0);
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), &[ix], &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[&fee_payer]).unwrap();
+    svm.send_transaction(tx).unwrap();
+    let reg = get_registry(&svm, &state_pda);
+    assert_eq!(reg.total_mappings, 2);
+    assert_eq!(reg.last_mapping_offset, 2 * 105);
+    assert_eq!(reg.allocated_bytes, 2 * 105);
+    assert_eq!(
+        svm.get_account(&state_pda).unwrap().data.len(),
+        ScopeMappingRegistry::LEN + 2 * 105
+    );
+    assert!(svm.get_balance(&fee_payer.pubkey()).unwrap() > lamports_before);
+    assert_eq!(get_mapping(&svm, &state_pda, 0).mint, mints[0]);
+    assert_eq!(get_mapping(&svm, &state_pda, 1).mint, mints[2]);
+}
+
+fn initialize_registry_and_audit_log(
+    svm: &mut LiteSVM,
+    fee_payer: &Keypair,
+    program_id: Pubkey,
+    state_pda: Pubkey,
+    bump: u8,
+) {
+    let ix = create_initialize_registry_ix(
+        program_id,
+        fee_payer,
+        state_pda,
+        bump,
+        fee_payer.pubkey().to_bytes(),
+    );
+    let audit_ix = create_initialize_audit_log_ix(program_id, fee_payer, state_pda);
+    send_ixs(svm, fee_payer, &[ix, audit_ix]).unwrap();
+}
+
+fn send_ixs(svm: &mut LiteSVM, fee_payer: &Keypair, ixs: &[Instruction]) -> TransactionResult {
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), ixs, &[], svm.latest_blockhash()).unwrap();
+    let tx = VersionedTransaction::try_new(VersionedMessage::V0(msg), &[fee_payer]).unwrap();
+    svm.send_transaction(tx)
+}
+
+fn create_get_mapping_ix(state_pda: Pubkey, mint: [u8; 32]) -> Instruction {
+    client::GetMapping {
+        state_acc: state_pda,
+    }
+    .instruction(&GetMappingIxData { mint })
+}
+
+#[test]
+fn test_get_mapping_returns_serialized_entry() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let mut mint_mapping = MintMapping::new(
+        [3u8; 32],
+        Some([0, u16::MAX, u16::MAX]),
+        Some([4u8; 32]),
+        None,
+        9,
+    );
+    mint_mapping
+        .add_source(SourceEntry::account(SourceType::Stork, [5u8; 32]))
+        .unwrap();
+    mint_mapping
+        .set_metadata(Some(TokenMetadata::new("SOL", "Wrapped SOL", 0).unwrap()))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let meta = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, [3u8; 32])],
+    )
+    .unwrap();
+    assert_eq!(meta.return_data.program_id, program_id);
+    assert_eq!(
+        &meta.return_data.data[..],
+        &mint_mapping.to_bytes().unwrap()[..mint_mapping.serialized_size() as usize]
+    );
+    let resolved = decode_mapping(&meta.return_data.data).unwrap();
+    assert_eq!(resolved.mapping, get_mapping(&svm, &state_pda, 0));
+    assert_eq!(resolved.multiplier, ALIAS_MULTIPLIER_ONE);
+
+    // Unknown and closed mints fail with MintNotFound
+    let not_found = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(MappingProgramError::MintNotFound as u32),
+    );
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, [9u8; 32])],
+    );
+    assert_eq!(result.unwrap_err().err, not_found);
+
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, [3u8; 32], bump);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    svm.expire_blockhash();
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, [3u8; 32])],
+    );
+    assert_eq!(result.unwrap_err().err, not_found);
+}
+
+fn create_initialize_price_cache_ix(fee_payer: &Keypair, state_pda: Pubkey) -> Instruction {
+    let (price_cache_pda, bump) = client::find_price_cache_address(&state_pda);
+    client::InitializePriceCache {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        price_cache_acc: price_cache_pda,
+    }
+    .instruction(&InitializePriceCacheIxData { bump })
+}
+
+fn create_refresh_prices_ix(
+    state_pda: Pubkey,
+    mints: &[[u8; 32]],
+    oracles: &[Pubkey],
+) -> Instruction {
+    let oracles: Vec<_> = oracles
+        .iter()
+        .map(|oracle| AccountMeta::new_readonly(*oracle, false))
+        .collect();
+    client::RefreshPrices {
+        state_acc: state_pda,
+        price_cache_acc: client::find_price_cache_address(&state_pda).0,
+    }
+    .instruction_with_remaining_accounts(&RefreshPricesIxData::new(mints).unwrap(), &oracles)
+}
+
+fn set_oracle_account(svm: &mut LiteSVM, owner: Pubkey, data: Vec<u8>) -> Pubkey {
+    let oracle = Pubkey::new_unique();
+    svm.set_account(
+        oracle,
+        Account {
+            lamports: 1_000_000_000,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    oracle
+}
+
+/// A fully verified Pyth `PriceUpdateV2` account
+fn pyth_price_data(price: i64, expo: i32, conf: u64, publish_time: i64) -> Vec<u8> {
+    let mut data = vec![0u8; 134];
+    data[40] = 1;
+    data[73..81].copy_from_slice(&price.to_le_bytes());
+    data[81..89].copy_from_slice(&conf.to_le_bytes());
+    data[89..93].copy_from_slice(&expo.to_le_bytes());
+    data[93..101].copy_from_slice(&publish_time.to_le_bytes());
+    data
+}
+
+/// A Switchboard On-Demand `PullFeedAccountData` account with an 18 decimal result
+fn switchboard_price_data(value: i128, std_dev: i128, last_update: i64) -> Vec<u8> {
+    let mut data = vec![0u8; 3208];
+    data[2216..2224].copy_from_slice(&last_update.to_le_bytes());
+    data[2264..2280].copy_from_slice(&value.to_le_bytes());
+    data[2280..2296].copy_from_slice(&std_dev.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_refresh_prices_caches_median() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let switchboard_program =
+        Pubkey::from_str("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv").unwrap();
+    // 100 and 102 with different exponents
+    let pyth = set_oracle_account(
+        &mut svm,
+        pyth_receiver,
+        pyth_price_data(10_000_000_000, -8, 5_000_000, 1_700_000_000),
+    );
+    let switchboard = set_oracle_account(
+        &mut svm,
+        switchboard_program,
+        switchboard_price_data(102 * 10i128.pow(18), 10i128.pow(18), 1_700_000_100),
+    );
+
+    let mint = [7u8; 32];
+    let mint_mapping = MintMapping::new(
+        mint,
+        None,
+        Some(pyth.to_bytes()),
+        Some(switchboard.to_bytes()),
+        9,
+    );
+    let ix = add_mapping_accounts(program_id, &fee_payer, state_pda)
+        .instruction_with_remaining_accounts(
+            &AddMappingIxData {
+                mapping: mint_mapping,
+            },
+            &[
+                AccountMeta::new_readonly(pyth, false),
+                AccountMeta::new_readonly(switchboard, false),
+            ],
+        );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    svm.warp_to_slot(1_000);
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[pyth, switchboard]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &mint).unwrap();
+    assert_eq!(cached.slot, 1_000);
+    // Median of the two, in the finer exponent, with the widest confidence and oldest timestamp
+    assert_eq!(cached.price.value, 1_010_000_000_000_000_000);
+    assert_eq!(cached.price.expo, -16);
+    assert_eq!(cached.price.conf, 10_000_000_000_000_000);
+    assert_eq!(cached.price.timestamp, 1_700_000_000);
+
+    let custom_error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+    // Every source must be read: leaving Switchboard out, or all of them, fails
+    svm.warp_to_slot(1_001);
+    for oracles in [[pyth, program_id], [program_id, program_id]] {
+        let ix = create_refresh_prices_ix(state_pda, &[mint], &oracles);
+        let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+        assert_eq!(
+            result.unwrap_err().err,
+            custom_error(MappingProgramError::InvalidOracleAccount)
+        );
+    }
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    assert_eq!(PriceCache::get_price(&data, &mint).unwrap().slot, 1_000);
+    // Accounts that are not the mapped ones, or not owned by the provider program
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[switchboard, pyth]);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::InvalidOracleAccount)
+    );
+    let mut account = svm.get_account(&pyth).unwrap();
+    account.owner = Pubkey::new_unique();
+    svm.set_account(pyth, account).unwrap();
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[pyth, switchboard]);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::OracleOwnerMismatch)
+    );
+    // Without a Pyth program in the provider config, no owner is accepted
+    let ix = create_set_provider_config_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        ProviderConfig {
+            scope_program: [0u8; 32],
+            pyth_program: [0u8; 32],
+            switchboard_program: switchboard_program.to_bytes(),
+            strict: 1,
+        },
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[pyth, switchboard]);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::OracleOwnerMismatch)
+    );
+    // Unmapped mint
+    let ix = create_refresh_prices_ix(state_pda, &[[8u8; 32]], &[]);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::MintNotFound)
+    );
+}
+
+#[test]
+fn test_alias_resolves_to_target() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let pyth = set_oracle_account(
+        &mut svm,
+        pyth_receiver,
+        pyth_price_data(150_000_000, -6, 1_000, 1_700_000_000),
+    );
+    let canonical = [1u8; 32];
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new(canonical, None, Some(pyth.to_bytes()), None, 9),
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    // A 2x alias, e.g. a wrapper holding two canonical tokens per unit
+    let wrapped = [2u8; 32];
+    let alias = MintMapping::new_alias(wrapped, canonical, Some(2 * ALIAS_MULTIPLIER_ONE), 6);
+    assert_eq!(
+        alias.serialized_size() as usize,
+        MintMapping::HEADER_LEN + MintAlias::LEN
+    );
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, alias);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_mapping(&svm, &state_pda, 1), alias);
+
+    let custom_error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+    // Aliases of unknown mints, of themselves, or with sources of their own are rejected
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new_alias([3u8; 32], [9u8; 32], None, 6),
+    );
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::MintNotFound)
+    );
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new_alias([3u8; 32], [3u8; 32], None, 6),
+    );
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::InvalidInstructionData)
+    );
+    let mut with_sources =
+        MintMapping::new([3u8; 32], Some([0, u16::MAX, u16::MAX]), None, None, 6);
+    with_sources
+        .set_alias(Some(MintAlias::new(canonical, None)))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, with_sources);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::InvalidInstructionData)
+    );
+
+    // GetMapping returns the canonical entry followed by the multiplier
+    let meta = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, wrapped)],
+    )
+    .unwrap();
+    let resolved = decode_mapping(&meta.return_data.data).unwrap();
+    assert_eq!(resolved.mapping, get_mapping(&svm, &state_pda, 0));
+    assert_eq!(resolved.multiplier, 2 * ALIAS_MULTIPLIER_ONE);
+
+    // Refreshing the alias reads the canonical sources and scales the price
+    let ix = create_refresh_prices_ix(state_pda, &[wrapped, canonical], &[pyth, pyth]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &canonical).unwrap();
+    assert_eq!((cached.price.value, cached.price.expo), (150_000_000, -6));
+    let cached = PriceCache::get_price(&data, &wrapped).unwrap();
+    assert_eq!(
+        (cached.price.value, cached.price.expo),
+        (300_000_000_000_000_000, -15)
+    );
+
+    // A second alias in the chain, then point the first one back at it to close a loop
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new_alias([4u8; 32], wrapped, None, 6),
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let mut account = svm.get_account(&state_pda).unwrap();
+    let (_, end) = ScopeMappingRegistry::mapping_offsets(&account.data)
+        .unwrap()
+        .nth(1)
+        .unwrap();
+    account.data[end - MintAlias::LEN..end - 8].copy_from_slice(&[4u8; 32]);
+    svm.set_account(state_pda, account).unwrap();
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, [4u8; 32])],
+    );
+    assert_eq!(
+        result.unwrap_err().err,
+        custom_error(MappingProgramError::AliasCycle)
+    );
+}
+
+/// An SPL stake pool account holding `total_lamports` for `pool_token_supply` pool tokens
+fn stake_pool_data(total_lamports: u64, pool_token_supply: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 611];
+    data[0] = 1;
+    data[258..266].copy_from_slice(&total_lamports.to_le_bytes());
+    data[266..274].copy_from_slice(&pool_token_supply.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_stake_pool_source_prices_in_quote() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let stake_pool_program =
+        Pubkey::from_str("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy").unwrap();
+    let pyth = set_oracle_account(
+        &mut svm,
+        pyth_receiver,
+        pyth_price_data(150_000_000, -6, 1_000, 1_700_000_000),
+    );
+    // 1.2 SOL per pool token
+    let pool = set_oracle_account(
+        &mut svm,
+        stake_pool_program,
+        stake_pool_data(1_200_000_000_000, 1_000_000_000_000),
+    );
+    let fake_pool = set_oracle_account(
+        &mut svm,
+        Pubkey::new_unique(),
+        stake_pool_data(1_200_000_000_000, 1_000_000_000_000),
+    );
+
+    let sol = [1u8; 32];
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new(sol, None, Some(pyth.to_bytes()), None, 9),
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let add_lst = |svm: &mut LiteSVM, mint: [u8; 32], pool: Pubkey, quote: [u8; 32]| {
+        let mut mint_mapping = MintMapping::new(mint, None, None, None, 9);
+        mint_mapping
+            .add_source(SourceEntry::stake_pool(pool.to_bytes(), quote))
+            .unwrap();
+        let ix = add_mapping_accounts(program_id, &fee_payer, state_pda)
+            .instruction_with_remaining_accounts(
+                &AddMappingIxData {
+                    mapping: mint_mapping,
+                },
+                &[AccountMeta::new_readonly(pool, false)],
+            );
+        send_ixs(svm, &fee_payer, &[ix])
+    };
+    let custom_error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+    // The quote mint must be mapped and the pool owned by a stake pool program
+    assert_eq!(
+        add_lst(&mut svm, [2u8; 32], pool, [9u8; 32])
+            .unwrap_err()
+            .err,
+        custom_error(MappingProgramError::MintNotFound)
+    );
+    assert_eq!(
+        add_lst(&mut svm, [2u8; 32], fake_pool, sol)
+            .unwrap_err()
+            .err,
+        custom_error(MappingProgramError::OracleOwnerMismatch)
+    );
+    let lst = [2u8; 32];
+    add_lst(&mut svm, lst, pool, sol).unwrap();
+
+    // Refreshing the quote first prices the pool token at 1.2 x 150
+    svm.warp_to_slot(10);
+    let ix = create_refresh_prices_ix(state_pda, &[sol, lst], &[pyth, pool]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &lst).unwrap();
+    assert_eq!(
+        (cached.price.value, cached.price.expo),
+        (180_000_000_000_000_000, -15)
+    );
+    assert_eq!(cached.price.timestamp, 1_700_000_000);
+
+    // A quote cached in an earlier slot is not used
+    svm.warp_to_slot(11);
+    let ix = create_refresh_prices_ix(state_pda, &[lst], &[pool]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        custom_error(MappingProgramError::PriceUnavailable)
+    );
+}
+
+#[test]
+fn test_fixed_price_with_guard_band() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let pyth = set_oracle_account(
+        &mut svm,
+        pyth_receiver,
+        pyth_price_data(99_000_000, -8, 10_000, 1_700_000_000),
+    );
+
+    // 1.00 ± 2% against the Pyth feed, and an unguarded 1.00 test token
+    let stable = [5u8; 32];
+    let mut stable_mapping = MintMapping::new(stable, None, Some(pyth.to_bytes()), None, 6);
+    stable_mapping
+        .add_source(SourceEntry::fixed(100, -2, Some((0, 200))))
+        .unwrap();
+    let test_token = [6u8; 32];
+    let mut test_mapping = MintMapping::new(test_token, None, None, None, 6);
+    test_mapping
+        .add_source(SourceEntry::fixed(100, -2, None))
+        .unwrap();
+    for mapping in [stable_mapping, test_mapping] {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+
+    // A guard band must reference a non-fixed source, and the price must be positive
+    let invalid_data = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(MappingProgramError::InvalidInstructionData as u32),
+    );
+    for source in [
+        SourceEntry::fixed(100, -2, Some((0, 200))),
+        SourceEntry::fixed(100, -2, Some((4, 200))),
+        SourceEntry::fixed(0, -2, None),
+    ] {
+        let mut mapping = MintMapping::new([7u8; 32], None, None, None, 6);
+        mapping.add_source(source).unwrap();
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+        let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+        assert_eq!(result.unwrap_err().err, invalid_data);
+    }
+
+    // Within the band both sources count, fixed prices take no account
+    let ix = create_refresh_prices_ix(state_pda, &[stable, test_token], &[pyth]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &stable).unwrap();
+    assert_eq!((cached.price.value, cached.price.expo), (99_500_000, -8));
+    assert_eq!(cached.price.timestamp, 1_700_000_000);
+    let cached = PriceCache::get_price(&data, &test_token).unwrap();
+    assert_eq!((cached.price.value, cached.price.expo), (100, -2));
+
+    // Off peg only the feed counts
+    let mut account = svm.get_account(&pyth).unwrap();
+    account.data = pyth_price_data(95_000_000, -8, 10_000, 1_700_000_100);
+    svm.set_account(pyth, account).unwrap();
+    let ix = create_refresh_prices_ix(state_pda, &[stable], &[pyth]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &stable).unwrap();
+    assert_eq!((cached.price.value, cached.price.expo), (95_000_000, -8));
+
+    // The reference of the guard band can't be left out
+    svm.expire_blockhash();
+    let ix = create_refresh_prices_ix(state_pda, &[stable], &[program_id]);
+    let result = send_ixs(&mut svm, &fee_payer, &[ix]);
+    assert_eq!(
+        result.unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::InvalidOracleAccount as u32)
+        )
+    );
+}
+
+#[test]
+fn test_price_selection_spot_ema_conservative() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    // Spot 100 ± 0.01, EMA 98 ± 0.05
+    let pyth_data = |spot: i64| {
+        let mut data = pyth_price_data(spot, -2, 1, 1_700_000_000);
+        data[109..117].copy_from_slice(&9_800i64.to_le_bytes());
+        data[117..125].copy_from_slice(&5u64.to_le_bytes());
+        data
+    };
+    let pyth_receiver = Pubkey::from_str("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ").unwrap();
+    let pyth = set_oracle_account(&mut svm, pyth_receiver, pyth_data(10_000));
+
+    let selections = [
+        PriceSelection::Spot,
+        PriceSelection::Ema,
+        PriceSelection::Conservative,
+    ];
+    let mints: Vec<[u8; 32]> = (1..=3u8).map(|i| [i; 32]).collect();
+    for (i, selection) in selections.into_iter().enumerate() {
+        let mut mapping = MintMapping::new(mints[i], None, Some(pyth.to_bytes()), None, 6);
+        mapping.set_price_selection(selection).unwrap();
+        assert_eq!(mapping.price_selection(), selection);
+        assert_eq!(mapping.has_attributes(), selection != PriceSelection::Spot);
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+        assert_eq!(get_mapping(&svm, &state_pda, i), mapping);
+    }
+
+    // Unknown selections are rejected
+    let mut mapping = MintMapping::new([4u8; 32], None, Some(pyth.to_bytes()), None, 6);
+    mapping
+        .set_attribute(Attribute::new(AttributeTag::PriceSelection as u8, &[3]).unwrap())
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::InvalidInstructionData as u32)
+        )
+    );
+
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let refresh = |svm: &mut LiteSVM| {
+        let ix = create_refresh_prices_ix(state_pda, &mints, &[pyth, pyth, pyth]);
+        send_ixs(svm, &fee_payer, &[ix]).unwrap();
+        let data = svm.get_account(&price_cache_pda).unwrap().data;
+        mints
+            .iter()
+            .map(|mint| {
+                let price = PriceCache::get_price(&data, mint).unwrap().price;
+                (price.value, price.conf)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(refresh(&mut svm), [(10_000, 1), (9_800, 5), (9_800, 5)]);
+
+    // Conservative follows spot once it drops below the EMA
+    let mut account = svm.get_account(&pyth).unwrap();
+    account.data = pyth_data(9_700);
+    svm.set_account(pyth, account).unwrap();
+    svm.expire_blockhash();
+    assert_eq!(refresh(&mut svm), [(9_700, 1), (9_800, 5), (9_700, 5)]);
+}
+
+fn create_set_quote_conversion_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    quote: QuoteCurrency,
+    mint: [u8; 32],
+) -> Instruction {
+    client::SetQuoteConversion {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&SetQuoteConversionIxData { quote, mint })
+}
+
+#[test]
+fn test_quote_currency_converts_to_usd() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+    let error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // SOL at 150.00 USD, and a token at 0.5 SOL
+    let wsol = [1u8; 32];
+    let mut wsol_mapping = MintMapping::new(wsol, None, None, None, 9);
+    wsol_mapping
+        .add_source(SourceEntry::fixed(15_000, -2, None))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, wsol_mapping);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let token = [2u8; 32];
+    let mut token_mapping = MintMapping::new(token, None, None, None, 6);
+    token_mapping
+        .add_source(SourceEntry::fixed(5, -1, None))
+        .unwrap();
+    token_mapping
+        .set_quote_currency(QuoteCurrency::Sol)
+        .unwrap();
+    assert_eq!(token_mapping.quote_currency(), QuoteCurrency::Sol);
+
+    // SOL quotes need a conversion mint first
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, token_mapping);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, std::slice::from_ref(&ix))
+            .unwrap_err()
+            .err,
+        error(MappingProgramError::InvalidQuoteConversion)
+    );
+
+    // The conversion mint must be mapped, and USD has no conversion
+    let set_conversion = |quote, mint| {
+        create_set_quote_conversion_ix(program_id, &fee_payer, state_pda, quote, mint)
+    };
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[set_conversion(QuoteCurrency::Sol, [9u8; 32])],
+    );
+    assert_eq!(
+        result.unwrap_err().err,
+        error(MappingProgramError::MintNotFound)
+    );
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[set_conversion(QuoteCurrency::Usd, wsol)],
+    );
+    assert_eq!(
+        result.unwrap_err().err,
+        error(MappingProgramError::InvalidQuoteConversion)
+    );
+
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[set_conversion(QuoteCurrency::Sol, wsol)],
+    )
+    .unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.sol_usd_mint, wsol);
+    assert_eq!(registry.conversion_mint(QuoteCurrency::Sol), Some(wsol));
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    // Without a SOL price cached in the same slot the token can't be converted
+    let ix = create_refresh_prices_ix(state_pda, &[token], &[]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::PriceUnavailable)
+    );
+
+    let ix = create_refresh_prices_ix(state_pda, &[wsol, token], &[]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let data = svm
+        .get_account(&client::find_price_cache_address(&state_pda).0)
+        .unwrap()
+        .data;
+
+    let cached = PriceCache::get_price(&data, &wsol).unwrap();
+    assert_eq!(cached.quote, QuoteCurrency::Usd);
+    assert_eq!(cached.native, cached.price);
+
+    let cached = PriceCache::get_price(&data, &token).unwrap();
+    assert_eq!(cached.quote, QuoteCurrency::Sol);
+    assert_eq!((cached.native.value, cached.native.expo), (5, -1));
+    assert_eq!(cached.price.value_in(-3), Some(75_000));
+}
+
+fn create_prune_expired_ix(
+    program_id: Pubkey,
+    authority: Pubkey,
+    state_pda: Pubkey,
+    max_entries: u8,
+) -> Instruction {
+    client::PruneExpired {
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        authority_acc: authority,
+    }
+    .instruction(&PruneExpiredIxData { max_entries })
+}
+
+#[test]
+fn test_validity_window_and_prune_expired() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    svm.warp_to_slot(100);
+    let error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // A listing going live at slot 200, a temporary mapping expiring at 150 and a regular one
+    let windows = [
+        ValidityWindow::new(Some(200), None),
+        ValidityWindow::new(None, Some(150)),
+        ValidityWindow::default(),
+    ];
+    let mints: Vec<[u8; 32]> = (1..=3u8).map(|i| [i; 32]).collect();
+    let mut mappings = vec![];
+    for (mint, window) in mints.iter().zip(windows) {
+        let mut mapping = MintMapping::new(*mint, Some([4, u16::MAX, u16::MAX]), None, None, 6);
+        mapping.set_validity_window(window).unwrap();
+        assert_eq!(mapping.validity_window(), window);
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+        mappings.push(mapping);
+    }
+    assert!(!mappings[2].has_attributes());
+
+    // Windows that already ended or never open are rejected
+    let mut mapping = MintMapping::new([4u8; 32], Some([4, u16::MAX, u16::MAX]), None, None, 6);
+    mapping
+        .set_validity_window(ValidityWindow::new(None, Some(50)))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::MappingNotActive)
+    );
+    mapping
+        .set_validity_window(ValidityWindow::new(Some(300), Some(300)))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidInstructionData)
+    );
+
+    // Lookups only see mappings inside their window
+    let data = svm.get_account(&state_pda).unwrap().data;
+    assert!(matches!(
+        MintMapping::resolve(&data, &mints[0], 100),
+        Err(MappingProgramError::MappingNotActive)
+    ));
+    assert_eq!(
+        MintMapping::get_mapping_details(&data, &mints[0], 200).ok(),
+        Some(mappings[0])
+    );
+    assert_eq!(
+        MintMapping::get_mapping_details(&data, &mints[1], 149).ok(),
+        Some(mappings[1])
+    );
+    assert!(matches!(
+        MintMapping::resolve(&data, &mints[1], 150),
+        Err(MappingProgramError::MappingNotActive)
+    ));
+
+    let get_mapping_ix = |mint| create_get_mapping_ix(state_pda, mint);
+    let result = send_ixs(&mut svm, &fee_payer, &[get_mapping_ix(mints[0])]);
+    assert_eq!(
+        result.unwrap_err().err,
+        error(MappingProgramError::MappingNotActive)
+    );
+    send_ixs(&mut svm, &fee_payer, &[get_mapping_ix(mints[1])]).unwrap();
+
+    // Nothing to prune before the temporary mapping expires
+    let prune = create_prune_expired_ix(program_id, fee_payer.pubkey(), state_pda, 8);
+    send_ixs(&mut svm, &fee_payer, std::slice::from_ref(&prune)).unwrap();
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 3);
+
+    svm.warp_to_slot(200);
+    svm.expire_blockhash();
+    send_ixs(&mut svm, &fee_payer, &[get_mapping_ix(mints[0])]).unwrap();
+    let result = send_ixs(&mut svm, &fee_payer, &[get_mapping_ix(mints[1])]);
+    assert_eq!(
+        result.unwrap_err().err,
+        error(MappingProgramError::MappingNotActive)
+    );
+
+    // The freed rent can only go to the registry authority
+    let ix = create_prune_expired_ix(program_id, Pubkey::new_unique(), state_pda, 8);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::PdaMismatch)
+    );
+
+    let before = svm.get_account(&state_pda).unwrap();
+    send_ixs(&mut svm, &fee_payer, &[prune]).unwrap();
+    let after = svm.get_account(&state_pda).unwrap();
+    let pruned_size = mappings[1].serialized_size() as usize;
+    assert_eq!(after.data.len(), before.data.len() - pruned_size);
+    assert!(after.lamports < before.lamports);
+
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.total_mappings, 2);
+    assert_eq!(get_mapping(&svm, &state_pda, 0), mappings[0]);
+    assert_eq!(get_mapping(&svm, &state_pda, 1), mappings[2]);
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    let record = records.last().unwrap();
+    assert_eq!(record.kind, AuditKind::PruneExpired);
+    assert_eq!(record.mint, mints[1]);
+    assert_eq!(record.signer, [0u8; 32]);
+}
+
+fn create_set_delegate_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    mint: [u8; 32],
+    delegate: [u8; 32],
+) -> Instruction {
+    client::SetDelegate {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&SetDelegateIxData { mint, delegate })
+}
+
+fn create_update_sources_ix(
+    program_id: Pubkey,
+    signer: Pubkey,
+    state_pda: Pubkey,
+    mapping: MintMapping,
+) -> Instruction {
+    client::UpdateSources {
+        signer_acc: signer,
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&UpdateSourcesIxData { mapping })
+}
+
+#[test]
+fn test_delegate_updates_sources_only() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    let error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+    let delegate = Keypair::new();
+    let stranger = Keypair::new();
+    svm.airdrop(&delegate.pubkey(), 10_000_000).unwrap();
+    svm.airdrop(&stranger.pubkey(), 10_000_000).unwrap();
+
+    let mint = [7u8; 32];
+    let pyth = Pubkey::new_unique().to_bytes();
+    let mapping = MintMapping::new(mint, None, Some(pyth), None, 6);
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mapping);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    // Nobody but the authority may update the sources before a delegate is set
+    let mut rotated = mapping;
+    rotated.set_pyth_account(Some(Pubkey::new_unique().to_bytes()));
+    let ix = create_update_sources_ix(program_id, delegate.pubkey(), state_pda, rotated);
+    assert_eq!(
+        send_ixs(&mut svm, &delegate, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidDelegate)
+    );
+
+    let ix = create_set_delegate_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        mint,
+        delegate.pubkey().to_bytes(),
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let delegated = get_mapping(&svm, &state_pda, 0);
+    assert_eq!(delegated.delegate(), Some(delegate.pubkey().to_bytes()));
+    assert_eq!(delegated.get_pyth_account(), Some(pyth));
+
+    // The delegate rotates the Pyth account, everything else stays
+    let rotated = delegated.with_sources_of(&rotated).unwrap();
+    let ix = create_update_sources_ix(program_id, delegate.pubkey(), state_pda, rotated);
+    send_ixs(&mut svm, &delegate, &[ix]).unwrap();
+    assert_eq!(get_mapping(&svm, &state_pda, 0), rotated);
+    assert_eq!(get_registry(&svm, &state_pda).total_mappings, 1);
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    let record = records.last().unwrap();
+    assert_eq!(record.kind, AuditKind::UpdateSources);
+    assert_eq!(record.signer, delegate.pubkey().to_bytes());
+
+    // Decimals and other mints are out of reach
+    let mut redecimaled = rotated;
+    redecimaled.decimals = 9;
+    let ix = create_update_sources_ix(program_id, delegate.pubkey(), state_pda, redecimaled);
+    assert_eq!(
+        send_ixs(&mut svm, &delegate, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidInstructionData)
+    );
+    let mut revoked = rotated;
+    revoked.set_delegate(None).unwrap();
+    let ix = create_update_sources_ix(program_id, delegate.pubkey(), state_pda, revoked);
+    assert_eq!(
+        send_ixs(&mut svm, &delegate, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidInstructionData)
+    );
+    let ix = create_update_sources_ix(program_id, stranger.pubkey(), state_pda, rotated);
+    assert_eq!(
+        send_ixs(&mut svm, &stranger, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidDelegate)
+    );
+
+    // Closing stays with the authority
+    let ix = create_close_mapping_ix(program_id, &delegate, state_pda, mint, bump);
+    assert!(send_ixs(&mut svm, &delegate, &[ix]).is_err());
+
+    // Revoking removes the delegate again
+    let ix = create_set_delegate_ix(program_id, &fee_payer, state_pda, mint, [0u8; 32]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let mapping = get_mapping(&svm, &state_pda, 0);
+    assert_eq!(mapping.delegate(), None);
+    assert!(!mapping.has_attributes());
+
+    let mut rotated_again = mapping;
+    rotated_again.set_pyth_account(Some(pyth));
+    let ix = create_update_sources_ix(program_id, delegate.pubkey(), state_pda, rotated_again);
+    assert_eq!(
+        send_ixs(&mut svm, &delegate, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidDelegate)
+    );
+
+    // The authority may still update the sources itself
+    let ix = create_update_sources_ix(program_id, fee_payer.pubkey(), state_pda, rotated_again);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_mapping(&svm, &state_pda, 0), rotated_again);
+}
+
+fn create_set_rate_limit_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    guardian: Option<Pubkey>,
+    ix_data: SetRateLimitIxData,
+) -> Instruction {
+    client::SetRateLimit {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        guardian_acc: guardian,
+    }
+    .instruction(&ix_data)
+}
+
+fn send_ixs_with_cosigner(
+    svm: &mut LiteSVM,
+    fee_payer: &Keypair,
+    cosigner: &Keypair,
+    ixs: &[Instruction],
+) -> TransactionResult {
+    let msg =
+        v0::Message::try_compile(&fee_payer.pubkey(), ixs, &[], svm.latest_blockhash()).unwrap();
+    let tx =
+        VersionedTransaction::try_new(VersionedMessage::V0(msg), &[fee_payer, cosigner]).unwrap();
+    svm.send_transaction(tx)
+}
+
+#[test]
+fn test_rate_limit_with_guardian_override() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    svm.warp_to_slot(10);
+    let guardian = Keypair::new();
+
+    // Two changes per 100 slots
+    let ix = create_set_rate_limit_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        None,
+        SetRateLimitIxData {
+            max_mutations: 2,
+            window_slots: 100,
+            guardian: guardian.pubkey().to_bytes(),
+        },
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let rate_limit = get_registry(&svm, &state_pda).rate_limit;
+    assert_eq!(rate_limit.guardian(), Some(&guardian.pubkey().to_bytes()));
+    assert_eq!(rate_limit.remaining(10, 0), Some(2));
+
+    let mappings: Vec<MintMapping> = (1..=3u8)
+        .map(|i| MintMapping::new([i; 32], Some([4, u16::MAX, u16::MAX]), None, None, 6))
+        .collect();
+    for mapping in &mappings[..2] {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mappings[2]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::RateLimitExceeded as u32)
+        )
+    );
+
+    // The guardian co-signs to go past the limit, passed after the oracle accounts
+    let guardian_meta = AccountMeta::new_readonly(guardian.pubkey(), true);
+    let ix = add_mapping_accounts(program_id, &fee_payer, state_pda)
+        .instruction_with_remaining_accounts(
+            &AddMappingIxData {
+                mapping: mappings[2],
+            },
+            std::slice::from_ref(&guardian_meta),
+        );
+    send_ixs_with_cosigner(&mut svm, &fee_payer, &guardian, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.total_mappings, 3);
+    assert_eq!(registry.rate_limit.mutations, 3);
+    assert_eq!(registry.rate_limit.remaining(50, 0), Some(0));
+
+    // Lifting the limit needs the guardian too
+    let lift = SetRateLimitIxData {
+        max_mutations: 0,
+        window_slots: 0,
+        guardian: [0u8; 32],
+    };
+    let ix = create_set_rate_limit_ix(program_id, &fee_payer, state_pda, None, lift);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+
+    // The counter resets in the next window
+    svm.warp_to_slot(110);
+    svm.expire_blockhash();
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mappings[0].mint, bump);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let rate_limit = get_registry(&svm, &state_pda).rate_limit;
+    assert_eq!(rate_limit.window, 1);
+    assert_eq!(rate_limit.mutations, 1);
+
+    let ix = create_set_rate_limit_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        Some(guardian.pubkey()),
+        lift,
+    );
+    send_ixs_with_cosigner(&mut svm, &fee_payer, &guardian, &[ix]).unwrap();
+    let rate_limit = get_registry(&svm, &state_pda).rate_limit;
+    assert_eq!(rate_limit.remaining(110, 0), None);
+    assert_eq!(rate_limit.guardian(), None);
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    assert_eq!(records.last().unwrap().kind, AuditKind::SetRateLimit);
+}
+
+fn create_set_disabled_providers_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    disabled_providers: u8,
+) -> Instruction {
+    client::SetDisabledProviders {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&SetDisabledProvidersIxData { disabled_providers })
+}
+
+#[test]
+fn test_disabled_providers_are_left_out() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    // Scope, Pyth and Switchboard, plus a Stork entry and a fixed price guarded by the Pyth feed
+    let mint = [3u8; 32];
+    let mut mint_mapping = MintMapping::new(
+        mint,
+        Some([0, u16::MAX, u16::MAX]),
+        Some([4u8; 32]),
+        Some([6u8; 32]),
+        9,
+    );
+    mint_mapping
+        .add_source(SourceEntry::account(SourceType::Stork, [5u8; 32]))
+        .unwrap();
+    mint_mapping
+        .add_source(SourceEntry::fixed(100, -2, Some((1, 200))))
+        .unwrap();
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mint_mapping);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let ix = create_set_disabled_providers_ix(program_id, &fee_payer, state_pda, 0b010);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_registry(&svm, &state_pda).disabled_providers, 0b010);
+
+    // Pyth is gone and takes the fixed price it guarded with it
+    let mut expected = MintMapping::new(
+        mint,
+        Some([0, u16::MAX, u16::MAX]),
+        None,
+        Some([6u8; 32]),
+        9,
+    );
+    expected
+        .add_source(SourceEntry::account(SourceType::Stork, [5u8; 32]))
+        .unwrap();
+    let data = svm.get_account(&state_pda).unwrap().data;
+    let resolved = MintMapping::resolve(&data, &mint, 0).ok().unwrap();
+    assert_eq!(resolved.mapping, expected);
+    let meta = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_get_mapping_ix(state_pda, mint)],
+    )
+    .unwrap();
+    assert_eq!(
+        decode_mapping(&meta.return_data.data).unwrap().mapping,
+        expected
+    );
+    // The stored entry is untouched
+    assert_eq!(get_mapping(&svm, &state_pda, 0), mint_mapping);
+
+    // Guard bands follow their reference when an earlier source is dropped
+    let without_scope = mint_mapping.without_providers(0b001).unwrap();
+    let fixed = without_scope
+        .oracle_sources()
+        .last()
+        .unwrap()
+        .fixed_price()
+        .unwrap();
+    assert_eq!(fixed.guard(), Some((0, 200)));
+
+    // Only provider bits can be set
+    let ix = create_set_disabled_providers_ix(program_id, &fee_payer, state_pda, 0b1000);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::InvalidInstructionData as u32)
+        )
+    );
+
+    // Clearing the mask brings the sources back
+    let ix = create_set_disabled_providers_ix(program_id, &fee_payer, state_pda, 0);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let data = svm.get_account(&state_pda).unwrap().data;
+    assert_eq!(
+        MintMapping::resolve(&data, &mint, 0).ok().unwrap().mapping,
+        mint_mapping
+    );
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    assert_eq!(
+        records.last().unwrap().kind,
+        AuditKind::SetDisabledProviders
+    );
+}
+
+fn create_snapshot_ix(fee_payer: &Keypair, state_pda: Pubkey, number: u64) -> Instruction {
+    let (snapshot_pda, bump) = client::find_snapshot_address(&state_pda, number);
+    client::Snapshot {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        snapshot_acc: snapshot_pda,
+    }
+    .instruction(&SnapshotIxData { number, bump })
+}
+
+#[test]
+fn test_snapshot_pins_registry_state() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let mappings: Vec<MintMapping> = (1..=2u8)
+        .map(|i| MintMapping::new([i; 32], None, Some([i + 10; 32]), None, 6))
+        .collect();
+    for mapping in &mappings {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+
+    svm.warp_to_slot(50);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_snapshot_ix(&fee_payer, state_pda, 1)],
+    )
+    .unwrap();
+    let registry_data = svm.get_account(&state_pda).unwrap().data;
+    let registry = ScopeMappingRegistry::from_account_data(&registry_data).unwrap();
+
+    // The registry keeps changing after the snapshot
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mappings[0].mint, bump);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let ix = create_add_mapping_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        MintMapping::new([3u8; 32], None, Some([13u8; 32]), None, 6),
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let snapshot_pda = client::find_snapshot_address(&state_pda, 1).0;
+    let account = svm.get_account(&snapshot_pda).unwrap();
+    assert_eq!(account.owner, program_id);
+    let snapshot = RegistrySnapshot::from_account_data(&account.data).unwrap();
+    assert_eq!(snapshot.number, 1);
+    assert_eq!(snapshot.slot, 50);
+    assert_eq!(snapshot.registry, state_pda.to_bytes());
+    assert_eq!(snapshot.registry_version, registry.version);
+    let written = ScopeMappingRegistry::LEN + registry.last_mapping_offset as usize;
+    assert_eq!(snapshot.data_len as usize, written);
+    assert_eq!(snapshot.content_hash, hash(&registry_data[..written]));
+
+    // Opens like the registry did when the snapshot was taken
+    let data = RegistrySnapshot::registry_data(&account.data).unwrap();
+    assert_eq!(
+        ScopeMappingRegistry::from_account_data(data).unwrap(),
+        registry
+    );
+    let snapshot_mappings: Vec<MintMapping> = ScopeMappingRegistry::mapping_offsets(data)
+        .unwrap()
+        .map(|(start, end)| MintMapping::from_bytes(&data[start..end]).unwrap())
+        .collect();
+    assert_eq!(snapshot_mappings, mappings);
+    assert_eq!(
+        MintMapping::resolve(data, &mappings[0].mint, 50)
+            .ok()
+            .unwrap()
+            .mapping,
+        mappings[0]
+    );
+    assert!(MintMapping::resolve(data, &[3u8; 32], 50).is_err());
+
+    // A tampered copy fails the content hash
+    let mut tampered = account.data.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(RegistrySnapshot::registry_data(&tampered).is_err());
+
+    // A snapshot number can only be taken once
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_snapshot_ix(&fee_payer, state_pda, 1)],
+    );
+    assert_eq!(
+        result.unwrap_err().err,
+        TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized)
+    );
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_snapshot_ix(&fee_payer, state_pda, 2)],
+    )
+    .unwrap();
+    let account = svm
+        .get_account(&client::find_snapshot_address(&state_pda, 2).0)
+        .unwrap();
+    let data = RegistrySnapshot::registry_data(&account.data).unwrap();
+    assert_eq!(
+        ScopeMappingRegistry::mapping_offsets(data).unwrap().count(),
+        2
+    );
+    assert!(MintMapping::resolve(data, &mappings[0].mint, 50).is_err());
+}
+
+fn create_initialize_draft_ix(fee_payer: &Keypair, state_pda: Pubkey) -> Instruction {
+    let (draft_pda, bump) = client::find_draft_address(&state_pda);
+    client::InitializeDraft {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        draft_acc: draft_pda,
+    }
+    .instruction(&InitializeDraftIxData { bump })
+}
+
+fn create_promote_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    expected_version: u32,
+) -> Instruction {
+    client::Promote {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        draft_acc: client::find_draft_address(&state_pda).0,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction(&PromoteIxData { expected_version })
+}
+
+#[test]
+fn test_draft_is_promoted_atomically() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let mappings: Vec<MintMapping> = (1..=3u8)
+        .map(|i| MintMapping::new([i; 32], None, Some([i + 10; 32]), None, 6))
+        .collect();
+    for mapping in &mappings[..2] {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+    let live = get_registry(&svm, &state_pda);
+
+    // The draft mirrors the live registry and records its edits in its own audit log
+    let (draft_pda, draft_bump) = client::find_draft_address(&state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[
+            create_initialize_draft_ix(&fee_payer, state_pda),
+            create_initialize_audit_log_ix(program_id, &fee_payer, draft_pda),
+        ],
+    )
+    .unwrap();
+    let draft = get_registry(&svm, &draft_pda);
+    assert_eq!(draft.draft_of(), Some(&state_pda.to_bytes()));
+    assert_eq!(draft.bump, draft_bump);
+    assert_eq!(draft.version, live.version);
+    assert_eq!(get_mapping(&svm, &draft_pda, 1), mappings[1]);
+
+    let ix = create_add_mapping_ix(program_id, &fee_payer, draft_pda, mappings[2]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let ix = create_close_mapping_ix(
+        program_id,
+        &fee_payer,
+        draft_pda,
+        mappings[0].mint,
+        draft_bump,
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    // The live registry doesn't see the edits yet
+    assert_eq!(get_registry(&svm, &state_pda), live);
+    // And a draft can't stand in for the live registry where only that one is accepted
+    let result = send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_snapshot_ix(&fee_payer, draft_pda, 1)],
+    );
+    assert!(result.is_err());
+
+    let version_mismatch = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(MappingProgramError::VersionMismatch as u32),
+    );
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, live.version + 1);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        version_mismatch
+    );
+
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, live.version);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.version, live.version + 1);
+    assert_eq!(registry.bump, bump);
+    assert_eq!(registry.draft_of(), None);
+    assert_eq!(registry.total_mappings, 2);
+    assert_eq!(get_mapping(&svm, &state_pda, 0), mappings[1]);
+    assert_eq!(get_mapping(&svm, &state_pda, 1), mappings[2]);
+    assert_eq!(
+        svm.get_account(&draft_pda)
+            .map_or(0, |account| account.lamports),
+        0
+    );
+
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    let record = records.last().unwrap();
+    assert_eq!(record.kind, AuditKind::Promote);
+    let data = svm.get_account(&state_pda).unwrap().data;
+    let end = ScopeMappingRegistry::LEN + registry.last_mapping_offset as usize;
+    assert_eq!(record.mapping_hash, hash(&data[..end]));
+
+    // A draft taken before a live edit can't overwrite it
+    svm.expire_blockhash();
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_draft_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+    let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mappings[1].mint, bump);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, registry.version);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        version_mismatch
+    );
+}
+
+#[test]
+fn test_promote_counts_against_the_live_rate_limit() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    svm.warp_to_slot(10);
+    let guardian = Keypair::new();
+
+    // One change per 1000 slots on the live registry
+    let limit = SetRateLimitIxData {
+        max_mutations: 1,
+        window_slots: 1_000,
+        guardian: guardian.pubkey().to_bytes(),
+    };
+    let ix = create_set_rate_limit_ix(program_id, &fee_payer, state_pda, None, limit);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    // The draft lifts the limit it copied, with the guardian, and takes two changes
+    let (draft_pda, _) = client::find_draft_address(&state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[
+            create_initialize_draft_ix(&fee_payer, state_pda),
+            create_initialize_audit_log_ix(program_id, &fee_payer, draft_pda),
+        ],
+    )
+    .unwrap();
+    let lift = SetRateLimitIxData {
+        max_mutations: 0,
+        window_slots: 0,
+        guardian: [0u8; 32],
+    };
+    let ix = create_set_rate_limit_ix(
+        program_id,
+        &fee_payer,
+        draft_pda,
+        Some(guardian.pubkey()),
+        lift,
+    );
+    send_ixs_with_cosigner(&mut svm, &fee_payer, &guardian, &[ix]).unwrap();
+    for i in 1..=2u8 {
+        let mapping = MintMapping::new([i; 32], None, Some([i + 10; 32]), None, 6);
+        let ix = create_add_mapping_ix(program_id, &fee_payer, draft_pda, mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+
+    // Promoting keeps the live limit and uses up its window
+    let live = get_registry(&svm, &state_pda);
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, live.version);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.total_mappings, 2);
+    assert_eq!(registry.rate_limit.max_mutations, 1);
+    assert_eq!(registry.rate_limit.mutations, 1);
+    assert_eq!(
+        registry.rate_limit.guardian(),
+        Some(&guardian.pubkey().to_bytes())
+    );
+
+    // A second promotion in the same window needs the guardian
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_draft_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, registry.version);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::RateLimitExceeded as u32)
+        )
+    );
+    let ix = client::Promote {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        draft_acc: draft_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+    }
+    .instruction_with_remaining_accounts(
+        &PromoteIxData {
+            expected_version: registry.version,
+        },
+        &[AccountMeta::new_readonly(guardian.pubkey(), true)],
+    );
+    send_ixs_with_cosigner(&mut svm, &fee_payer, &guardian, &[ix]).unwrap();
+    assert_eq!(get_registry(&svm, &state_pda).rate_limit.mutations, 2);
+}
+
+#[test]
+fn test_promote_needs_reserved_capacity_past_one_realloc() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+
+    let (draft_pda, draft_bump) = client::find_draft_address(&state_pda);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[
+            create_initialize_draft_ix(&fee_payer, state_pda),
+            create_initialize_audit_log_ix(program_id, &fee_payer, draft_pda),
+        ],
+    )
+    .unwrap();
+
+    // 140-byte entries, well past one realloc in total, with the first one closed again
+    let mappings: Vec<MintMapping> = (1..=80u8)
+        .map(|i| {
+            let mut mapping = MintMapping::new([i; 32], None, Some([i; 32]), Some([i; 32]), 6);
+            mapping
+                .set_metadata(Some(
+                    TokenMetadata::new("SYMBOL", "A token with a long enough name", 0).unwrap(),
+                ))
+                .unwrap();
+            mapping
+        })
+        .collect();
+    for mapping in &mappings {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, draft_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+    let ix = create_close_mapping_ix(
+        program_id,
+        &fee_payer,
+        draft_pda,
+        mappings[0].mint,
+        draft_bump,
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let draft = get_registry(&svm, &draft_pda);
+    let live = get_registry(&svm, &state_pda);
+    assert!(draft.used_bytes < draft.last_mapping_offset);
+    assert!(draft.used_bytes as usize > live.allocated_bytes as usize + 10 * 1024);
+
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, live.version);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::InsufficientCapacity as u32)
+        )
+    );
+
+    // Reserving the room takes one realloc per instruction
+    let missing = draft.used_bytes - live.allocated_bytes;
+    let ixs: Vec<Instruction> = [10 * 1024, missing - 10 * 1024]
+        .into_iter()
+        .map(|bytes| create_reserve_capacity_ix(program_id, &fee_payer, state_pda, bytes as u16))
+        .collect();
+    send_ixs(&mut svm, &fee_payer, &ixs).unwrap();
+    let live = get_registry(&svm, &state_pda);
+    assert_eq!(live.allocated_bytes, draft.used_bytes);
+
+    // Only the live entries of the draft are promoted
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, live.version);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.total_mappings, 79);
+    assert_eq!(registry.last_mapping_offset, draft.used_bytes);
+    assert_eq!(registry.used_bytes, draft.used_bytes);
+    let data = svm.get_account(&state_pda).unwrap().data;
+    assert_eq!(
+        data.len(),
+        ScopeMappingRegistry::LEN + draft.used_bytes as usize
+    );
+    assert_eq!(registry.mappings_root, draft.mappings_root);
+    assert_eq!(mappings_root(&data).unwrap(), registry.mappings_root);
+    for (index, mapping) in mappings[1..].iter().enumerate() {
+        assert_eq!(get_mapping(&svm, &state_pda, index), *mapping);
+    }
+}
+
+#[test]
+fn test_mappings_root_tracks_entries() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    assert_eq!(get_registry(&svm, &state_pda).mappings_root, [0u8; 32]);
+
+    let mappings: Vec<MintMapping> = (1..=5u8)
+        .map(|i| MintMapping::new([i; 32], None, Some([i + 10; 32]), None, 6))
+        .collect();
+    for mapping in &mappings {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+    let data = svm.get_account(&state_pda).unwrap().data;
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.mappings_root, mappings_root(&data).unwrap());
+
+    // Every entry proves against the header root, and only as itself
+    for (index, mapping) in mappings.iter().enumerate() {
+        let proof = MappingProof::build(&data, &mapping.mint).unwrap();
+        assert_eq!(proof.index, index as u32);
+        assert_eq!(proof.leaf_count, 5);
+        assert_eq!(proof.version, registry.version);
+        assert_eq!(proof.mapping().unwrap(), *mapping);
+        assert!(proof.verify(&registry.mappings_root));
+
+        let mut tampered = proof.clone();
+        tampered.entry[40] ^= 1;
+        assert!(!tampered.verify(&registry.mappings_root));
+        assert!(!verify_inclusion(
+            &registry.mappings_root,
+            &proof.entry,
+            (index as u32 + 1) % 5,
+            proof.leaf_count,
+            &proof.siblings,
+        ));
+    }
+
+    // Closing entries updates the root; three leaves pair the first two and move the last up
+    for mapping in &mappings[..2] {
+        let ix = create_close_mapping_ix(program_id, &fee_payer, state_pda, mapping.mint, bump);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+    let data = svm.get_account(&state_pda).unwrap().data;
+    let root = get_registry(&svm, &state_pda).mappings_root;
+    let leaves: Vec<[u8; 32]> = mappings[2..]
+        .iter()
+        .map(|mapping| {
+            leaf_hash(&mapping.to_bytes().unwrap()[..mapping.serialized_size() as usize])
+        })
+        .collect();
+    assert_eq!(
+        root,
+        node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2])
+    );
+    let proof = MappingProof::build(&data, &mappings[4].mint).unwrap();
+    assert_eq!(proof.siblings, vec![node_hash(&leaves[0], &leaves[1])]);
+    assert!(proof.verify(&root));
+    assert!(MappingProof::build(&data, &mappings[0].mint).is_err());
+
+    // Compaction moves entries without changing them
+    let ix = create_compact_ix(program_id, &fee_payer, state_pda, 0);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let data = svm.get_account(&state_pda).unwrap().data;
+    assert_eq!(get_registry(&svm, &state_pda).mappings_root, root);
+    assert_eq!(mappings_root(&data).unwrap(), root);
+}
+
+fn create_add_page_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    index: u32,
+) -> Instruction {
+    let (page_pda, bump) = client::find_page_address(&state_pda, index);
+    let pages: Vec<AccountMeta> = (1..index)
+        .map(|index| {
+            AccountMeta::new_readonly(client::find_page_address(&state_pda, index).0, false)
+        })
+        .collect();
+    client::AddPage {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        page_acc: page_pda,
+    }
+    .instruction_with_remaining_accounts(&AddPageIxData { bump }, &pages)
+}
+
+#[test]
+fn test_paged_registry_spans_lookups() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    let ix = client::InitializeState {
+        payer_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+    }
+    .instruction(&InitializeRegistryIxData {
+        bump,
+        max_mappings: 2,
+    });
+    let audit_ix = create_initialize_audit_log_ix(program_id, &fee_payer, state_pda);
+    send_ixs(&mut svm, &fee_payer, &[ix, audit_ix]).unwrap();
+    // Placeholder oracle keys, so the oracle accounts are left out
+    relax_oracle_checks(&mut svm, &fee_payer, program_id, state_pda);
+    assert_eq!(get_registry(&svm, &state_pda).max_mappings, 2);
+    let error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    let mappings: Vec<MintMapping> = (1..=4u8)
+        .map(|i| MintMapping::new([i; 32], None, Some([i + 10; 32]), None, 6))
+        .collect();
+    for mapping in &mappings[..2] {
+        let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, *mapping);
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    }
+
+    // The cap set at initialization holds
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, mappings[2]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::MaxMappingsReached)
+    );
+
+    // A continuation page counts as a change and only keeps its own bookkeeping
+    let mutations = get_registry(&svm, &state_pda).rate_limit.mutations;
+    let (page_pda, _) = client::find_page_address(&state_pda, 1);
+    let page_ix = create_add_page_ix(program_id, &fee_payer, state_pda, 1);
+    send_ixs(&mut svm, &fee_payer, &[page_ix]).unwrap();
+    assert_eq!(
+        get_registry(&svm, &state_pda).rate_limit.mutations,
+        mutations + 1
+    );
+    let page = get_registry(&svm, &page_pda);
+    assert_eq!(page.page_of(), Some(&state_pda.to_bytes()));
+    assert_eq!(page.page_index, 1);
+    assert_eq!(page.max_mappings, 0);
+    assert_eq!(page.providers, ProviderConfig::default());
+    assert_eq!(
+        get_registry(&svm, &state_pda).pages_root,
+        leaf_hash(&[0u8; 32])
+    );
+
+    // Entries that don't fit the registry land on the page, through the registry
+    let pages = [AccountMeta::new(page_pda, false)];
+    let add_with_pages = |mapping: MintMapping| {
+        add_mapping_accounts(program_id, &fee_payer, state_pda)
+            .instruction_with_remaining_accounts(&AddMappingIxData { mapping }, &pages)
+    };
+    for mapping in &mappings[2..] {
+        send_ixs(&mut svm, &fee_payer, &[add_with_pages(*mapping)]).unwrap();
+    }
+
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.page_count, 1);
+    assert_eq!(registry.total_mappings, 2);
+    assert_eq!(registry.rate_limit.mutations, mutations + 3);
+    let page = get_registry(&svm, &page_pda);
+    assert_eq!(page.total_mappings, 2);
+    assert_eq!(page.rate_limit, RateLimit::default());
+
+    // The registry header commits to the entries of its pages through their roots
+    let registry_data = svm.get_account(&state_pda).unwrap().data;
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    let chain = [&registry_data[..], &page_data[..]];
+    assert_eq!(page.mappings_root, mappings_root(&page_data).unwrap());
+    assert_eq!(registry.pages_root, pages_root(&chain[1..]).unwrap());
+    for mapping in &mappings {
+        let proof = MappingProof::build_pages(&chain, &mapping.mint).unwrap();
+        assert_eq!(proof.version, registry.version);
+        assert_eq!(proof.page.is_some(), mapping.mint[0] > 2);
+        assert!(proof.verify_registry(&registry));
+    }
+    let mut tampered = MappingProof::build_pages(&chain, &mappings[3].mint).unwrap();
+    tampered.root[0] ^= 1;
+    assert!(!tampered.verify_registry(&registry));
+
+    // The registry needs its pages, and takes no page in its place
+    let extra = MintMapping::new([9; 32], None, Some([19; 32]), None, 6);
+    let ix = create_add_mapping_ix(program_id, &fee_payer, state_pda, extra);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys)
+    );
+    let ix = create_add_mapping_ix(program_id, &fee_payer, page_pda, extra);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::PdaMismatch)
+    );
+
+    // A mint has one entry across the chain
+    svm.expire_blockhash();
+    for mapping in [mappings[0], mappings[3]] {
+        assert_eq!(
+            send_ixs(&mut svm, &fee_payer, &[add_with_pages(mapping)])
+                .unwrap_err()
+                .err,
+            error(MappingProgramError::MintAlreadyMapped)
+        );
+    }
+
+    // Lookups span the registry and its pages
+    let registry_data = svm.get_account(&state_pda).unwrap().data;
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    let chain = [&registry_data[..], &page_data[..]];
+    for mapping in &mappings {
+        let resolved = MintMapping::resolve_pages(&chain, &mapping.mint, 0)
+            .ok()
+            .unwrap();
+        assert_eq!(resolved.mapping, *mapping);
+    }
+    assert!(MintMapping::resolve(&registry_data, &mappings[3].mint, 0).is_err());
+
+    let ix = client::GetMapping {
+        state_acc: state_pda,
+    }
+    .instruction_with_remaining_accounts(
+        &GetMappingIxData {
+            mint: mappings[3].mint,
+        },
+        &[AccountMeta::new_readonly(page_pda, false)],
+    );
+    let meta = send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(
+        decode_mapping(&meta.return_data.data).unwrap().mapping,
+        mappings[3]
+    );
+
+    // A registry with pages needs them, and only its own
+    let ix = create_get_mapping_ix(state_pda, mappings[0].mint);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys)
+    );
+    let ix = client::GetMapping {
+        state_acc: state_pda,
+    }
+    .instruction_with_remaining_accounts(
+        &GetMappingIxData {
+            mint: mappings[0].mint,
+        },
+        &[AccountMeta::new_readonly(state_pda, false)],
+    );
+    assert!(send_ixs(&mut svm, &fee_payer, &[ix]).is_err());
+
+    // Entries of a page are closed through the registry, and settings only change on it
+    let version = get_registry(&svm, &state_pda).version;
+    let ix = client::CloseMapping {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        refund_acc: None,
+    }
+    .instruction_with_remaining_accounts(
+        &CloseMappingIxData {
+            mint: mappings[2].mint,
+            bump,
+        },
+        &pages,
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_registry(&svm, &page_pda).total_mappings, 1);
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.version, version + 1);
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    assert_eq!(registry.pages_root, pages_root(&[&page_data]).unwrap());
+    let ix = create_set_disabled_providers_ix(program_id, &fee_payer, page_pda, 0);
+    assert!(send_ixs(&mut svm, &fee_payer, &[ix]).is_err());
+
+    svm.expire_blockhash();
+    let ix = client::GetMapping {
+        state_acc: state_pda,
+    }
+    .instruction_with_remaining_accounts(
+        &GetMappingIxData {
+            mint: mappings[2].mint,
+        },
+        &[AccountMeta::new_readonly(page_pda, false)],
+    );
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::MintNotFound)
+    );
+
+    // Bookkeeping on a page is recorded in the audit log of the registry, pages have none
+    let ix = create_initialize_audit_log_ix(program_id, &fee_payer, page_pda);
+    assert!(send_ixs(&mut svm, &fee_payer, &[ix]).is_err());
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let last_mapping_offset = get_registry(&svm, &page_pda).last_mapping_offset;
+    let ix = Compact {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: page_pda,
+        audit_log_acc: audit_log_pda,
+        refund_acc: None,
+    }
+    .instruction(&CompactIxData {
+        retain_bytes: u16::MAX,
+    });
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert!(get_registry(&svm, &page_pda).last_mapping_offset < last_mapping_offset);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    assert_eq!(records.last().unwrap().kind, AuditKind::Compact);
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    assert_eq!(
+        get_registry(&svm, &state_pda).pages_root,
+        pages_root(&[&page_data]).unwrap()
+    );
+
+    // Expired entries of a page are pruned through the registry
+    svm.warp_to_slot(100);
+    let mut expiring = MintMapping::new([8; 32], None, Some([18; 32]), None, 6);
+    expiring
+        .set_validity_window(ValidityWindow::new(None, Some(150)))
+        .unwrap();
+    send_ixs(&mut svm, &fee_payer, &[add_with_pages(expiring)]).unwrap();
+    assert_eq!(get_registry(&svm, &page_pda).total_mappings, 2);
+    svm.warp_to_slot(200);
+    let version = get_registry(&svm, &state_pda).version;
+    let ix = client::PruneExpired {
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        authority_acc: fee_payer.pubkey(),
+    }
+    .instruction_with_remaining_accounts(&PruneExpiredIxData { max_entries: 8 }, &pages);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_registry(&svm, &page_pda).total_mappings, 1);
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.version, version + 1);
+    let registry_data = svm.get_account(&state_pda).unwrap().data;
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    let chain = [&registry_data[..], &page_data[..]];
+    let proof = MappingProof::build_pages(&chain, &mappings[3].mint).unwrap();
+    assert!(proof.verify_registry(&registry));
+
+    // An alias on the page resolves to its target on the registry
+    let alias = MintMapping::new_alias([9; 32], mappings[0].mint, None, 6);
+    send_ixs(&mut svm, &fee_payer, &[add_with_pages(alias)]).unwrap();
+    assert_eq!(get_registry(&svm, &page_pda).total_mappings, 2);
+    let registry_data = svm.get_account(&state_pda).unwrap().data;
+    let page_data = svm.get_account(&page_pda).unwrap().data;
+    let chain = [&registry_data[..], &page_data[..]];
+    let resolved = MintMapping::resolve_pages(&chain, &alias.mint, 0)
+        .ok()
+        .unwrap();
+    assert_eq!(resolved.mapping, mappings[0]);
+
+    // Snapshots and drafts would miss the pages
+    for ix in [
+        create_snapshot_ix(&fee_payer, state_pda, 1),
+        create_initialize_draft_ix(&fee_payer, state_pda),
+    ] {
+        assert_eq!(
+            send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+            error(MappingProgramError::RegistryHasPages)
+        );
+    }
+
+    // Adding a page counts against the limit of the registry
+    let mutations = get_registry(&svm, &state_pda).rate_limit.mutations;
+    let ix = create_set_rate_limit_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        None,
+        SetRateLimitIxData {
+            max_mutations: mutations,
+            window_slots: 0,
+            guardian: [0; 32],
+        },
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let ix = create_add_page_ix(program_id, &fee_payer, state_pda, 2);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::RateLimitExceeded)
+    );
+}
+
+#[test]
+fn test_promote_rejects_a_registry_with_pages() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_draft_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+
+    // The draft holds the registry account only, so promoting it would drop the page entries
+    let ix = create_add_page_ix(program_id, &fee_payer, state_pda, 1);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let version = get_registry(&svm, &state_pda).version;
+    let ix = create_promote_ix(program_id, &fee_payer, state_pda, version);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::RegistryHasPages as u32)
+        )
+    );
+}
+
+fn create_set_scope_feed_ix(
+    program_id: Pubkey,
+    fee_payer: &Keypair,
+    state_pda: Pubkey,
+    index: u8,
+    feed: Option<Pubkey>,
+) -> Instruction {
+    client::SetScopeFeed {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        feed_acc: feed,
+    }
+    .instruction(&SetScopeFeedIxData {
+        index,
+        feed: feed.map_or([0u8; 32], |feed| feed.to_bytes()),
+    })
+}
+
+/// A Scope `OraclePrices` account holding `(value, exp, unix_timestamp)` prices
+fn scope_price_data(prices: &[(u64, u64, u64)]) -> Vec<u8> {
+    let mut data = vec![0u8; 40 + prices.len() * 56];
+    for (index, (value, exp, unix_timestamp)) in prices.iter().enumerate() {
+        let start = 40 + index * 56;
+        data[start..start + 8].copy_from_slice(&value.to_le_bytes());
+        data[start + 8..start + 16].copy_from_slice(&exp.to_le_bytes());
+        data[start + 24..start + 32].copy_from_slice(&unix_timestamp.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_attribute_setters_report_a_full_list() {
+    let mut mapping = MintMapping::new([1u8; 32], Some([4, u16::MAX, u16::MAX]), None, None, 6);
+    for tag in 10..10 + AttributeList::MAX_ENTRIES as u8 {
+        mapping
+            .set_attribute(Attribute::new(tag, &[0]).unwrap())
+            .unwrap();
+    }
+    let full = mapping;
+
+    let size_error = MappingProgramError::InvalidMappingSize.into();
+    assert_eq!(mapping.set_scope_feed(1).unwrap_err(), size_error);
+    let selection = mapping.set_price_selection(PriceSelection::Ema);
+    assert_eq!(selection.unwrap_err(), size_error);
+    let quote = mapping.set_quote_currency(QuoteCurrency::Sol);
+    assert_eq!(quote.unwrap_err(), size_error);
+    let window = mapping.set_validity_window(ValidityWindow::new(None, Some(150)));
+    assert_eq!(window.unwrap_err(), size_error);
+    let mut with_feed = MintMapping::new([1u8; 32], None, None, None, 6);
+    with_feed.set_scope_feed(1).unwrap();
+    assert_eq!(full.with_sources_of(&with_feed).unwrap_err(), size_error);
+    assert_eq!(mapping, full);
+
+    // The defaults store nothing, so they still succeed
+    mapping.set_scope_feed(0).unwrap();
+    mapping.set_price_selection(PriceSelection::Spot).unwrap();
+    assert_eq!(mapping, full);
+}
+
+#[test]
+fn test_setters_reject_entries_past_max_len() {
+    let mut mapping = MintMapping::new(
+        [1u8; 32],
+        Some([4, u16::MAX, u16::MAX]),
+        Some([2u8; 32]),
+        Some([3u8; 32]),
+        6,
+    );
+    let metadata = TokenMetadata::new("SYMBOL", "A token with a long enough name", 0).unwrap();
+    mapping.set_metadata(Some(metadata)).unwrap();
+    // Each 32 byte attribute takes 34 bytes, three of them bring the entry to 249 bytes
+    for tag in 10..13u8 {
+        mapping
+            .set_attribute(Attribute::new(tag, &[tag; 32]).unwrap())
+            .unwrap();
+    }
+    assert_eq!(mapping.offset, 249);
+    assert_eq!(mapping.serialized_size(), 249);
+    let full = mapping;
+
+    // Nothing that would outgrow the size byte is applied
+    let size_error = MappingProgramError::InvalidMappingSize.into();
+    let attribute = Attribute::new(13, &[13; 32]).unwrap();
+    assert_eq!(mapping.set_attribute(attribute).unwrap_err(), size_error);
+    assert_eq!(
+        mapping.set_delegate(Some([9u8; 32])).unwrap_err(),
+        size_error
+    );
+    let source = SourceEntry::account(SourceType::Stork, [5u8; 32]);
+    assert_eq!(mapping.add_source(source).unwrap_err(), size_error);
+    let alias = Some(MintAlias::new([8u8; 32], None));
+    assert_eq!(mapping.set_alias(alias).unwrap_err(), size_error);
+    assert_eq!(mapping, full);
+    assert_eq!(full.to_bytes().unwrap()[32], 249);
+
+    // An entry assembled past the limit by hand has no encoding
+    let mut oversized = full;
+    oversized.attributes.set(attribute).unwrap();
+    assert_eq!(oversized.to_bytes().unwrap_err(), size_error);
+    assert_eq!(oversized.without_providers(0b001).unwrap_err(), size_error);
+    assert!(!oversized.is_valid());
+}
+
+#[test]
+fn test_scope_feeds_bound_chain_indices() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    send_ixs(
+        &mut svm,
+        &fee_payer,
+        &[create_initialize_price_cache_ix(&fee_payer, state_pda)],
+    )
+    .unwrap();
+    let error = |error: MappingProgramError| {
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    };
+
+    // Three prices, 150.000000 at index 2
+    let scope_program = Pubkey::from_str("HFn8GnPADiny6XqUoWE8uRPPxb29ikn4yTuPa9MF2fWJ").unwrap();
+    let prices = [(1, 0, 0), (2, 0, 0), (150_000_000, 6, 1_700_000_000)];
+    let feed = set_oracle_account(&mut svm, scope_program, scope_price_data(&prices));
+    let other_feed = set_oracle_account(&mut svm, scope_program, scope_price_data(&prices));
+
+    // Feeds must be owned by the Scope program and sit below MAX_SCOPE_FEEDS
+    let foreign = set_oracle_account(&mut svm, Pubkey::new_unique(), scope_price_data(&prices));
+    let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, 1, Some(foreign));
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::OracleOwnerMismatch)
+    );
+    let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, 4, Some(feed));
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::ScopeFeedNotSet)
+    );
+
+    let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, 1, Some(feed));
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.scope_feed(1), Some(feed.to_bytes()));
+    assert_eq!(registry.scope_feed(0), None);
+    let (audit_log_pda, _) = get_audit_log_pda(program_id, state_pda);
+    let records = AuditLog::records(&svm.get_account(&audit_log_pda).unwrap().data).unwrap();
+    assert_eq!(records.last().unwrap().kind, AuditKind::SetScopeFeed);
+
+    let mint = [7u8; 32];
+    let add_mapping = |mapping: MintMapping, oracles: &[Pubkey]| {
+        let oracles: Vec<_> = oracles
+            .iter()
+            .map(|oracle| AccountMeta::new_readonly(*oracle, false))
+            .collect();
+        add_mapping_accounts(program_id, &fee_payer, state_pda)
+            .instruction_with_remaining_accounts(&AddMappingIxData { mapping }, &oracles)
+    };
+
+    // Feed 0 is unset once the registry names feeds
+    let mut mapping = MintMapping::new(mint, Some([2, u16::MAX, u16::MAX]), None, None, 6);
+    let ix = add_mapping(mapping, &[feed]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::ScopeFeedNotSet)
+    );
+
+    // The chain is checked against the feed, which has to be passed
+    mapping.set_scope_feed(1).unwrap();
+    let ix = add_mapping(mapping, &[]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidOracleAccount)
+    );
+    let ix = add_mapping(mapping, &[other_feed]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidOracleAccount)
+    );
+    let mut out_of_bounds = MintMapping::new(mint, Some([0, 3, u16::MAX]), None, None, 6);
+    out_of_bounds.set_scope_feed(1).unwrap();
+    let ix = add_mapping(out_of_bounds, &[feed]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::ScopeIndexOutOfBounds)
+    );
+
+    let ix = add_mapping(mapping, &[feed]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let stored = get_mapping(&svm, &state_pda, 0);
+    assert_eq!(stored, mapping);
+    assert_eq!(stored.scope_feed(), 1);
+
+    // Prices are read from the referenced feed only
+    svm.warp_to_slot(1_000);
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[other_feed]);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        error(MappingProgramError::InvalidOracleAccount)
+    );
+    let ix = create_refresh_prices_ix(state_pda, &[mint], &[feed]);
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    let price_cache_pda = client::find_price_cache_address(&state_pda).0;
+    let data = svm.get_account(&price_cache_pda).unwrap().data;
+    let cached = PriceCache::get_price(&data, &mint).unwrap();
+    assert_eq!((cached.price.value, cached.price.expo), (150_000_000, -6));
+    assert_eq!(cached.price.timestamp, 1_700_000_000);
+
+    // Updating the sources moves the chain and its feed together
+    let mut moved = MintMapping::new(mint, Some([0, u16::MAX, u16::MAX]), None, None, 6);
+    moved.set_scope_feed(1).unwrap();
+    assert_eq!(stored.with_sources_of(&moved).unwrap().scope_feed(), 1);
+    moved.set_scope_feed(0).unwrap();
+    assert_eq!(stored.with_sources_of(&moved).unwrap().scope_feed(), 0);
+}
+
+#[test]
+fn test_scope_feed_counts_against_the_rate_limit() {
+    let (mut svm, fee_payer, program_id, state_pda, bump) = setup_svm_and_program();
+    initialize_registry_and_audit_log(&mut svm, &fee_payer, program_id, state_pda, bump);
+    svm.warp_to_slot(10);
+    let guardian = Keypair::new();
+
+    let ix = create_set_rate_limit_ix(
+        program_id,
+        &fee_payer,
+        state_pda,
+        None,
+        SetRateLimitIxData {
+            max_mutations: 1,
+            window_slots: 100,
+            guardian: guardian.pubkey().to_bytes(),
+        },
+    );
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+
+    let scope_program = Pubkey::from_str("HFn8GnPADiny6XqUoWE8uRPPxb29ikn4yTuPa9MF2fWJ").unwrap();
+    let feed = set_oracle_account(&mut svm, scope_program, scope_price_data(&[(1, 0, 0)]));
+    let other_feed = set_oracle_account(&mut svm, scope_program, scope_price_data(&[(1, 0, 0)]));
+
+    // Redirecting a feed is a change like any other
+    let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, 0, Some(feed));
+    send_ixs(&mut svm, &fee_payer, &[ix]).unwrap();
+    assert_eq!(get_registry(&svm, &state_pda).rate_limit.mutations, 1);
+
+    let ix = create_set_scope_feed_ix(program_id, &fee_payer, state_pda, 0, Some(other_feed));
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::RateLimitExceeded as u32)
+        )
+    );
+    let ix = create_set_disabled_providers_ix(program_id, &fee_payer, state_pda, 0b001);
+    assert_eq!(
+        send_ixs(&mut svm, &fee_payer, &[ix]).unwrap_err().err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(MappingProgramError::RateLimitExceeded as u32)
+        )
+    );
+    assert_eq!(
+        get_registry(&svm, &state_pda).scope_feeds[0],
+        feed.to_bytes()
+    );
+
+    // The guardian co-signs to go past the limit, passed after the feed account
+    let guardian_meta = AccountMeta::new_readonly(guardian.pubkey(), true);
+    let ix = client::SetScopeFeed {
+        authority_acc: fee_payer.pubkey(),
+        state_acc: state_pda,
+        audit_log_acc: get_audit_log_pda(program_id, state_pda).0,
+        feed_acc: Some(other_feed),
+    }
+    .instruction_with_remaining_accounts(
+        &SetScopeFeedIxData {
+            index: 0,
+            feed: other_feed.to_bytes(),
+        },
+        std::slice::from_ref(&guardian_meta),
+    );
+    send_ixs_with_cosigner(&mut svm, &fee_payer, &guardian, &[ix]).unwrap();
+    let registry = get_registry(&svm, &state_pda);
+    assert_eq!(registry.scope_feeds[0], other_feed.to_bytes());
+    assert_eq!(registry.rate_limit.mutations, 2);
 }
@@ -0,0 +1,358 @@
+use pinocchio::program_error::ProgramError;
+use shank::ShankType;
+
+use crate::{error::MappingProgramError, scope_mapping_registry::MAX_SCOPE_FEEDS};
+
+/// Known mapping attribute tags. Attributes tune how a mapping is priced and are stored in a
+/// tagged list after the alias section, in the same `tag(1) + len(1) + payload(len)` format as
+/// the source list.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ShankType)]
+pub enum AttributeTag {
+    PriceSelection = 1, // PriceSelection, 1 byte
+    QuoteCurrency = 2,  // QuoteCurrency, 1 byte
+    ValidityWindow = 3, // ValidityWindow, 16 bytes
+    Delegate = 4,       // Key allowed to update the sources, 32 bytes
+    ScopeFeed = 5,      // Index of the registry Scope feed the chains read, 1 byte
+}
+
+impl AttributeTag {
+    pub const ALL: [AttributeTag; 5] = [
+        AttributeTag::PriceSelection,
+        AttributeTag::QuoteCurrency,
+        AttributeTag::ValidityWindow,
+        AttributeTag::Delegate,
+        AttributeTag::ScopeFeed,
+    ];
+
+    /// Exact payload length of an attribute with this tag.
+    pub fn payload_len(self) -> usize {
+        match self {
+            AttributeTag::PriceSelection
+            | AttributeTag::QuoteCurrency
+            | AttributeTag::ScopeFeed => 1,
+            AttributeTag::ValidityWindow => ValidityWindow::LEN,
+            AttributeTag::Delegate => 32,
+        }
+    }
+}
+
+impl TryFrom<u8> for AttributeTag {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|tag| *tag as u8 == value)
+            .ok_or(MappingProgramError::InvalidMappingSize.into())
+    }
+}
+
+/// Which Pyth price a mapping is valued at. Sources without an EMA always use their spot price.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, ShankType)]
+pub enum PriceSelection {
+    #[default]
+    Spot = 0,
+    Ema = 1,
+    /// The lower of spot and EMA, for collateral valuation
+    Conservative = 2,
+}
+
+impl PriceSelection {
+    pub const ALL: [PriceSelection; 3] = [
+        PriceSelection::Spot,
+        PriceSelection::Ema,
+        PriceSelection::Conservative,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PriceSelection::Spot => "spot",
+            PriceSelection::Ema => "ema",
+            PriceSelection::Conservative => "conservative",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|selection| selection.name() == name)
+    }
+}
+
+impl TryFrom<u8> for PriceSelection {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|selection| *selection as u8 == value)
+            .ok_or(MappingProgramError::InvalidMappingSize.into())
+    }
+}
+
+/// Currency the oracle sources of a mapping quote it in. Prices in another currency than USD are
+/// converted with the mapping the registry names for that currency.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, ShankType)]
+pub enum QuoteCurrency {
+    #[default]
+    Usd = 0,
+    Sol = 1,
+}
+
+impl QuoteCurrency {
+    pub const ALL: [QuoteCurrency; 2] = [QuoteCurrency::Usd, QuoteCurrency::Sol];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuoteCurrency::Usd => "usd",
+            QuoteCurrency::Sol => "sol",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|quote| quote.name() == name)
+    }
+}
+
+impl TryFrom<u8> for QuoteCurrency {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|quote| *quote as u8 == value)
+            .ok_or(MappingProgramError::InvalidMappingSize.into())
+    }
+}
+
+/// Slots a mapping is live in: from `valid_from` up to, not including, `valid_until`. Lookups of
+/// a mapping outside its window fail with `MappingNotActive`, and `PruneExpired` removes it once
+/// `valid_until` has passed.
+///
+/// Serialized as valid_from(8, LE) + valid_until(8, LE).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ShankType)]
+pub struct ValidityWindow {
+    pub valid_from: u64,  // 0 when live right away
+    pub valid_until: u64, // u64::MAX when it never expires
+}
+
+impl Default for ValidityWindow {
+    fn default() -> Self {
+        Self {
+            valid_from: 0,
+            valid_until: u64::MAX,
+        }
+    }
+}
+
+impl ValidityWindow {
+    pub const LEN: usize = 16;
+
+    pub fn new(valid_from: Option<u64>, valid_until: Option<u64>) -> Self {
+        Self {
+            valid_from: valid_from.unwrap_or(0),
+            valid_until: valid_until.unwrap_or(u64::MAX),
+        }
+    }
+
+    /// A window that opens before it closes.
+    pub fn is_valid(&self) -> bool {
+        self.valid_from < self.valid_until
+    }
+
+    /// Whether the window has neither bound set.
+    pub fn is_unbounded(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_active(&self, slot: u64) -> bool {
+        self.valid_from <= slot && slot < self.valid_until
+    }
+
+    pub fn is_expired(&self, slot: u64) -> bool {
+        slot >= self.valid_until
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        Ok(Self {
+            valid_from: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            valid_until: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.valid_from.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.valid_until.to_le_bytes());
+        bytes
+    }
+}
+
+/// One tagged attribute: tag(1) + len(1) + payload(len).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, ShankType)]
+pub struct Attribute {
+    pub tag: u8,
+    pub len: u8,
+    pub payload: [u8; 32],
+}
+
+impl Attribute {
+    pub const MAX_PAYLOAD_LEN: usize = 32;
+
+    pub fn new(tag: u8, payload: &[u8]) -> Result<Self, ProgramError> {
+        if payload.len() > Self::MAX_PAYLOAD_LEN {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        let mut attribute = Self {
+            tag,
+            len: payload.len() as u8,
+            ..Self::default()
+        };
+        attribute.payload[..payload.len()].copy_from_slice(payload);
+        Ok(attribute)
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload[..(self.len as usize).min(Self::MAX_PAYLOAD_LEN)]
+    }
+
+    /// A known tag with a payload of the length and values it expects.
+    pub fn is_valid(&self) -> bool {
+        let Ok(tag) = AttributeTag::try_from(self.tag) else {
+            return false;
+        };
+        self.len as usize == tag.payload_len()
+            && match tag {
+                AttributeTag::PriceSelection => PriceSelection::try_from(self.payload[0]).is_ok(),
+                AttributeTag::QuoteCurrency => QuoteCurrency::try_from(self.payload[0]).is_ok(),
+                AttributeTag::ValidityWindow => {
+                    ValidityWindow::from_bytes(self.payload()).is_ok_and(|window| window.is_valid())
+                }
+                AttributeTag::Delegate => self.payload() != [0u8; 32],
+                AttributeTag::ScopeFeed => (self.payload[0] as usize) < MAX_SCOPE_FEEDS,
+            }
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        2 + self.len as usize
+    }
+}
+
+/// Tagged attribute list of a mapping: count(1) + count * (tag(1) + len(1) + payload(len)).
+/// Each tag appears at most once.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, ShankType)]
+pub struct AttributeList {
+    pub count: u8,
+    pub entries: [Attribute; 5],
+}
+
+impl AttributeList {
+    pub const MAX_ENTRIES: usize = 5;
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Attribute> {
+        self.entries[..(self.count as usize).min(Self::MAX_ENTRIES)].iter()
+    }
+
+    pub fn get(&self, tag: AttributeTag) -> Option<&Attribute> {
+        self.iter().find(|attribute| attribute.tag == tag as u8)
+    }
+
+    /// Replaces the attribute with the same tag, or appends it.
+    pub fn set(&mut self, attribute: Attribute) -> Result<(), ProgramError> {
+        let count = (self.count as usize).min(Self::MAX_ENTRIES);
+        if let Some(existing) = self.entries[..count]
+            .iter_mut()
+            .find(|existing| existing.tag == attribute.tag)
+        {
+            *existing = attribute;
+            return Ok(());
+        }
+        if count >= Self::MAX_ENTRIES {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        self.entries[count] = attribute;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Drops the attribute with `tag`, keeping the order of the others.
+    pub fn remove(&mut self, tag: AttributeTag) {
+        let count = (self.count as usize).min(Self::MAX_ENTRIES);
+        if let Some(index) = self.entries[..count]
+            .iter()
+            .position(|attribute| attribute.tag == tag as u8)
+        {
+            self.entries.copy_within(index + 1..count, index);
+            self.entries[count - 1] = Attribute::default();
+            self.count -= 1;
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.count as usize <= Self::MAX_ENTRIES
+            && self.iter().all(Attribute::is_valid)
+            && self.iter().enumerate().all(|(index, attribute)| {
+                self.iter()
+                    .skip(index + 1)
+                    .all(|other| other.tag != attribute.tag)
+            })
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.iter().map(Attribute::serialized_size).sum::<usize>()
+    }
+
+    /// Reads a list from the start of `bytes`, returning it with the number of bytes read.
+    /// Attributes with unknown tags are kept so newer entries still round-trip.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), ProgramError> {
+        let mut list = Self::default();
+        let count = *bytes.first().ok_or(ProgramError::InvalidAccountData)? as usize;
+        if count > Self::MAX_ENTRIES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut offset = 1;
+        for index in 0..count {
+            if offset + 2 > bytes.len() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let tag = bytes[offset];
+            let len = bytes[offset + 1] as usize;
+            if len > Attribute::MAX_PAYLOAD_LEN || offset + 2 + len > bytes.len() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            list.entries[index] = Attribute::new(tag, &bytes[offset + 2..offset + 2 + len])?;
+            list.count += 1;
+            offset += 2 + len;
+        }
+
+        Ok((list, offset))
+    }
+
+    /// Writes the list to the start of `bytes` and returns the number of bytes written.
+    pub fn write_bytes(&self, bytes: &mut [u8]) -> usize {
+        bytes[0] = self.count;
+        let mut offset = 1;
+        for attribute in self.iter() {
+            let payload = attribute.payload();
+            bytes[offset] = attribute.tag;
+            bytes[offset + 1] = payload.len() as u8;
+            bytes[offset + 2..offset + 2 + payload.len()].copy_from_slice(payload);
+            offset += 2 + payload.len();
+        }
+        offset
+    }
+}
@@ -0,0 +1,220 @@
+use super::utils::{DataLen, Initialized};
+use alloc::vec::Vec;
+use pinocchio::{
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    ProgramResult,
+};
+
+use crate::error::MappingProgramError;
+
+/// Number of records kept before the oldest one is overwritten.
+pub const AUDIT_LOG_CAPACITY: u16 = 64;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankType)]
+pub enum AuditKind {
+    AddMapping,
+    CloseMapping,
+    ReserveCapacity,
+    Compact,
+    SetProviderConfig,
+    SetQuoteConversion,
+    PruneExpired,
+    SetDelegate,
+    UpdateSources,
+    SetRateLimit,
+    SetDisabledProviders,
+    Promote,
+    AddPage,
+    SetScopeFeed,
+}
+
+impl TryFrom<u8> for AuditKind {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AuditKind::AddMapping),
+            1 => Ok(AuditKind::CloseMapping),
+            2 => Ok(AuditKind::ReserveCapacity),
+            3 => Ok(AuditKind::Compact),
+            4 => Ok(AuditKind::SetProviderConfig),
+            5 => Ok(AuditKind::SetQuoteConversion),
+            6 => Ok(AuditKind::PruneExpired),
+            7 => Ok(AuditKind::SetDelegate),
+            8 => Ok(AuditKind::UpdateSources),
+            9 => Ok(AuditKind::SetRateLimit),
+            10 => Ok(AuditKind::SetDisabledProviders),
+            11 => Ok(AuditKind::Promote),
+            12 => Ok(AuditKind::AddPage),
+            13 => Ok(AuditKind::SetScopeFeed),
+            _ => Err(MappingProgramError::InvalidAccountData.into()),
+        }
+    }
+}
+
+/// A single registry change. Serialized without padding:
+/// slot(8) + kind(1) + mint(32) + signer(32) + mapping_hash(32) = 105 bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AuditRecord {
+    pub slot: u64,
+    pub kind: AuditKind,
+    pub mint: [u8; 32],
+    pub signer: [u8; 32],
+    pub mapping_hash: [u8; 32], // sha256 of the new serialized mapping, zeroed on close
+}
+
+impl AuditRecord {
+    pub const LEN: usize = 105;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut record = Self {
+            slot: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            kind: AuditKind::try_from(bytes[8])?,
+            mint: [0; 32],
+            signer: [0; 32],
+            mapping_hash: [0; 32],
+        };
+        record.mint.copy_from_slice(&bytes[9..41]);
+        record.signer.copy_from_slice(&bytes[41..73]);
+        record.mapping_hash.copy_from_slice(&bytes[73..105]);
+        Ok(record)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.slot.to_le_bytes());
+        bytes[8] = self.kind as u8;
+        bytes[9..41].copy_from_slice(&self.mint);
+        bytes[41..73].copy_from_slice(&self.signer);
+        bytes[73..105].copy_from_slice(&self.mapping_hash);
+        bytes
+    }
+}
+
+/// Header of the audit-log PDA that sits next to each registry. The header is followed by
+/// `capacity` fixed-size records used as a ring buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankAccount)]
+pub struct AuditLog {
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub capacity: u16,
+    pub head: u16,     // Index the next record is written to
+    pub count: u16,    // Number of valid records, saturates at capacity
+    pub sequence: u64, // Total records ever appended
+    pub registry: [u8; 32],
+}
+
+impl DataLen for AuditLog {
+    const LEN: usize = core::mem::size_of::<AuditLog>();
+}
+
+impl Initialized for AuditLog {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized > 0
+    }
+}
+
+impl AuditLog {
+    pub const SEED: &'static str = "AuditLog";
+
+    pub fn new(registry: [u8; 32], bump: u8) -> Self {
+        Self {
+            is_initialized: 1,
+            bump,
+            capacity: AUDIT_LOG_CAPACITY,
+            head: 0,
+            count: 0,
+            sequence: 0,
+            registry,
+        }
+    }
+
+    pub fn account_size() -> usize {
+        Self::LEN + AUDIT_LOG_CAPACITY as usize * AuditRecord::LEN
+    }
+
+    pub fn validate_pda(
+        bump: u8,
+        pda: &Pubkey,
+        registry: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let seed_with_bump = &[Self::SEED.as_bytes(), registry, &[bump]];
+        let derived = pubkey::create_program_address(seed_with_bump, program_id)?;
+        if derived != *pda {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        Ok(())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let log = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const Self) };
+        if !log.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if data.len() < Self::LEN + log.capacity as usize * AuditRecord::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(log)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                self as *const Self as *const u8,
+                bytes.as_mut_ptr(),
+                Self::LEN,
+            );
+        }
+        bytes
+    }
+
+    /// Writes `record` over the oldest entry and stores the updated header in `data`.
+    pub fn append(&mut self, data: &mut [u8], record: &AuditRecord) -> ProgramResult {
+        if !self.is_initialized() || self.capacity == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let start = Self::LEN + self.head as usize * AuditRecord::LEN;
+        if data.len() < start + AuditRecord::LEN {
+            return Err(MappingProgramError::WriteOverflow.into());
+        }
+        data[start..start + AuditRecord::LEN].copy_from_slice(&record.to_bytes());
+
+        self.head = (self.head + 1) % self.capacity;
+        self.count = (self.count + 1).min(self.capacity);
+        self.sequence += 1;
+        data[..Self::LEN].copy_from_slice(&self.to_bytes());
+        Ok(())
+    }
+
+    /// Returns the `index`-th retained record, 0 being the oldest.
+    pub fn get_record(data: &[u8], index: u16) -> Result<AuditRecord, ProgramError> {
+        let log = Self::from_account_data(data)?;
+        if index >= log.count {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let position = (log.head + log.capacity - log.count + index) % log.capacity;
+        let start = Self::LEN + position as usize * AuditRecord::LEN;
+        AuditRecord::from_bytes(&data[start..start + AuditRecord::LEN])
+    }
+
+    /// All retained records in the order they were written.
+    pub fn records(data: &[u8]) -> Result<Vec<AuditRecord>, ProgramError> {
+        let log = Self::from_account_data(data)?;
+        (0..log.count)
+            .map(|index| Self::get_record(data, index))
+            .collect()
+    }
+}
@@ -18,6 +18,50 @@ pub enum MappingProgramError {
     MintNotFound,
     // Invalid Account Data
     InvalidAccountData,
+    // Realloc exceeds the per-instruction limit
+    ReallocTooLarge,
+    // Metadata symbol or name too long
+    MetadataTooLong,
+    // Mint account is not an initialized SPL Token or Token-2022 mint for the mapping
+    InvalidMintAccount,
+    // Mapping decimals differ from the mint decimals
+    DecimalsMismatch,
+    // Oracle account does not match the mapping or is missing in strict mode
+    InvalidOracleAccount,
+    // Oracle account is not owned by the configured provider program
+    OracleOwnerMismatch,
+    // Source list entry with a tag this program doesn't know
+    UnknownSourceType,
+    // Instruction data format version this program doesn't support
+    UnsupportedFormatVersion,
+    // None of the oracle sources passed for a mint produced a price
+    PriceUnavailable,
+    // Price cache has no free slot for another mint
+    PriceCacheFull,
+    // Alias chain loops back on itself or is longer than MAX_ALIAS_DEPTH
+    AliasCycle,
+    // Quote currency has no conversion mapping, or the conversion mapping isn't quoted in USD
+    InvalidQuoteConversion,
+    // Mapping is outside its validity window
+    MappingNotActive,
+    // Signer is neither the registry authority nor the delegate of the mint
+    InvalidDelegate,
+    // Registry reached its mapping change limit for the current window
+    RateLimitExceeded,
+    // Registry version differs from the one the promotion expected
+    VersionMismatch,
+    // Registry already chains into `MAX_REGISTRY_PAGES` continuation pages
+    MaxPagesReached,
+    // Mapping references a Scope feed the registry doesn't hold
+    ScopeFeedNotSet,
+    // Scope chain index falls outside the price array of the feed
+    ScopeIndexOutOfBounds,
+    // Mint already has an entry in the registry or one of its continuation pages
+    MintAlreadyMapped,
+    // Registry chains into continuation pages, which snapshots and drafts don't cover
+    RegistryHasPages,
+    // Live registry has less room than the promoted entries need, past what one realloc adds
+    InsufficientCapacity,
 }
 
 impl From<MappingProgramError> for ProgramError {
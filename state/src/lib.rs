@@ -1,8 +1,34 @@
+#![no_std]
+
+extern crate alloc;
+
+pub mod attributes;
+pub mod audit_log;
 pub mod error;
+pub mod merkle;
+pub mod mint_alias;
 pub mod mint_mapping;
+pub mod price;
+pub mod price_cache;
+pub mod provider_config;
+pub mod rate_limit;
 pub mod scope_mapping_registry;
+pub mod snapshot;
+pub mod source_type;
+pub mod token_metadata;
 pub mod utils;
 
+pub use attributes::*;
+pub use audit_log::*;
+pub use merkle::*;
+pub use mint_alias::*;
 pub use mint_mapping::*;
+pub use price::*;
+pub use price_cache::*;
+pub use provider_config::*;
+pub use rate_limit::*;
 pub use scope_mapping_registry::*;
+pub use snapshot::*;
+pub use source_type::*;
+pub use token_metadata::*;
 pub use utils::*;
@@ -0,0 +1,251 @@
+use alloc::vec::Vec;
+use pinocchio::program_error::ProgramError;
+
+use crate::{
+    error::MappingProgramError,
+    mint_mapping::MintMapping,
+    scope_mapping_registry::{ScopeMappingRegistry, MAX_REGISTRY_PAGES},
+    utils::hashv,
+};
+
+/// Domain separator of leaf hashes, `H(0x00 || entry)`.
+pub const MERKLE_LEAF_PREFIX: u8 = 0x00;
+/// Domain separator of inner node hashes, `H(0x01 || left || right)`.
+pub const MERKLE_NODE_PREFIX: u8 = 0x01;
+/// Most levels a tree of `u32::MAX` leaves can have, and the most siblings a proof can hold.
+pub const MAX_PROOF_DEPTH: usize = 32;
+
+/// Leaf of a serialized `MintMapping` entry, as stored in the registry.
+pub fn leaf_hash(entry: &[u8]) -> [u8; 32] {
+    hashv(&[&[MERKLE_LEAF_PREFIX], entry])
+}
+
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[MERKLE_NODE_PREFIX], left, right])
+}
+
+/// Merkle root over the live entries of registry account data, in storage order. A registry
+/// without entries has an all-zero root.
+pub fn mappings_root(data: &[u8]) -> Result<[u8; 32], ProgramError> {
+    merkle_root(
+        ScopeMappingRegistry::mapping_offsets(data)?
+            .map(|(start, end)| leaf_hash(&data[start..end])),
+    )
+}
+
+/// Merkle root over the `mappings_root` of continuation pages, given as their account data in
+/// page order, with each page root hashed as a leaf. A registry without pages has an all-zero
+/// root.
+pub fn pages_root(pages: &[&[u8]]) -> Result<[u8; 32], ProgramError> {
+    let mut leaves = [[0u8; 32]; MAX_REGISTRY_PAGES as usize];
+    let leaves = leaves
+        .get_mut(..pages.len())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    for (leaf, page) in leaves.iter_mut().zip(pages) {
+        *leaf = leaf_hash(&ScopeMappingRegistry::from_account_data(page)?.mappings_root);
+    }
+    merkle_root(leaves.iter().copied())
+}
+
+/// Merkle root over `leaves`. Levels are paired left to right and the last node of an odd level
+/// moves up unchanged; no leaves give an all-zero root. Computed in one pass without
+/// allocating, keeping one subtree root per level.
+pub fn merkle_root(leaves: impl Iterator<Item = [u8; 32]>) -> Result<[u8; 32], ProgramError> {
+    // Roots of the complete subtrees seen so far, with their heights strictly decreasing
+    let mut peaks = [([0u8; 32], 0u8); MAX_PROOF_DEPTH + 1];
+    let mut count = 0;
+    for leaf in leaves {
+        let mut node = leaf;
+        let mut height = 0;
+        while count > 0 && peaks[count - 1].1 == height {
+            count -= 1;
+            node = node_hash(&peaks[count].0, &node);
+            height += 1;
+        }
+        if count == peaks.len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        peaks[count] = (node, height);
+        count += 1;
+    }
+
+    // Odd nodes move up, so the remaining subtrees fold from the right
+    let Some(((mut root, _), rest)) = peaks[..count].split_last() else {
+        return Ok([0u8; 32]);
+    };
+    for (peak, _) in rest.iter().rev() {
+        root = node_hash(peak, &root);
+    }
+    Ok(root)
+}
+
+/// Sibling hashes of leaf `index` among `leaves`, from the leaf up, in the shape
+/// `verify_inclusion` takes.
+fn inclusion_siblings(leaves: Vec<[u8; 32]>, index: usize) -> Vec<[u8; 32]> {
+    let mut level = leaves;
+    let mut siblings = Vec::new();
+    let mut position = index;
+    while level.len() > 1 {
+        let sibling = position ^ 1;
+        if sibling < level.len() {
+            siblings.push(level[sibling]);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [node] => *node,
+                _ => unreachable!(),
+            })
+            .collect();
+        position /= 2;
+    }
+    siblings
+}
+
+/// Whether `entry` is leaf `index` of the `leaf_count` leaves under `root`, with `siblings`
+/// listed from the leaf up. Levels where the node moves up without a sibling take no entry.
+/// Needs neither `std` nor a heap, so programs can check proofs on-chain.
+pub fn verify_inclusion(
+    root: &[u8; 32],
+    entry: &[u8],
+    index: u32,
+    leaf_count: u32,
+    siblings: &[[u8; 32]],
+) -> bool {
+    if index >= leaf_count {
+        return false;
+    }
+    let mut node = leaf_hash(entry);
+    let mut index = index;
+    let mut width = leaf_count;
+    let mut siblings = siblings.iter();
+    while width > 1 {
+        if index % 2 == 1 {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            node = node_hash(sibling, &node);
+        } else if index + 1 < width {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            node = node_hash(&node, sibling);
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    siblings.next().is_none() && node == *root
+}
+
+/// Proof that a mapping is part of a registry at some version, built off-chain from the account
+/// data and checked against the registry header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MappingProof {
+    pub version: u32,            // Registry version the proof was built at
+    pub root: [u8; 32],          // `mappings_root` of the account holding the entry
+    pub index: u32,              // Position of the entry among the live entries
+    pub leaf_count: u32,         // Number of live entries
+    pub entry: Vec<u8>,          // Serialized mapping, as stored
+    pub siblings: Vec<[u8; 32]>, // From the leaf up
+    pub page: Option<PageProof>, // How the page holding the entry is committed, `None` off pages
+}
+
+/// Proof that the `mappings_root` of a continuation page is leaf `index` under the
+/// `pages_root` of its registry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageProof {
+    pub root: [u8; 32],          // `pages_root` of the registry
+    pub index: u32,              // Position of the page, from 0
+    pub page_count: u32,         // Number of pages
+    pub siblings: Vec<[u8; 32]>, // From the leaf up
+}
+
+impl MappingProof {
+    /// Builds the proof of the live entry of `mint` from the account data of one registry or
+    /// page, against its own `mappings_root`. The proof covers the stored entry as is, whether
+    /// or not it is an alias or in its validity window.
+    pub fn build(data: &[u8], mint: &[u8; 32]) -> Result<Self, ProgramError> {
+        let registry = ScopeMappingRegistry::from_account_data(data)?;
+        let leaves: Vec<[u8; 32]> = ScopeMappingRegistry::mapping_offsets(data)?
+            .map(|(start, end)| leaf_hash(&data[start..end]))
+            .collect();
+        let (index, (start, end)) = ScopeMappingRegistry::mapping_offsets(data)?
+            .enumerate()
+            .find(|(_, (start, _))| data[*start..*start + 32] == *mint)
+            .ok_or(MappingProgramError::MintNotFound)?;
+
+        Ok(Self {
+            version: registry.version,
+            root: registry.mappings_root,
+            index: index as u32,
+            leaf_count: leaves.len() as u32,
+            entry: data[start..end].to_vec(),
+            siblings: inclusion_siblings(leaves, index),
+            page: None,
+        })
+    }
+
+    /// Builds the proof of the live entry of `mint` against the header of a registry, with
+    /// `pages` holding the account data of the registry first and then of all its pages in
+    /// order. An entry on a page is proven against the page root, and the page root against the
+    /// `pages_root` of the registry.
+    pub fn build_pages(pages: &[&[u8]], mint: &[u8; 32]) -> Result<Self, ProgramError> {
+        let (page, _) = MintMapping::get_mapping_offset_pages(pages, mint)?;
+        let mut proof = Self::build(pages[page], mint)?;
+        if page == 0 {
+            return Ok(proof);
+        }
+
+        let registry = ScopeMappingRegistry::from_account_data(pages[0])?;
+        let leaves = pages[1..]
+            .iter()
+            .map(|data| {
+                ScopeMappingRegistry::from_account_data(data)
+                    .map(|page| leaf_hash(&page.mappings_root))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        proof.version = registry.version;
+        proof.page = Some(PageProof {
+            root: registry.pages_root,
+            index: page as u32 - 1,
+            page_count: leaves.len() as u32,
+            siblings: inclusion_siblings(leaves, page - 1),
+        });
+        Ok(proof)
+    }
+
+    /// The mapping the proof is about.
+    pub fn mapping(&self) -> Result<MintMapping, ProgramError> {
+        MintMapping::from_bytes(&self.entry)
+    }
+
+    /// Checks the entry against `root`, the `mappings_root` of the account holding it.
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        verify_inclusion(
+            root,
+            &self.entry,
+            self.index,
+            self.leaf_count,
+            &self.siblings,
+        )
+    }
+
+    /// Checks the proof against the header of the registry, through the page holding the entry
+    /// if there is one.
+    pub fn verify_registry(&self, registry: &ScopeMappingRegistry) -> bool {
+        match &self.page {
+            None => self.verify(&registry.mappings_root),
+            Some(page) => {
+                self.verify(&self.root)
+                    && verify_inclusion(
+                        &registry.pages_root,
+                        &self.root,
+                        page.index,
+                        page.page_count,
+                        &page.siblings,
+                    )
+            }
+        }
+    }
+}
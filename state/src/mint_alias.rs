@@ -0,0 +1,78 @@
+use pinocchio::program_error::ProgramError;
+use shank::ShankType;
+
+use crate::{error::MappingProgramError, price::Price};
+
+/// Multipliers are fixed point with 9 decimals, this is 1x.
+pub const ALIAS_MULTIPLIER_ONE: u64 = 1_000_000_000;
+const ALIAS_MULTIPLIER_EXPO: i32 = -9;
+
+/// Most aliases a lookup follows before giving up with `AliasCycle`.
+pub const MAX_ALIAS_DEPTH: usize = 4;
+
+/// Points a mapping at the entry of another mint, e.g. a bridged or Token-2022 copy of a
+/// canonical token. Lookups of the alias return the target entry, with prices scaled by
+/// `multiplier`.
+///
+/// Serialized as target(32) + multiplier(8, LE).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, ShankType, Default)]
+pub struct MintAlias {
+    pub target: [u8; 32],
+    /// Fixed point with 9 decimals, `ALIAS_MULTIPLIER_ONE` for none
+    pub multiplier: u64,
+}
+
+impl MintAlias {
+    pub const LEN: usize = 40;
+
+    pub fn new(target: [u8; 32], multiplier: Option<u64>) -> Self {
+        Self {
+            target,
+            multiplier: multiplier.unwrap_or(ALIAS_MULTIPLIER_ONE),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.multiplier > 0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() < Self::LEN {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        let mut alias = Self {
+            multiplier: u64::from_le_bytes(bytes[32..40].try_into().unwrap()),
+            ..Self::default()
+        };
+        alias.target.copy_from_slice(&bytes[0..32]);
+        Ok(alias)
+    }
+
+    /// Writes the alias at the start of `bytes` and returns the number of bytes written.
+    pub fn write_bytes(&self, bytes: &mut [u8]) -> usize {
+        bytes[0..32].copy_from_slice(&self.target);
+        bytes[32..40].copy_from_slice(&self.multiplier.to_le_bytes());
+        Self::LEN
+    }
+}
+
+/// Combined multiplier of a chain of aliases, `None` on overflow.
+pub fn chain_multiplier(multiplier: u64, next: u64) -> Option<u64> {
+    let product = (multiplier as u128).checked_mul(next as u128)? / ALIAS_MULTIPLIER_ONE as u128;
+    u64::try_from(product).ok().filter(|product| *product > 0)
+}
+
+/// Scales `price` by an alias multiplier.
+pub fn apply_multiplier(price: &Price, multiplier: u64) -> Option<Price> {
+    if multiplier == ALIAS_MULTIPLIER_ONE {
+        return Some(*price);
+    }
+    let multiplier = i64::try_from(multiplier).ok()?;
+    price.mul(&Price {
+        value: multiplier,
+        expo: ALIAS_MULTIPLIER_EXPO,
+        conf: 0,
+        timestamp: i64::MAX,
+    })
+}
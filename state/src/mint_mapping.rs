@@ -1,30 +1,90 @@
 use pinocchio::program_error::ProgramError;
 use shank::ShankAccount;
 
-use crate::{error::MappingProgramError, scope_mapping_registry::ScopeMappingRegistry, DataLen};
-
+use crate::{
+    attributes::{
+        Attribute, AttributeList, AttributeTag, PriceSelection, QuoteCurrency, ValidityWindow,
+    },
+    error::MappingProgramError,
+    mint_alias::{chain_multiplier, MintAlias, ALIAS_MULTIPLIER_ONE, MAX_ALIAS_DEPTH},
+    scope_mapping_registry::ScopeMappingRegistry,
+    source_type::{SourceEntry, SourceList, SourceType},
+    token_metadata::TokenMetadata,
+};
+
+/// The `mapping_details` bits of the Scope, Pyth and Switchboard sources. The registry's
+/// `disabled_providers` mask uses the same bits.
+pub const MAPPING_PROVIDERS: u8 = 0b0000_0111;
+
+/// Set in `mapping_details` when the entry carries a `TokenMetadata` section after its sources.
+pub const MAPPING_METADATA: u8 = 0b0000_1000;
+
+/// Set in `mapping_details` when the entry carries a tagged `SourceList` after the fixed sources.
+pub const MAPPING_SOURCE_LIST: u8 = 0b0001_0000;
+
+/// Set in `mapping_details` when the entry is an alias of another mint. Aliases carry no oracle
+/// sources of their own.
+pub const MAPPING_ALIAS: u8 = 0b0010_0000;
+
+/// Set in `mapping_details` when the entry carries a tagged `AttributeList` after its alias.
+pub const MAPPING_ATTRIBUTES: u8 = 0b0100_0000;
+
+/// Set in `mapping_details` of an entry removed by `CloseMapping`. The entry keeps only its size
+/// until the registry is compacted.
+pub const MAPPING_CLOSED: u8 = 0b1000_0000;
+
+/// The `mapping_details` bits an `AddMapping` entry may set. `MAPPING_CLOSED` is only ever set by
+/// the program.
+pub const MAPPING_INPUT_FLAGS: u8 =
+    MAPPING_PROVIDERS | MAPPING_METADATA | MAPPING_SOURCE_LIST | MAPPING_ALIAS | MAPPING_ATTRIBUTES;
+
+/// One registry entry. Entries are variable-length: only the sections flagged in
+/// `mapping_details` are serialized, in field order, and the entry size is stored in `offset`.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, ShankAccount, Default)]
 pub struct MintMapping {
-    // Mapping details
-    // 000 -> None/ disables,
-    // 001 -> scope,
-    // 010 -> pyth,
-    // 011 -> pyth + scope,
-    // 100 -> switch_board,
-    // 101 -> switch_board + scope,
-    // 110 -> switch_board + pyth,
-    // 111 -> switch_board + pyth + scope,
     pub mint: [u8; 32],
-    pub offset: u8, // Offset for the mapping
+    /// Serialized size of the entry in bytes
+    pub offset: u8,
+    /// Presence bits: 0b001 scope, 0b010 pyth, 0b100 switchboard, 0b1000 metadata,
+    /// 0b1_0000 source list, 0b10_0000 alias, 0b100_0000 attributes, 0b1000_0000 closed
     pub mapping_details: u8,
-    pub decimals: u8,                    // Mint decimals for price calculations
-    pub scope_details: Option<[u16; 3]>, // Conversion chain (e.g., [32, 0, u16::MAX, u16::MAX])
-    pub pyth_account: Option<[u8; 32]>,  // 0 = None, 1 = Some + 32 bytes
-    pub switch_board: Option<[u8; 32]>,  // 0 = None, 1 = Some + 32 bytes
+    /// Mint decimals for price calculations
+    pub decimals: u8,
+    /// Scope conversion chain, 6 bytes when bit 0b001 is set (e.g. [32, u16::MAX, u16::MAX])
+    pub scope_details: Option<[u16; 3]>,
+    /// Pyth price account, 32 bytes when bit 0b010 is set
+    pub pyth_account: Option<[u8; 32]>,
+    /// Switchboard feed account, 32 bytes when bit 0b100 is set
+    pub switch_board: Option<[u8; 32]>,
+    /// Tagged sources beyond the three fixed ones, present when bit 0b1_0000 is set
+    pub sources: SourceList,
+    /// Symbol, name and tags, present when bit 0b1000 is set
+    pub metadata: Option<TokenMetadata>,
+    /// Target mint and multiplier, 40 bytes when bit 0b10_0000 is set
+    pub alias: Option<MintAlias>,
+    /// Tagged pricing attributes, present when bit 0b100_0000 is set
+    pub attributes: AttributeList,
+}
+
+/// The entry a lookup ended at after following aliases, and the product of their multipliers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResolvedMapping {
+    pub mapping: MintMapping,
+    /// Fixed point with 9 decimals, `ALIAS_MULTIPLIER_ONE` when no alias scaled the price
+    pub multiplier: u64,
 }
 
 impl MintMapping {
+    /// mint(32) + offset(1) + mapping_details(1) + decimals(1)
+    pub const HEADER_LEN: usize = 35;
+    /// Leads the entry in instruction data, ahead of `to_bytes()[..serialized_size]`
+    pub const FORMAT_VERSION: u8 = 1;
+    /// Entry sizes are stored in a single byte
+    pub const MAX_LEN: usize = u8::MAX as usize;
+    /// The three fixed sources and a full source list
+    pub const MAX_SOURCES: usize = 3 + SourceList::MAX_ENTRIES;
+
     pub fn set_pyth_account(&mut self, value: Option<[u8; 32]>) {
         self.pyth_account = value;
     }
@@ -39,6 +99,319 @@ impl MintMapping {
         self.switch_board
     }
 
+    /// Attaches or removes the metadata section, keeping `mapping_details` and `offset` in sync.
+    /// Fails, leaving the entry as it was, when the entry would outgrow `MAX_LEN`.
+    pub fn set_metadata(&mut self, metadata: Option<TokenMetadata>) -> Result<(), ProgramError> {
+        let mut mapping = *self;
+        mapping.metadata = metadata;
+        if metadata.is_some() {
+            mapping.mapping_details |= MAPPING_METADATA;
+        } else {
+            mapping.mapping_details &= !MAPPING_METADATA;
+        }
+        *self = mapping.resized()?;
+        Ok(())
+    }
+
+    pub fn get_metadata(&self) -> Option<TokenMetadata> {
+        self.metadata
+    }
+
+    /// Makes the entry an alias of `target` with an optional multiplier, keeping
+    /// `mapping_details` and `offset` in sync. Fails, leaving the entry as it was, when the
+    /// entry would outgrow `MAX_LEN`.
+    pub fn set_alias(&mut self, alias: Option<MintAlias>) -> Result<(), ProgramError> {
+        let mut mapping = *self;
+        mapping.alias = alias;
+        if alias.is_some() {
+            mapping.mapping_details |= MAPPING_ALIAS;
+        } else {
+            mapping.mapping_details &= !MAPPING_ALIAS;
+        }
+        *self = mapping.resized()?;
+        Ok(())
+    }
+
+    /// Sets or replaces an attribute, keeping `mapping_details` and `offset` in sync. Fails,
+    /// leaving the entry as it was, when the list is full or the entry would outgrow `MAX_LEN`.
+    pub fn set_attribute(&mut self, attribute: Attribute) -> Result<(), ProgramError> {
+        let mut mapping = *self;
+        mapping.attributes.set(attribute)?;
+        mapping.mapping_details |= MAPPING_ATTRIBUTES;
+        *self = mapping.resized()?;
+        Ok(())
+    }
+
+    /// Removes an attribute, keeping `mapping_details` and `offset` in sync.
+    pub fn remove_attribute(&mut self, tag: AttributeTag) -> Result<(), ProgramError> {
+        let mut mapping = *self;
+        mapping.attributes.remove(tag);
+        if mapping.attributes.is_empty() {
+            mapping.mapping_details &= !MAPPING_ATTRIBUTES;
+        }
+        *self = mapping.resized()?;
+        Ok(())
+    }
+
+    /// The serialized size as stored in `offset`, or `InvalidMappingSize` when it does not fit
+    /// in a byte.
+    fn checked_size(&self) -> Result<u8, ProgramError> {
+        u8::try_from(self.serialized_size())
+            .map_err(|_| MappingProgramError::InvalidMappingSize.into())
+    }
+
+    /// This mapping with `offset` set to its serialized size.
+    fn resized(mut self) -> Result<Self, ProgramError> {
+        self.offset = self.checked_size()?;
+        Ok(self)
+    }
+
+    /// Picks the Pyth spot price, EMA or the lower of both. Spot is the default and stores nothing.
+    /// Fails when the attribute list is already full.
+    pub fn set_price_selection(&mut self, selection: PriceSelection) -> Result<(), ProgramError> {
+        if selection == PriceSelection::Spot {
+            self.remove_attribute(AttributeTag::PriceSelection)
+        } else {
+            self.set_attribute(Attribute::new(
+                AttributeTag::PriceSelection as u8,
+                &[selection as u8],
+            )?)
+        }
+    }
+
+    pub fn price_selection(&self) -> PriceSelection {
+        self.attributes
+            .get(AttributeTag::PriceSelection)
+            .filter(|_| self.has_attributes())
+            .and_then(|attribute| PriceSelection::try_from(*attribute.payload().first()?).ok())
+            .unwrap_or_default()
+    }
+
+    /// Records the currency the sources quote the mint in. USD is the default and stores nothing.
+    /// Fails when the attribute list is already full.
+    pub fn set_quote_currency(&mut self, quote: QuoteCurrency) -> Result<(), ProgramError> {
+        if quote == QuoteCurrency::Usd {
+            self.remove_attribute(AttributeTag::QuoteCurrency)
+        } else {
+            self.set_attribute(Attribute::new(
+                AttributeTag::QuoteCurrency as u8,
+                &[quote as u8],
+            )?)
+        }
+    }
+
+    pub fn quote_currency(&self) -> QuoteCurrency {
+        self.attributes
+            .get(AttributeTag::QuoteCurrency)
+            .filter(|_| self.has_attributes())
+            .and_then(|attribute| QuoteCurrency::try_from(*attribute.payload().first()?).ok())
+            .unwrap_or_default()
+    }
+
+    /// Limits the slots the mapping is live in. An unbounded window stores nothing.
+    /// Fails when the attribute list is already full.
+    pub fn set_validity_window(&mut self, window: ValidityWindow) -> Result<(), ProgramError> {
+        if window.is_unbounded() {
+            self.remove_attribute(AttributeTag::ValidityWindow)
+        } else {
+            self.set_attribute(Attribute::new(
+                AttributeTag::ValidityWindow as u8,
+                &window.to_bytes(),
+            )?)
+        }
+    }
+
+    pub fn validity_window(&self) -> ValidityWindow {
+        self.attributes
+            .get(AttributeTag::ValidityWindow)
+            .filter(|_| self.has_attributes())
+            .and_then(|attribute| ValidityWindow::from_bytes(attribute.payload()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn is_active(&self, slot: u64) -> bool {
+        self.validity_window().is_active(slot)
+    }
+
+    /// Assigns or revokes the key allowed to update the oracle sources of this mint.
+    /// Fails when the attribute list is already full.
+    pub fn set_delegate(&mut self, delegate: Option<[u8; 32]>) -> Result<(), ProgramError> {
+        match delegate {
+            Some(delegate) => {
+                self.set_attribute(Attribute::new(AttributeTag::Delegate as u8, &delegate)?)
+            }
+            None => self.remove_attribute(AttributeTag::Delegate),
+        }
+    }
+
+    pub fn delegate(&self) -> Option<[u8; 32]> {
+        self.attributes
+            .get(AttributeTag::Delegate)
+            .filter(|_| self.has_attributes())
+            .and_then(|attribute| attribute.payload().try_into().ok())
+    }
+
+    /// Points the Scope chains at the registry feed `index`. Feed 0 is the default and stores
+    /// nothing. Fails when the attribute list is already full.
+    pub fn set_scope_feed(&mut self, index: u8) -> Result<(), ProgramError> {
+        if index == 0 {
+            self.remove_attribute(AttributeTag::ScopeFeed)
+        } else {
+            self.set_attribute(Attribute::new(AttributeTag::ScopeFeed as u8, &[index])?)
+        }
+    }
+
+    pub fn scope_feed(&self) -> u8 {
+        self.attributes
+            .get(AttributeTag::ScopeFeed)
+            .filter(|_| self.has_attributes())
+            .and_then(|attribute| attribute.payload().first().copied())
+            .unwrap_or_default()
+    }
+
+    /// This mapping with the oracle sources of `other`: the Scope chain and the feed it reads,
+    /// the Pyth and Switchboard accounts and the tagged source list. Everything else is kept.
+    /// Fails when the feed does not fit in the attribute list or the entry would outgrow
+    /// `MAX_LEN`.
+    pub fn with_sources_of(&self, other: &MintMapping) -> Result<MintMapping, ProgramError> {
+        const SOURCE_BITS: u8 = MAPPING_PROVIDERS | MAPPING_SOURCE_LIST;
+        let mut mapping = *self;
+        mapping.scope_details = other.scope_details;
+        mapping.pyth_account = other.pyth_account;
+        mapping.switch_board = other.switch_board;
+        mapping.sources = other.sources;
+        mapping.mapping_details =
+            (self.mapping_details & !SOURCE_BITS) | (other.mapping_details & SOURCE_BITS);
+        let mut mapping = mapping.resized()?;
+        mapping.set_scope_feed(other.scope_feed())?;
+        Ok(mapping)
+    }
+
+    /// This mapping without the sources of the providers in `disabled`, a mask of
+    /// `MAPPING_PROVIDERS` bits, whether in the fixed fields or the source list. Guard bands are
+    /// renumbered to the remaining sources, and a guarded fixed price whose reference was
+    /// dropped goes with it. Fails only for an entry that was already larger than `MAX_LEN`.
+    pub fn without_providers(&self, disabled: u8) -> Result<MintMapping, ProgramError> {
+        let disabled = disabled & MAPPING_PROVIDERS;
+        if disabled == 0 {
+            return Ok(*self);
+        }
+
+        let mut sources = [SourceEntry::default(); Self::MAX_SOURCES];
+        let mut count = 0;
+        for source in self.oracle_sources().take(Self::MAX_SOURCES) {
+            sources[count] = source;
+            count += 1;
+        }
+        let is_disabled = |source: &SourceEntry| {
+            source
+                .source_type()
+                .and_then(SourceType::legacy_bit)
+                .is_some_and(|bit| bit & disabled != 0)
+        };
+        let mut kept = [false; Self::MAX_SOURCES];
+        for index in 0..count {
+            kept[index] = !is_disabled(&sources[index]);
+        }
+        // References always point at sources that are not fixed, so one pass is enough
+        for index in 0..count {
+            if let Some((reference, _)) = sources[index].fixed_price().and_then(|f| f.guard()) {
+                if !kept.get(reference as usize).copied().unwrap_or(false) {
+                    kept[index] = false;
+                }
+            }
+        }
+
+        let mut mapping = *self;
+        if disabled & 0b001 != 0 {
+            mapping.scope_details = None;
+        }
+        if disabled & 0b010 != 0 {
+            mapping.pyth_account = None;
+        }
+        if disabled & 0b100 != 0 {
+            mapping.switch_board = None;
+        }
+        mapping.mapping_details &= !disabled;
+
+        let listed_from = count
+            - self
+                .sources
+                .iter()
+                .filter(|_| self.has_source_list())
+                .count();
+        mapping.sources = SourceList::default();
+        for index in listed_from..count {
+            if !kept[index] {
+                continue;
+            }
+            let mut source = sources[index];
+            if let Some(fixed) = source.fixed_price() {
+                if let Some((reference, band_bps)) = fixed.guard() {
+                    let reference = kept[..reference as usize].iter().filter(|k| **k).count();
+                    source = SourceEntry::fixed(
+                        fixed.value,
+                        fixed.expo,
+                        Some((reference as u8, band_bps)),
+                    );
+                }
+            }
+            // Fits, the list only gets shorter
+            mapping.sources.push(source).unwrap();
+        }
+        if mapping.sources.is_empty() {
+            mapping.mapping_details &= !MAPPING_SOURCE_LIST;
+        }
+        mapping.resized()
+    }
+
+    /// An alias entry for `mint` pointing at `target`.
+    pub fn new_alias(
+        mint: [u8; 32],
+        target: [u8; 32],
+        multiplier: Option<u64>,
+        decimals: u8,
+    ) -> Self {
+        let mut mapping = Self::new(mint, None, None, None, decimals);
+        mapping.alias = Some(MintAlias::new(target, multiplier));
+        mapping.mapping_details |= MAPPING_ALIAS;
+        mapping.offset += MintAlias::LEN as u8;
+        mapping
+    }
+
+    /// Appends an entry to the tagged source list, keeping `mapping_details` and `offset` in sync.
+    /// Fails, leaving the entry as it was, when the list is full or the entry would outgrow
+    /// `MAX_LEN`.
+    pub fn add_source(&mut self, entry: SourceEntry) -> Result<(), ProgramError> {
+        let mut mapping = *self;
+        mapping.sources.push(entry)?;
+        mapping.mapping_details |= MAPPING_SOURCE_LIST;
+        *self = mapping.resized()?;
+        Ok(())
+    }
+
+    /// All sources of the mapping, the fixed fields first and then the tagged list, in the order
+    /// their accounts are passed to instructions.
+    pub fn oracle_sources(&self) -> impl Iterator<Item = SourceEntry> + '_ {
+        let fixed = [
+            self.scope_details
+                .filter(|_| self.has_scope())
+                .map(SourceEntry::scope),
+            self.pyth_account
+                .filter(|_| self.has_pyth())
+                .map(|account| SourceEntry::account(SourceType::Pyth, account)),
+            self.switch_board
+                .filter(|_| self.has_switch_board())
+                .map(|account| SourceEntry::account(SourceType::Switchboard, account)),
+        ];
+        let listed = self
+            .sources
+            .iter()
+            .filter(|_| self.has_source_list())
+            .copied();
+        fixed.into_iter().flatten().chain(listed)
+    }
+
     pub fn new(
         mint: [u8; 32],
         scope_details: Option<[u16; 3]>,
@@ -48,14 +421,19 @@ impl MintMapping {
     ) -> Self {
         let mut mapping = Self::default();
         let mut mapping_details = 0;
+        // The header and the three fixed sources take at most 105 bytes
+        let mut offset = Self::HEADER_LEN as u8;
         if scope_details.is_some() {
             mapping_details |= 0b001;
+            offset += 6;
         }
         if pyth_account.is_some() {
             mapping_details |= 0b010;
+            offset += 32;
         }
         if switch_board.is_some() {
             mapping_details |= 0b100;
+            offset += 32;
         }
 
         mapping.mint = mint;
@@ -64,7 +442,7 @@ impl MintMapping {
         mapping.scope_details = scope_details;
         mapping.pyth_account = pyth_account;
         mapping.switch_board = switch_board;
-        mapping.offset = mapping.serialized_size() as u8;
+        mapping.offset = offset;
         mapping
     }
 
@@ -103,19 +481,43 @@ impl MintMapping {
             let mut switch = [0u8; 32];
             switch.copy_from_slice(&bytes[data_offset..data_offset + 32]);
             mapping.switch_board = Some(switch);
+            data_offset += 32;
+        }
+
+        if (mapping.mapping_details & MAPPING_SOURCE_LIST) != 0 && data_offset < bytes.len() {
+            let (sources, read) = SourceList::from_bytes(&bytes[data_offset..])?;
+            mapping.sources = sources;
+            data_offset += read;
+        }
+
+        if (mapping.mapping_details & MAPPING_METADATA) != 0 && data_offset < bytes.len() {
+            let metadata = TokenMetadata::from_bytes(&bytes[data_offset..])?;
+            data_offset += metadata.serialized_size();
+            mapping.metadata = Some(metadata);
+        }
+
+        if (mapping.mapping_details & MAPPING_ALIAS) != 0 && data_offset < bytes.len() {
+            mapping.alias = Some(MintAlias::from_bytes(&bytes[data_offset..])?);
+            data_offset += MintAlias::LEN;
+        }
+
+        if (mapping.mapping_details & MAPPING_ATTRIBUTES) != 0 && data_offset < bytes.len() {
+            mapping.attributes = AttributeList::from_bytes(&bytes[data_offset..])?.0;
         }
 
         Ok(mapping)
     }
 
     /// Convert a MintMapping to a byte array with extreme efficiency
-    /// Returns exact size needed (35-71 bytes) - includes offset field
-    pub fn to_bytes(&self) -> [u8; 105] {
-        let mut bytes = [0; 105];
+    /// Only the first `serialized_size()` bytes are used - includes offset field
+    /// Fails with `InvalidMappingSize` when the entry is larger than `MAX_LEN`
+    pub fn to_bytes(&self) -> Result<[u8; Self::MAX_LEN], ProgramError> {
+        let size = self.checked_size()?;
+        let mut bytes = [0; Self::MAX_LEN];
 
         // Header: mint(32) + offset(1) + mapping_details(1) + decimals(1) = 35 bytes
         bytes[0..32].copy_from_slice(&self.mint);
-        // Offset for the mapping written in the last at index 32
+        bytes[32] = size;
         bytes[33] = self.mapping_details;
         bytes[34] = self.decimals;
 
@@ -142,9 +544,30 @@ impl MintMapping {
             data_offset += 32;
         }
 
-        bytes[32] = data_offset as u8;
+        // Bit 4: tagged source list (variable length)
+        if (self.mapping_details & MAPPING_SOURCE_LIST) != 0 {
+            data_offset += self.sources.write_bytes(&mut bytes[data_offset..]);
+        }
+
+        // Bit 3: metadata (variable length)
+        if (self.mapping_details & MAPPING_METADATA) != 0 && self.metadata.is_some() {
+            data_offset += self
+                .metadata
+                .unwrap()
+                .write_bytes(&mut bytes[data_offset..]);
+        }
+
+        // Bit 5: alias (40 bytes)
+        if (self.mapping_details & MAPPING_ALIAS) != 0 && self.alias.is_some() {
+            data_offset += self.alias.unwrap().write_bytes(&mut bytes[data_offset..]);
+        }
+
+        // Bit 6: tagged attribute list (variable length)
+        if (self.mapping_details & MAPPING_ATTRIBUTES) != 0 {
+            self.attributes.write_bytes(&mut bytes[data_offset..]);
+        }
 
-        bytes
+        Ok(bytes)
     }
 
     pub fn serialized_size(&self) -> u16 {
@@ -159,6 +582,18 @@ impl MintMapping {
         if (self.mapping_details & 0b100) != 0 && self.switch_board.is_some() {
             size += 32;
         }
+        if (self.mapping_details & MAPPING_SOURCE_LIST) != 0 {
+            size += self.sources.serialized_size();
+        }
+        if (self.mapping_details & MAPPING_METADATA) != 0 && self.metadata.is_some() {
+            size += self.metadata.unwrap().serialized_size();
+        }
+        if (self.mapping_details & MAPPING_ALIAS) != 0 && self.alias.is_some() {
+            size += MintAlias::LEN;
+        }
+        if (self.mapping_details & MAPPING_ATTRIBUTES) != 0 {
+            size += self.attributes.serialized_size();
+        }
 
         size as u16
     }
@@ -167,10 +602,38 @@ impl MintMapping {
         let has_scope = (self.mapping_details & 0b001) != 0;
         let has_pyth = (self.mapping_details & 0b010) != 0;
         let has_switch = (self.mapping_details & 0b100) != 0;
+        let has_metadata = (self.mapping_details & MAPPING_METADATA) != 0;
+        let has_source_list = (self.mapping_details & MAPPING_SOURCE_LIST) != 0;
+        let has_alias = (self.mapping_details & MAPPING_ALIAS) != 0;
+        let has_attributes = (self.mapping_details & MAPPING_ATTRIBUTES) != 0;
 
-        (has_scope == self.scope_details.is_some())
+        (self.mapping_details & !MAPPING_INPUT_FLAGS) == 0
+            && (has_scope == self.scope_details.is_some())
             && (has_pyth == self.pyth_account.is_some())
             && (has_switch == self.switch_board.is_some())
+            && (has_metadata == self.metadata.is_some())
+            && !matches!(self.metadata, Some(metadata) if !metadata.is_valid())
+            && (has_source_list == !self.sources.is_empty())
+            && self.sources.is_valid()
+            && (has_alias == self.alias.is_some())
+            && !matches!(self.alias, Some(alias) if !alias.is_valid() || alias.target == self.mint)
+            && !(has_alias && self.oracle_sources().next().is_some())
+            && self.fixed_references_are_valid()
+            && (has_attributes == !self.attributes.is_empty())
+            && self.attributes.is_valid()
+            && self.serialized_size() as usize <= Self::MAX_LEN
+    }
+
+    /// Guard bands of fixed prices must reference a source of the mapping that is not fixed.
+    fn fixed_references_are_valid(&self) -> bool {
+        self.oracle_sources()
+            .filter_map(|source| source.fixed_price()?.guard())
+            .all(|(reference, _)| {
+                self.oracle_sources()
+                    .nth(reference as usize)
+                    .and_then(|source| source.source_type())
+                    .is_some_and(|source_type| source_type != SourceType::Fixed)
+            })
     }
 
     pub fn has_scope(&self) -> bool {
@@ -182,52 +645,152 @@ impl MintMapping {
     pub fn has_switch_board(&self) -> bool {
         (self.mapping_details & 0b100) != 0
     }
+    pub fn has_metadata(&self) -> bool {
+        (self.mapping_details & MAPPING_METADATA) != 0
+    }
+    pub fn has_source_list(&self) -> bool {
+        (self.mapping_details & MAPPING_SOURCE_LIST) != 0
+    }
+    pub fn has_attributes(&self) -> bool {
+        (self.mapping_details & MAPPING_ATTRIBUTES) != 0
+    }
+    pub fn is_alias(&self) -> bool {
+        (self.mapping_details & MAPPING_ALIAS) != 0
+    }
 
     /// Set mapping details with validation
     pub fn set_mapping_details(&mut self, details: u8) {
-        self.mapping_details = details & 0b111; // Ensure only 3 bits are used
+        self.mapping_details = details
+            & (0b111 | MAPPING_METADATA | MAPPING_SOURCE_LIST | MAPPING_ALIAS | MAPPING_ATTRIBUTES);
+        // Ensure only known bits are used
+    }
+
+    pub fn is_closed(&self) -> bool {
+        (self.mapping_details & MAPPING_CLOSED) != 0
     }
 
     pub fn enabled_components(&self) -> u8 {
-        self.mapping_details.count_ones() as u8
+        self.oracle_sources().count() as u8
     }
 
+    /// The mapping of `mint` at `slot`, following aliases to the entry that holds the oracle
+    /// sources.
     pub fn get_mapping_details(
         data: &[u8],
         mint: &[u8; 32],
+        slot: u64,
     ) -> Result<MintMapping, MappingProgramError> {
-        let mut total_size = unsafe { *data.get_unchecked(34) } as u16;
-        let mut offset = ScopeMappingRegistry::LEN;
-
-        while total_size > 0 {
-            let mint_end_offset = unsafe { *data.get_unchecked(offset + 32) } as usize;
-            if data[offset..offset + 32] == *mint {
-                return MintMapping::from_bytes(&data[offset..offset + mint_end_offset])
-                    .map_err(|_| MappingProgramError::MintNotFound);
+        Self::resolve(data, mint, slot).map(|resolved| resolved.mapping)
+    }
+
+    /// Looks up `mint` and follows aliases, at most `MAX_ALIAS_DEPTH` of them, to a regular entry.
+    /// Fails with `AliasCycle` when the chain loops or is too long, `MintNotFound` when any entry
+    /// along it is missing or closed, and `MappingNotActive` when any is outside its validity
+    /// window at `slot`. Sources of providers the registry has disabled are left out.
+    pub fn resolve(
+        data: &[u8],
+        mint: &[u8; 32],
+        slot: u64,
+    ) -> Result<ResolvedMapping, MappingProgramError> {
+        Self::resolve_pages(&[data], mint, slot)
+    }
+
+    /// `resolve` over a registry and its continuation pages, with `pages` holding the account
+    /// data of the registry first and then of its pages in order. Aliases can point at entries
+    /// in any page, and the disabled providers are those of the registry.
+    pub fn resolve_pages(
+        pages: &[&[u8]],
+        mint: &[u8; 32],
+        slot: u64,
+    ) -> Result<ResolvedMapping, MappingProgramError> {
+        let (page, (start, end), multiplier) = Self::resolve_offset_pages(pages, mint, slot)?;
+        let registry_data = pages.first().ok_or(MappingProgramError::MintNotFound)?;
+        let registry = ScopeMappingRegistry::from_account_data(registry_data)
+            .map_err(|_| MappingProgramError::InvalidAccountData)?;
+        let mapping = MintMapping::from_bytes(&pages[page][start..end])
+            .and_then(|mapping| mapping.without_providers(registry.disabled_providers))
+            .map_err(|_| MappingProgramError::InvalidAccountData)?;
+        Ok(ResolvedMapping {
+            mapping,
+            multiplier,
+        })
+    }
+
+    /// Entry bounds of the regular entry `mint` resolves to, and the alias multiplier. The raw
+    /// entry still holds the sources of disabled providers.
+    pub fn resolve_offset(
+        data: &[u8],
+        mint: &[u8; 32],
+        slot: u64,
+    ) -> Result<((usize, usize), u64), MappingProgramError> {
+        let (_, bounds, multiplier) = Self::resolve_offset_pages(&[data], mint, slot)?;
+        Ok((bounds, multiplier))
+    }
+
+    /// `resolve_offset` over a registry and its continuation pages, also returning the index in
+    /// `pages` of the account holding the entry.
+    pub fn resolve_offset_pages(
+        pages: &[&[u8]],
+        mint: &[u8; 32],
+        slot: u64,
+    ) -> Result<(usize, (usize, usize), u64), MappingProgramError> {
+        let mut visited = [[0u8; 32]; MAX_ALIAS_DEPTH + 1];
+        let mut current = *mint;
+        let mut multiplier = ALIAS_MULTIPLIER_ONE;
+
+        for depth in 0..=MAX_ALIAS_DEPTH {
+            if visited[..depth].contains(&current) {
+                return Err(MappingProgramError::AliasCycle);
             }
-            offset += mint_end_offset;
-            total_size -= 1;
-        }
+            visited[depth] = current;
 
-        Err(MappingProgramError::MintNotFound)
+            let (page, (start, end)) = Self::get_mapping_offset_pages(pages, &current)?;
+            let mapping = MintMapping::from_bytes(&pages[page][start..end])
+                .map_err(|_| MappingProgramError::InvalidAccountData)?;
+            if !mapping.is_active(slot) {
+                return Err(MappingProgramError::MappingNotActive);
+            }
+            if !mapping.is_alias() {
+                return Ok((page, (start, end), multiplier));
+            }
+            let alias = mapping
+                .alias
+                .ok_or(MappingProgramError::InvalidAccountData)?;
+            multiplier = chain_multiplier(multiplier, alias.multiplier)
+                .ok_or(MappingProgramError::InvalidAccountData)?;
+            current = alias.target;
+        }
+        Err(MappingProgramError::AliasCycle)
     }
 
+    /// Bounds of the live entry of `mint` regardless of its validity window, for instructions
+    /// that edit entries. Lookups go through `resolve`.
     pub fn get_mapping_offset(
         data: &[u8],
         mint: &[u8; 32],
     ) -> Result<(usize, usize), MappingProgramError> {
-        let mut total_size = unsafe { *data.get_unchecked(34) } as u16;
-        let mut offset = ScopeMappingRegistry::LEN;
+        let mut offsets = ScopeMappingRegistry::mapping_offsets(data)
+            .map_err(|_| MappingProgramError::InvalidAccountData)?;
 
-        while total_size > 0 {
-            let mint_end_offset = unsafe { *data.get_unchecked(offset + 32) } as usize;
-            if data[offset..offset + 32] == *mint {
-                return Ok((offset, offset + mint_end_offset));
+        offsets
+            .find(|(start, _)| data[*start..*start + 32] == *mint)
+            .ok_or(MappingProgramError::MintNotFound)
+    }
+
+    /// `get_mapping_offset` over a registry and its continuation pages, in page order, so a mint
+    /// listed twice takes its first entry. Returns the index in `pages` of the account holding
+    /// the entry along with its bounds.
+    pub fn get_mapping_offset_pages(
+        pages: &[&[u8]],
+        mint: &[u8; 32],
+    ) -> Result<(usize, (usize, usize)), MappingProgramError> {
+        for (page, data) in pages.iter().enumerate() {
+            match Self::get_mapping_offset(data, mint) {
+                Ok(bounds) => return Ok((page, bounds)),
+                Err(MappingProgramError::MintNotFound) => continue,
+                Err(err) => return Err(err),
             }
-            offset += mint_end_offset;
-            total_size -= 1;
         }
-
         Err(MappingProgramError::MintNotFound)
     }
 }
@@ -0,0 +1,330 @@
+use pinocchio::program_error::ProgramError;
+
+use crate::{
+    attributes::{PriceSelection, QuoteCurrency},
+    error::MappingProgramError,
+};
+
+/// A price as `value * 10^expo`, with its confidence interval in the same exponent and the unix
+/// timestamp it was published at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Price {
+    pub value: i64,
+    pub expo: i32,
+    pub conf: u64,
+    pub timestamp: i64,
+}
+
+// Scope OraclePrices: discriminator(8) + oracle_mappings(32) + [DatedPrice; N]
+pub const SCOPE_PRICES_OFFSET: usize = 40;
+// DatedPrice: value(u64) + exp(u64) + last_updated_slot(u64) + unix_timestamp(u64) + reserved(24)
+pub const SCOPE_DATED_PRICE_LEN: usize = 56;
+/// Terminates a Scope conversion chain shorter than three hops
+pub const SCOPE_CHAIN_END: u16 = u16::MAX;
+
+// Pyth PriceUpdateV2
+const PYTH_VERIFICATION_LEVEL_OFFSET: usize = 40;
+const PYTH_VERIFICATION_FULL: u8 = 1;
+const PYTH_PRICE_OFFSET: usize = 73;
+const PYTH_CONF_OFFSET: usize = 81;
+const PYTH_EXPO_OFFSET: usize = 89;
+const PYTH_PUBLISH_TIME_OFFSET: usize = 93;
+const PYTH_EMA_PRICE_OFFSET: usize = 109;
+const PYTH_EMA_CONF_OFFSET: usize = 117;
+const PYTH_PRICE_UPDATE_LEN: usize = 125;
+
+// Switchboard On-Demand PullFeedAccountData, values are i128 with 18 decimals
+const SWITCHBOARD_LAST_UPDATE_OFFSET: usize = 2216;
+const SWITCHBOARD_VALUE_OFFSET: usize = 2264;
+const SWITCHBOARD_STD_DEV_OFFSET: usize = 2280;
+const SWITCHBOARD_DECIMALS: i32 = 18;
+
+// SPL StakePool: account_type(1) + 8 pubkeys and a bump seed before the totals
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+const STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+const STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET: usize = 266;
+const STAKE_POOL_RATE_EXPO: i32 = -9;
+
+/// Most sources an aggregate is taken over
+pub const MAX_AGGREGATED_SOURCES: usize = 8;
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_i64(data: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_i128(data: &[u8], offset: usize) -> i128 {
+    i128::from_le_bytes(data[offset..offset + 16].try_into().unwrap())
+}
+
+/// Drops precision from `value` and `conf` until both fit an `i64`/`u64` price.
+fn fit(mut value: i128, mut conf: u128, mut expo: i32, timestamp: i64) -> Option<Price> {
+    while value.unsigned_abs() > i64::MAX as u128 || conf > u64::MAX as u128 {
+        value /= 10;
+        conf /= 10;
+        expo = expo.checked_add(1)?;
+    }
+    Some(Price {
+        value: value as i64,
+        expo,
+        conf: conf as u64,
+        timestamp,
+    })
+}
+
+/// Scales `value` from `from_expo` to the smaller `to_expo`.
+fn rescale(value: i128, from_expo: i32, to_expo: i32) -> Option<i128> {
+    let shift = u32::try_from(from_expo.checked_sub(to_expo)?).ok()?;
+    value.checked_mul(10i128.checked_pow(shift)?)
+}
+
+impl Price {
+    /// Serialized as value(8) + expo(4) + conf(8) + timestamp(8), little-endian.
+    pub const LEN: usize = 28;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            value: read_i64(bytes, 0),
+            expo: read_i32(bytes, 8),
+            conf: read_u64(bytes, 12),
+            timestamp: read_i64(bytes, 20),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.value.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.expo.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.conf.to_le_bytes());
+        bytes[20..28].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes
+    }
+
+    /// This price in `expo`, which must not be larger than `self.expo`.
+    pub fn value_in(&self, expo: i32) -> Option<i128> {
+        rescale(self.value as i128, self.expo, expo)
+    }
+
+    /// Product of two prices, e.g. a token priced in SOL times SOL in USD. The result has the
+    /// older of the two timestamps and a confidence of the summed relative confidences.
+    pub fn mul(&self, other: &Price) -> Option<Price> {
+        let value = (self.value as i128).checked_mul(other.value as i128)?;
+        let conf = (self.conf as u128)
+            .checked_mul(other.value.unsigned_abs() as u128)?
+            .checked_add((other.conf as u128).checked_mul(self.value.unsigned_abs() as u128)?)?;
+        fit(
+            value,
+            conf,
+            self.expo.checked_add(other.expo)?,
+            self.timestamp.min(other.timestamp),
+        )
+    }
+}
+
+/// Reads the `index`-th price of a Scope `OraclePrices` account.
+pub fn parse_scope_price(data: &[u8], index: u16) -> Result<Price, ProgramError> {
+    let start = SCOPE_PRICES_OFFSET + index as usize * SCOPE_DATED_PRICE_LEN;
+    if data.len() < start + SCOPE_DATED_PRICE_LEN {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    let exp = read_u64(data, start + 8);
+    let expo = i32::try_from(exp).map_err(|_| MappingProgramError::InvalidOracleAccount)?;
+    fit(
+        read_u64(data, start) as i128,
+        0,
+        -expo,
+        read_u64(data, start + 24) as i64,
+    )
+    .ok_or(MappingProgramError::InvalidOracleAccount.into())
+}
+
+/// Number of prices in the array of a Scope `OraclePrices` account.
+pub fn scope_price_count(data: &[u8]) -> usize {
+    data.len().saturating_sub(SCOPE_PRICES_OFFSET) / SCOPE_DATED_PRICE_LEN
+}
+
+/// Checks that every index of a conversion chain, up to `SCOPE_CHAIN_END`, falls inside the
+/// price array of a Scope `OraclePrices` account.
+pub fn verify_scope_chain(data: &[u8], chain: &[u16; 3]) -> Result<(), ProgramError> {
+    let count = scope_price_count(data);
+    if chain
+        .iter()
+        .take_while(|index| **index != SCOPE_CHAIN_END)
+        .any(|index| *index as usize >= count)
+    {
+        return Err(MappingProgramError::ScopeIndexOutOfBounds.into());
+    }
+    Ok(())
+}
+
+/// Multiplies the Scope prices along a conversion chain, stopping at `SCOPE_CHAIN_END`.
+pub fn parse_scope_chain(data: &[u8], chain: &[u16; 3]) -> Result<Price, ProgramError> {
+    let mut hops = chain.iter().take_while(|index| **index != SCOPE_CHAIN_END);
+    let first = hops.next().ok_or(MappingProgramError::InvalidMappingSize)?;
+    let mut price = parse_scope_price(data, *first)?;
+    for index in hops {
+        price = price
+            .mul(&parse_scope_price(data, *index)?)
+            .ok_or(MappingProgramError::InvalidOracleAccount)?;
+    }
+    Ok(price)
+}
+
+fn parse_pyth_update(
+    data: &[u8],
+    price_offset: usize,
+    conf_offset: usize,
+) -> Result<Price, ProgramError> {
+    if data.len() < PYTH_PRICE_UPDATE_LEN
+        || data[PYTH_VERIFICATION_LEVEL_OFFSET] != PYTH_VERIFICATION_FULL
+    {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    Ok(Price {
+        value: read_i64(data, price_offset),
+        expo: read_i32(data, PYTH_EXPO_OFFSET),
+        conf: read_u64(data, conf_offset),
+        timestamp: read_i64(data, PYTH_PUBLISH_TIME_OFFSET),
+    })
+}
+
+/// Reads the spot price of a fully verified Pyth `PriceUpdateV2` account.
+pub fn parse_pyth_price(data: &[u8]) -> Result<Price, ProgramError> {
+    parse_pyth_update(data, PYTH_PRICE_OFFSET, PYTH_CONF_OFFSET)
+}
+
+/// Reads the EMA price of a fully verified Pyth `PriceUpdateV2` account.
+pub fn parse_pyth_ema_price(data: &[u8]) -> Result<Price, ProgramError> {
+    parse_pyth_update(data, PYTH_EMA_PRICE_OFFSET, PYTH_EMA_CONF_OFFSET)
+}
+
+/// Reads the Pyth price a mapping selected: spot, EMA, or the lower of both with the wider
+/// confidence.
+pub fn parse_pyth_selected_price(
+    data: &[u8],
+    selection: PriceSelection,
+) -> Result<Price, ProgramError> {
+    match selection {
+        PriceSelection::Spot => parse_pyth_price(data),
+        PriceSelection::Ema => parse_pyth_ema_price(data),
+        PriceSelection::Conservative => {
+            let spot = parse_pyth_price(data)?;
+            let ema = parse_pyth_ema_price(data)?;
+            // Both share the exponent of the update
+            Ok(Price {
+                value: spot.value.min(ema.value),
+                conf: spot.conf.max(ema.conf),
+                ..spot
+            })
+        }
+    }
+}
+
+/// Reads the latest result of a Switchboard On-Demand `PullFeedAccountData` account, using the
+/// standard deviation of the samples as confidence.
+pub fn parse_switchboard_price(data: &[u8]) -> Result<Price, ProgramError> {
+    if data.len() < SWITCHBOARD_STD_DEV_OFFSET + 16 {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    let std_dev = read_i128(data, SWITCHBOARD_STD_DEV_OFFSET);
+    fit(
+        read_i128(data, SWITCHBOARD_VALUE_OFFSET),
+        std_dev.unsigned_abs(),
+        -SWITCHBOARD_DECIMALS,
+        read_i64(data, SWITCHBOARD_LAST_UPDATE_OFFSET),
+    )
+    .ok_or(MappingProgramError::InvalidOracleAccount.into())
+}
+
+/// `total_lamports` and `pool_token_supply` of an SPL stake pool account.
+pub fn parse_stake_pool(data: &[u8]) -> Result<(u64, u64), ProgramError> {
+    if data.len() < STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET + 8 || data[0] != STAKE_POOL_ACCOUNT_TYPE {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    Ok((
+        read_u64(data, STAKE_POOL_TOTAL_LAMPORTS_OFFSET),
+        read_u64(data, STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET),
+    ))
+}
+
+/// Lamports per pool token of an SPL stake pool, with 9 decimals. Pool tokens and SOL both have
+/// 9 decimals, so multiplying by the SOL price gives the pool token price. The rate carries no
+/// publish time of its own.
+pub fn parse_stake_pool_rate(data: &[u8]) -> Result<Price, ProgramError> {
+    let (total_lamports, pool_token_supply) = parse_stake_pool(data)?;
+    if pool_token_supply == 0 {
+        return Err(MappingProgramError::InvalidOracleAccount.into());
+    }
+    let rate = total_lamports as u128 * 10u128.pow(STAKE_POOL_RATE_EXPO.unsigned_abs())
+        / pool_token_supply as u128;
+    fit(rate as i128, 0, STAKE_POOL_RATE_EXPO, i64::MAX)
+        .ok_or(MappingProgramError::InvalidOracleAccount.into())
+}
+
+/// Converts a price quoted in `quote` to USD, given the USD price of the quote currency for
+/// anything but USD. `None` when the conversion price is missing or the product overflows.
+pub fn to_usd(native: &Price, quote: QuoteCurrency, conversion: Option<&Price>) -> Option<Price> {
+    match quote {
+        QuoteCurrency::Usd => Some(*native),
+        _ => native.mul(conversion?),
+    }
+}
+
+/// Whether `price` is within `band_bps` basis points of `reference`.
+pub fn within_band(price: &Price, reference: &Price, band_bps: u16) -> bool {
+    let expo = price.expo.min(reference.expo);
+    let (Some(value), Some(reference)) = (price.value_in(expo), reference.value_in(expo)) else {
+        return false;
+    };
+    let Some(deviation) = value
+        .checked_sub(reference)
+        .and_then(|d| d.checked_mul(10_000))
+    else {
+        return false;
+    };
+    reference
+        .checked_mul(band_bps as i128)
+        .is_some_and(|band| deviation.abs() <= band)
+}
+
+/// Median of the positive prices in `prices`, the mean of the middle two for an even count.
+/// The confidence is the widest one and the timestamp the oldest one of the inputs.
+pub fn aggregate(prices: &[Price]) -> Option<Price> {
+    let mut values = [0i128; MAX_AGGREGATED_SOURCES];
+    let mut count = 0;
+    let expo = prices.iter().map(|price| price.expo).min()?;
+    let mut conf = 0u128;
+    let mut timestamp = i64::MAX;
+
+    for price in prices.iter().filter(|price| price.value > 0) {
+        if count == MAX_AGGREGATED_SOURCES {
+            break;
+        }
+        values[count] = price.value_in(expo)?;
+        conf = conf.max(rescale(price.conf as i128, price.expo, expo)? as u128);
+        timestamp = timestamp.min(price.timestamp);
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+
+    let values = &mut values[..count];
+    values.sort_unstable();
+    let median = if count % 2 == 1 {
+        values[count / 2]
+    } else {
+        (values[count / 2 - 1] + values[count / 2]) / 2
+    };
+    fit(median, conf, expo, timestamp)
+}
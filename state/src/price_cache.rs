@@ -0,0 +1,189 @@
+use super::utils::{DataLen, Initialized};
+use alloc::vec::Vec;
+use pinocchio::{
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    ProgramResult,
+};
+
+use crate::{attributes::QuoteCurrency, error::MappingProgramError, price::Price};
+
+/// Number of mints a price cache holds.
+pub const PRICE_CACHE_CAPACITY: u16 = 64;
+
+/// An aggregated price written by `RefreshPrices`, in USD and in the currency the sources of the
+/// mint quote it in. Serialized without padding:
+/// mint(32) + price(28) + slot(8) + native(28) + quote(1) = 97 bytes, prices being
+/// value(8) + expo(4) + conf(8) + timestamp(8)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CachedPrice {
+    pub mint: [u8; 32],
+    pub price: Price, // USD
+    pub slot: u64,    // Slot the price was refreshed at
+    pub native: Price,
+    pub quote: QuoteCurrency,
+}
+
+impl CachedPrice {
+    pub const LEN: usize = 97;
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut cached = Self {
+            mint: [0; 32],
+            price: Price::from_bytes(&bytes[32..60])?,
+            slot: u64::from_le_bytes(bytes[60..68].try_into().unwrap()),
+            native: Price::from_bytes(&bytes[68..96])?,
+            quote: QuoteCurrency::try_from(bytes[96])
+                .map_err(|_| ProgramError::InvalidAccountData)?,
+        };
+        cached.mint.copy_from_slice(&bytes[0..32]);
+        Ok(cached)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..32].copy_from_slice(&self.mint);
+        bytes[32..60].copy_from_slice(&self.price.to_bytes());
+        bytes[60..68].copy_from_slice(&self.slot.to_le_bytes());
+        bytes[68..96].copy_from_slice(&self.native.to_bytes());
+        bytes[96] = self.quote as u8;
+        bytes
+    }
+}
+
+/// Header of the price-cache PDA that sits next to each registry. The header is followed by
+/// `capacity` fixed-size `CachedPrice` slots, the first `count` of which are in use.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankAccount)]
+pub struct PriceCache {
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub capacity: u16,
+    pub count: u16, // Number of mints with a cached price
+    pub registry: [u8; 32],
+}
+
+impl DataLen for PriceCache {
+    const LEN: usize = core::mem::size_of::<PriceCache>();
+}
+
+impl Initialized for PriceCache {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized > 0
+    }
+}
+
+impl PriceCache {
+    pub const SEED: &'static str = "PriceCache";
+
+    pub fn new(registry: [u8; 32], bump: u8) -> Self {
+        Self {
+            is_initialized: 1,
+            bump,
+            capacity: PRICE_CACHE_CAPACITY,
+            count: 0,
+            registry,
+        }
+    }
+
+    pub fn account_size() -> usize {
+        Self::LEN + PRICE_CACHE_CAPACITY as usize * CachedPrice::LEN
+    }
+
+    pub fn validate_pda(
+        bump: u8,
+        pda: &Pubkey,
+        registry: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let seed_with_bump = &[Self::SEED.as_bytes(), registry, &[bump]];
+        let derived = pubkey::create_program_address(seed_with_bump, program_id)?;
+        if derived != *pda {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        Ok(())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let cache = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const Self) };
+        if !cache.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if cache.count > cache.capacity
+            || data.len() < Self::LEN + cache.capacity as usize * CachedPrice::LEN
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(cache)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                self as *const Self as *const u8,
+                bytes.as_mut_ptr(),
+                Self::LEN,
+            );
+        }
+        bytes
+    }
+
+    fn slot_range(index: u16) -> (usize, usize) {
+        let start = Self::LEN + index as usize * CachedPrice::LEN;
+        (start, start + CachedPrice::LEN)
+    }
+
+    fn position(&self, data: &[u8], mint: &[u8; 32]) -> Option<u16> {
+        (0..self.count).find(|index| {
+            let (start, _) = Self::slot_range(*index);
+            data[start..start + 32] == *mint
+        })
+    }
+
+    /// Overwrites the cached price of `cached.mint`, or takes the next free slot for a new mint,
+    /// and stores the updated header in `data`.
+    pub fn upsert(&mut self, data: &mut [u8], cached: &CachedPrice) -> ProgramResult {
+        let index = match self.position(data, &cached.mint) {
+            Some(index) => index,
+            None if self.count < self.capacity => {
+                self.count += 1;
+                self.count - 1
+            }
+            None => return Err(MappingProgramError::PriceCacheFull.into()),
+        };
+        let (start, end) = Self::slot_range(index);
+        data[start..end].copy_from_slice(&cached.to_bytes());
+        data[..Self::LEN].copy_from_slice(&self.to_bytes());
+        Ok(())
+    }
+
+    /// The cached price of `mint`, `MintNotFound` when it was never refreshed.
+    pub fn get_price(data: &[u8], mint: &[u8; 32]) -> Result<CachedPrice, ProgramError> {
+        let cache = Self::from_account_data(data)?;
+        let index = cache
+            .position(data, mint)
+            .ok_or(MappingProgramError::MintNotFound)?;
+        let (start, end) = Self::slot_range(index);
+        CachedPrice::from_bytes(&data[start..end])
+    }
+
+    /// All cached prices in slot order.
+    pub fn prices(data: &[u8]) -> Result<Vec<CachedPrice>, ProgramError> {
+        let cache = Self::from_account_data(data)?;
+        (0..cache.count)
+            .map(|index| {
+                let (start, end) = Self::slot_range(index);
+                CachedPrice::from_bytes(&data[start..end])
+            })
+            .collect()
+    }
+}
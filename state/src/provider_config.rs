@@ -0,0 +1,59 @@
+use pinocchio::program_error::ProgramError;
+use shank::ShankType;
+
+use crate::source_type::SourceType;
+
+/// Expected owner program of the oracle accounts for each source type, stored in the registry
+/// header. An all-zero program disables the owner check for that source.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, ShankType, Default)]
+pub struct ProviderConfig {
+    pub scope_program: [u8; 32],
+    pub pyth_program: [u8; 32],
+    pub switchboard_program: [u8; 32],
+    pub strict: u8, // 1 (the default) = AddMapping must be passed every oracle account of the mapping
+}
+
+impl ProviderConfig {
+    pub const LEN: usize = 97;
+
+    /// Expected owner for a source type, `None` when unchecked.
+    pub fn expected_owner(&self, source: SourceType) -> Option<&[u8; 32]> {
+        let program = match source {
+            SourceType::Scope => &self.scope_program,
+            SourceType::Pyth => &self.pyth_program,
+            SourceType::Switchboard => &self.switchboard_program,
+            _ => return None,
+        };
+        if program == &[0u8; 32] {
+            None
+        } else {
+            Some(program)
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict != 0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        if bytes.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut config = Self::default();
+        config.scope_program.copy_from_slice(&bytes[0..32]);
+        config.pyth_program.copy_from_slice(&bytes[32..64]);
+        config.switchboard_program.copy_from_slice(&bytes[64..96]);
+        config.strict = bytes[96];
+        Ok(config)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..32].copy_from_slice(&self.scope_program);
+        bytes[32..64].copy_from_slice(&self.pyth_program);
+        bytes[64..96].copy_from_slice(&self.switchboard_program);
+        bytes[96] = self.strict;
+        bytes
+    }
+}
@@ -0,0 +1,89 @@
+use pinocchio::ProgramResult;
+use shank::ShankType;
+
+use crate::error::MappingProgramError;
+
+/// Caps the mapping changes a registry accepts per window, limiting what a leaked authority key
+/// can do before it is rotated. Windows are epochs, or blocks of `window_slots` slots when set,
+/// and the counter resets when a new window starts. Changes co-signed by the guardian go through
+/// past the limit.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, ShankType, Default)]
+pub struct RateLimit {
+    pub max_mutations: u16, // 0 = unlimited
+    pub mutations: u16,     // Changes counted in the current window
+    pub window_slots: u64,  // 0 = one window per epoch
+    pub window: u64,        // Epoch, or slot / window_slots, the counter belongs to
+    pub guardian: [u8; 32], // Co-signer that overrides the limit, zero for none
+}
+
+impl RateLimit {
+    pub fn new(max_mutations: u16, window_slots: u64, guardian: [u8; 32]) -> Self {
+        Self {
+            max_mutations,
+            window_slots,
+            guardian,
+            ..Self::default()
+        }
+    }
+
+    pub fn guardian(&self) -> Option<&[u8; 32]> {
+        Some(&self.guardian).filter(|guardian| *guardian != &[0u8; 32])
+    }
+
+    /// Window that `slot` of `epoch` falls in.
+    pub fn window_at(&self, slot: u64, epoch: u64) -> u64 {
+        slot.checked_div(self.window_slots).unwrap_or(epoch)
+    }
+
+    /// Changes counted in the window of `slot`.
+    pub fn mutations_at(&self, slot: u64, epoch: u64) -> u16 {
+        if self.window_at(slot, epoch) == self.window {
+            self.mutations
+        } else {
+            0
+        }
+    }
+
+    /// Changes still accepted in the window of `slot` without the guardian, `None` if unlimited.
+    pub fn remaining(&self, slot: u64, epoch: u64) -> Option<u16> {
+        if self.max_mutations == 0 {
+            return None;
+        }
+        Some(
+            self.max_mutations
+                .saturating_sub(self.mutations_at(slot, epoch)),
+        )
+    }
+
+    /// Takes the limit, window length and guardian of `settings`, carrying the changes counted in
+    /// the current window over so a new configuration doesn't reset the counter.
+    pub fn reconfigure(&mut self, settings: &RateLimit, slot: u64, epoch: u64) {
+        let mutations = self.mutations_at(slot, epoch);
+        self.max_mutations = settings.max_mutations;
+        self.window_slots = settings.window_slots;
+        self.guardian = settings.guardian;
+        self.window = self.window_at(slot, epoch);
+        self.mutations = mutations;
+    }
+
+    /// Counts one change in the window of `slot`, starting a new window when it has moved on.
+    /// Fails with `RateLimitExceeded` once the limit is reached, unless `guardian_signed`.
+    pub fn record_mutation(
+        &mut self,
+        slot: u64,
+        epoch: u64,
+        guardian_signed: bool,
+    ) -> ProgramResult {
+        let window = self.window_at(slot, epoch);
+        if window != self.window {
+            self.window = window;
+            self.mutations = 0;
+        }
+        if self.max_mutations != 0 && self.mutations >= self.max_mutations && !guardian_signed {
+            return Err(MappingProgramError::RateLimitExceeded.into());
+        }
+        self.mutations = self.mutations.saturating_add(1);
+        Ok(())
+    }
+}
@@ -1,5 +1,3 @@
-extern crate alloc;
-
 use super::utils::{DataLen, Initialized};
 use alloc::vec::Vec;
 use pinocchio::{
@@ -8,21 +6,49 @@ use pinocchio::{
     ProgramResult,
 };
 
-use crate::{error::MappingProgramError, mint_mapping::MintMapping};
+use crate::{
+    attributes::QuoteCurrency,
+    error::MappingProgramError,
+    merkle::mappings_root,
+    mint_mapping::{MintMapping, MAPPING_CLOSED},
+    provider_config::ProviderConfig,
+    rate_limit::RateLimit,
+    source_type::SourceType,
+};
 
-pub const MAX_MAPPINGS: u16 = 512;
+/// Entry cap of registries initialized without one
+pub const MAX_MAPPINGS: u32 = 512;
+/// Most continuation pages a registry can chain into
+pub const MAX_REGISTRY_PAGES: u32 = 16;
+/// Most Scope `OraclePrices` accounts a registry can name
+pub const MAX_SCOPE_FEEDS: usize = 4;
 
-pub const SCOPE_MAPPING_ADDRESS: &str = "Bx76evtFL2ZNeJwrdeysLtPiJDeu3dQ8ZVxVcR3kuWF9";
+/// Registry PDA of the mainnet authority under `ScopeMappingRegistry::SEED`
+pub const SCOPE_MAPPING_ADDRESS: &str = "87VZvKLUkoGEyGj91fQboYoh2kyLR2gz4ThGpjBLTqsH";
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, shank::ShankAccount)]
 pub struct ScopeMappingRegistry {
     pub is_initialized: u8,
     pub owner: [u8; 32],
-    pub total_mappings: u16,
-    pub version: u8,
-    pub last_mapping_offset: u16,
+    pub total_mappings: u32,
+    pub version: u32,
+    pub last_mapping_offset: u32, // End of the written entries, closed ones included
     pub bump: u8,
+    pub used_bytes: u32,                          // Bytes held by live entries
+    pub allocated_bytes: u32,                     // Bytes available for entries after the header
+    pub max_mappings: u32, // Live entries each account of the chain can hold, zero on a page
+    pub providers: ProviderConfig, // Expected oracle account owners
+    pub sol_usd_mint: [u8; 32], // Mint whose USD price converts SOL quotes, zero for none
+    pub rate_limit: RateLimit, // Mapping changes accepted per window
+    pub disabled_providers: u8, // `MAPPING_PROVIDERS` bits of providers lookups leave out
+    pub draft_of: [u8; 32], // Live registry a draft is promoted into, zero for a live one
+    pub mappings_root: [u8; 32], // Merkle root of the live entries, see `merkle`
+    pub page_of: [u8; 32], // Registry a continuation page belongs to, zero for a registry
+    pub page_index: u32,   // Position of a page in its registry's chain, from 1
+    pub page_count: u32,   // Continuation pages of a registry
+    pub pages_root: [u8; 32], // Merkle root over the `mappings_root` of the pages, see `merkle`
+    pub scope_feeds: [[u8; 32]; MAX_SCOPE_FEEDS], // Scope `OraclePrices` accounts, zero for none
 }
 
 impl DataLen for ScopeMappingRegistry {
@@ -36,7 +62,13 @@ impl Initialized for ScopeMappingRegistry {
 }
 
 impl ScopeMappingRegistry {
-    pub const SEED: &'static str = "ScopeMappingRegistry";
+    /// The header has no layout version, so a layout change moves the registry to a new seed
+    /// instead of reinterpreting an account written under the old one. `V2` is the header with
+    /// capacity tracking and everything added after it; the `ScopeMappingRegistry` account is
+    /// left as is and not read by this program.
+    pub const SEED: &'static str = "ScopeMappingRegistryV2";
+    pub const DRAFT_SEED: &'static str = "Draft";
+    pub const PAGE_SEED: &'static str = "RegistryPage";
 
     pub fn validate_pda(
         bump: u8,
@@ -52,16 +84,107 @@ impl ScopeMappingRegistry {
         Ok(())
     }
 
-    pub fn add(&mut self, mapping_size: u16) -> ProgramResult {
+    /// Validates the draft PDA (`["Draft", registry]`) of the live registry at `registry`.
+    pub fn validate_draft_pda(
+        bump: u8,
+        pda: &Pubkey,
+        registry: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let seed_with_bump = &[Self::DRAFT_SEED.as_bytes(), registry, &[bump]];
+        let derived = pubkey::create_program_address(seed_with_bump, program_id)?;
+        if derived != *pda {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        Ok(())
+    }
+
+    /// Validates the PDA (`["RegistryPage", registry, index]`) of continuation page `index` of
+    /// the registry at `registry`.
+    pub fn validate_page_pda(
+        bump: u8,
+        pda: &Pubkey,
+        registry: &Pubkey,
+        index: u32,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let index_bytes = index.to_le_bytes();
+        let seed_with_bump = &[Self::PAGE_SEED.as_bytes(), registry, &index_bytes, &[bump]];
+        let derived = pubkey::create_program_address(seed_with_bump, program_id)?;
+        if derived != *pda {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        Ok(())
+    }
+
+    /// Live registry a draft is promoted into, `None` for a live registry.
+    pub fn draft_of(&self) -> Option<&[u8; 32]> {
+        Some(&self.draft_of).filter(|registry| *registry != &[0u8; 32])
+    }
+
+    /// Validates `pda` as the address of this registry, live or draft, for instructions that
+    /// edit either.
+    pub fn validate_address(
+        &self,
+        pda: &Pubkey,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        match self.draft_of() {
+            Some(registry) => Self::validate_draft_pda(self.bump, pda, registry, program_id),
+            None => Self::validate_pda(self.bump, pda, owner, program_id),
+        }
+    }
+
+    /// Registry a continuation page belongs to, `None` for a registry or draft.
+    pub fn page_of(&self) -> Option<&[u8; 32]> {
+        Some(&self.page_of).filter(|registry| *registry != &[0u8; 32])
+    }
+
+    /// Validates `pda` as the address of this registry, draft or continuation page, for the
+    /// bookkeeping instructions that also take a page. Entries and settings are edited through
+    /// the registry or its draft.
+    pub fn validate_entries_address(
+        &self,
+        pda: &Pubkey,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        match self.page_of() {
+            Some(registry) => {
+                Self::validate_page_pda(self.bump, pda, registry, self.page_index, program_id)
+            }
+            None => self.validate_address(pda, owner, program_id),
+        }
+    }
+
+    /// Header of the next continuation page of this registry, with an empty mapping region and
+    /// only its own bookkeeping. Settings, the entry cap and the rate limit are read from the
+    /// registry.
+    pub fn next_page(&self, registry: [u8; 32], bump: u8) -> Self {
+        Self {
+            page_of: registry,
+            page_index: self.page_count + 1,
+            ..Self::new(self.owner, bump, 0)
+        }
+    }
+
+    /// Counts an entry of `mapping_size` bytes written after the last one, in an account whose
+    /// registry holds at most `max_mappings` live entries per account.
+    pub fn add(&mut self, mapping_size: u16, max_mappings: u32) -> ProgramResult {
         if !self.is_initialized() {
             return Err(ProgramError::InvalidAccountData);
         }
-        if self.total_mappings >= MAX_MAPPINGS {
+        if self.total_mappings >= max_mappings {
             return Err(MappingProgramError::MaxMappingsReached.into());
         }
+        if self.free_bytes() < mapping_size as u32 {
+            return Err(MappingProgramError::WriteOverflow.into());
+        }
         self.total_mappings += 1;
         self.version += 1;
-        self.last_mapping_offset += mapping_size;
+        self.last_mapping_offset += mapping_size as u32;
+        self.used_bytes += mapping_size as u32;
         Ok(())
     }
 
@@ -71,16 +194,170 @@ impl ScopeMappingRegistry {
         }
         self.total_mappings -= 1;
         self.version += 1;
-        self.last_mapping_offset -= mapping_size;
+        self.used_bytes -= mapping_size as u32;
+        Ok(())
+    }
+
+    /// Mint whose USD price converts prices quoted in `quote`, `None` for USD or when unset.
+    pub fn conversion_mint(&self, quote: QuoteCurrency) -> Option<[u8; 32]> {
+        match quote {
+            QuoteCurrency::Usd => None,
+            QuoteCurrency::Sol => Some(self.sol_usd_mint).filter(|mint| mint != &[0u8; 32]),
+        }
+    }
+
+    /// Sets the mint converting prices quoted in `quote`, all-zero to clear it.
+    pub fn set_conversion_mint(&mut self, quote: QuoteCurrency, mint: [u8; 32]) -> ProgramResult {
+        match quote {
+            QuoteCurrency::Usd => return Err(MappingProgramError::InvalidQuoteConversion.into()),
+            QuoteCurrency::Sol => self.sol_usd_mint = mint,
+        }
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Scope `OraclePrices` account at `index`, `None` when unset.
+    pub fn scope_feed(&self, index: u8) -> Option<[u8; 32]> {
+        self.scope_feeds
+            .get(index as usize)
+            .copied()
+            .filter(|feed| feed != &[0u8; 32])
+    }
+
+    /// Sets the Scope `OraclePrices` account at `index`, all-zero to clear it.
+    pub fn set_scope_feed(&mut self, index: u8, feed: [u8; 32]) -> ProgramResult {
+        let slot = self
+            .scope_feeds
+            .get_mut(index as usize)
+            .ok_or(MappingProgramError::ScopeFeedNotSet)?;
+        *slot = feed;
+        self.version += 1;
+        Ok(())
+    }
+
+    /// The feed the Scope chains of a mapping referencing feed `index` read. A registry without
+    /// any feeds leaves feed 0 unchecked, as before feeds were recorded.
+    pub fn mapping_scope_feed(&self, index: u8) -> Result<Option<[u8; 32]>, ProgramError> {
+        match self.scope_feed(index) {
+            Some(feed) => Ok(Some(feed)),
+            None if index == 0 && self.scope_feeds.iter().all(|feed| feed == &[0u8; 32]) => {
+                Ok(None)
+            }
+            None => Err(MappingProgramError::ScopeFeedNotSet.into()),
+        }
+    }
+
+    /// Whether lookups leave out sources of `source_type`. Only Scope, Pyth and Switchboard can
+    /// be disabled.
+    pub fn is_provider_disabled(&self, source_type: SourceType) -> bool {
+        source_type
+            .legacy_bit()
+            .is_some_and(|bit| self.disabled_providers & bit != 0)
+    }
+
+    /// Allocated bytes after the last written entry, available without a realloc.
+    pub fn free_bytes(&self) -> u32 {
+        self.allocated_bytes
+            .saturating_sub(self.last_mapping_offset)
+    }
+
+    /// Allocated bytes not held by live entries, reclaimable with a compaction.
+    pub fn slack_bytes(&self) -> u32 {
+        self.allocated_bytes.saturating_sub(self.used_bytes)
+    }
+
+    /// Turns the entry at `start..end` into a closed entry that lookups skip. The entry keeps its
+    /// size byte so the entries after it stay reachable; a closed entry at the end of the
+    /// written region is dropped right away.
+    pub fn close_mapping(&mut self, data: &mut [u8], start: usize, end: usize) -> ProgramResult {
+        let mapping_size = (end - start) as u16;
+        self.subtract_mapping(mapping_size)?;
+
+        // Zero out the entry to avoid data leakage
+        data[start..end].fill(0);
+
+        if end == Self::LEN + self.last_mapping_offset as usize {
+            self.last_mapping_offset -= mapping_size as u32;
+        } else {
+            data[start + 32] = mapping_size as u8;
+            data[start + 33] = MAPPING_CLOSED;
+        }
+        Ok(())
+    }
+
+    /// Recomputes `mappings_root` after the entries in `data` changed. The header is written to
+    /// `data` before and after, since the entries are found through it.
+    pub fn refresh_root(&mut self, data: &mut [u8]) -> ProgramResult {
+        data[..Self::LEN].copy_from_slice(&self.to_bytes());
+        self.mappings_root = mappings_root(data)?;
+        data[..Self::LEN].copy_from_slice(&self.to_bytes());
         Ok(())
     }
 
+    /// Closes live entries whose validity window ended before `slot`, at most `mints.len()` of
+    /// them, and records their mints in `mints`. Returns the number of entries closed and the
+    /// bytes they held.
+    pub fn close_expired(
+        &mut self,
+        data: &mut [u8],
+        slot: u64,
+        mints: &mut [[u8; 32]],
+    ) -> Result<(usize, u32), ProgramError> {
+        let mut count = 0;
+        let mut freed_bytes = 0u32;
+        while count < mints.len() {
+            let expired = Self::mapping_offsets(data)?.find(|(start, end)| {
+                MintMapping::from_bytes(&data[*start..*end])
+                    .is_ok_and(|mapping| mapping.validity_window().is_expired(slot))
+            });
+            let Some((start, end)) = expired else {
+                break;
+            };
+            mints[count].copy_from_slice(&data[start..start + 32]);
+            self.close_mapping(data, start, end)?;
+            // The offsets iterator reads the header from `data`
+            data[..Self::LEN].copy_from_slice(&self.to_bytes());
+            count += 1;
+            freed_bytes += (end - start) as u32;
+        }
+        Ok((count, freed_bytes))
+    }
+
+    /// Moves all live entries to the front of the mapping region, dropping closed ones, and
+    /// zeroes the freed tail. Returns the number of bytes the live entries now occupy.
+    pub fn compact_mappings(&mut self, data: &mut [u8]) -> Result<u32, ProgramError> {
+        let end = Self::LEN + self.last_mapping_offset as usize;
+        if data.len() < end {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut read = Self::LEN;
+        let mut write = Self::LEN;
+        while read < end {
+            let mapping_size = data[read + 32] as usize;
+            if mapping_size < MintMapping::HEADER_LEN || read + mapping_size > end {
+                return Err(MappingProgramError::InvalidMappingSize.into());
+            }
+            if data[read + 33] & MAPPING_CLOSED == 0 {
+                data.copy_within(read..read + mapping_size, write);
+                write += mapping_size;
+            }
+            read += mapping_size;
+        }
+        data[write..end].fill(0);
+
+        self.last_mapping_offset = (write - Self::LEN) as u32;
+        self.used_bytes = self.last_mapping_offset;
+        self.version += 1;
+        Ok(self.used_bytes)
+    }
+
     pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Result<Self, ProgramError> {
         if bytes.len() != Self::LEN {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let mapping = unsafe { *(bytes.as_ptr() as *const Self) };
+        let mapping = unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const Self) };
         Ok(mapping)
     }
 
@@ -102,7 +379,7 @@ impl ScopeMappingRegistry {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let mapping = unsafe { *(bytes.as_ptr() as *const Self) };
+        let mapping = unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const Self) };
         Ok(mapping)
     }
 
@@ -126,7 +403,7 @@ impl ScopeMappingRegistry {
     ) -> Result<(), ProgramError> {
         let reg_bytes = registry.to_bytes();
         data[..Self::LEN].copy_from_slice(&reg_bytes);
-        let mapping_bytes = mapping.to_bytes();
+        let mapping_bytes = mapping.to_bytes()?;
         data[Self::LEN..Self::LEN + mapping_bytes.len()].copy_from_slice(&mapping_bytes);
         Ok(())
     }
@@ -138,7 +415,18 @@ impl ScopeMappingRegistry {
         Ok(&data[Self::LEN..])
     }
 
-    pub fn new(owner: [u8; 32], bump: u8) -> Self {
+    /// Iterates over the `(start, end)` offsets of the live entries in registry account data.
+    pub fn mapping_offsets(data: &[u8]) -> Result<MappingOffsets<'_>, ProgramError> {
+        let registry = Self::from_account_data(data)?;
+        let end = Self::LEN + registry.last_mapping_offset as usize;
+        Ok(MappingOffsets {
+            data,
+            offset: Self::LEN,
+            end: end.min(data.len()),
+        })
+    }
+
+    pub fn new(owner: [u8; 32], bump: u8, max_mappings: u32) -> Self {
         Self {
             is_initialized: 1,
             owner: owner,
@@ -146,6 +434,45 @@ impl ScopeMappingRegistry {
             version: 0,
             last_mapping_offset: 0,
             bump: bump,
+            used_bytes: 0,
+            allocated_bytes: 0,
+            max_mappings,
+            providers: ProviderConfig::default(),
+            sol_usd_mint: [0; 32],
+            rate_limit: RateLimit::default(),
+            disabled_providers: 0,
+            draft_of: [0; 32],
+            mappings_root: [0; 32],
+            page_of: [0; 32],
+            page_index: 0,
+            page_count: 0,
+            pages_root: [0; 32],
+            scope_feeds: [[0; 32]; MAX_SCOPE_FEEDS],
+        }
+    }
+}
+
+pub struct MappingOffsets<'a> {
+    data: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl Iterator for MappingOffsets<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset + MintMapping::HEADER_LEN <= self.end {
+            let start = self.offset;
+            let mapping_size = self.data[start + 32] as usize;
+            if mapping_size < MintMapping::HEADER_LEN || start + mapping_size > self.end {
+                return None;
+            }
+            self.offset += mapping_size;
+            if self.data[start + 33] & MAPPING_CLOSED == 0 {
+                return Some((start, start + mapping_size));
+            }
         }
+        None
     }
 }
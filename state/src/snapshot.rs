@@ -0,0 +1,127 @@
+use super::utils::{DataLen, Initialized};
+use pinocchio::{
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+};
+
+use crate::{
+    error::MappingProgramError, scope_mapping_registry::ScopeMappingRegistry, utils::hash,
+};
+
+/// Header of a registry snapshot PDA (`["Snapshot", registry, number]`). The header is followed
+/// by a verbatim copy of the registry account data up to the end of its written entries, which
+/// the program never modifies after `Snapshot` creates the account.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, shank::ShankAccount)]
+pub struct RegistrySnapshot {
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub registry_version: u32, // Version of the registry when the snapshot was taken
+    pub data_len: u32,         // Bytes of registry data after the header
+    pub number: u64,
+    pub slot: u64, // Slot the snapshot was taken at
+    pub registry: [u8; 32],
+    pub content_hash: [u8; 32], // sha256 of the registry data after the header
+}
+
+impl DataLen for RegistrySnapshot {
+    const LEN: usize = core::mem::size_of::<RegistrySnapshot>();
+}
+
+impl Initialized for RegistrySnapshot {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized > 0
+    }
+}
+
+impl RegistrySnapshot {
+    pub const SEED: &'static str = "Snapshot";
+
+    /// Snapshot `number` of the registry at `registry`, holding `registry_data`.
+    pub fn new(
+        registry: [u8; 32],
+        number: u64,
+        bump: u8,
+        slot: u64,
+        registry_data: &[u8],
+    ) -> Result<Self, ProgramError> {
+        let header = ScopeMappingRegistry::from_account_data(registry_data)?;
+        Ok(Self {
+            is_initialized: 1,
+            bump,
+            registry_version: header.version,
+            data_len: registry_data.len() as u32,
+            number,
+            slot,
+            registry,
+            content_hash: hash(registry_data),
+        })
+    }
+
+    /// Registry account data to copy into a snapshot: the header and the written entries,
+    /// without the unused tail of the allocation.
+    pub fn registry_content(data: &[u8]) -> Result<&[u8], ProgramError> {
+        let registry = ScopeMappingRegistry::from_account_data(data)?;
+        let end = ScopeMappingRegistry::LEN + registry.last_mapping_offset as usize;
+        data.get(..end).ok_or(ProgramError::InvalidAccountData)
+    }
+
+    pub fn account_size(&self) -> usize {
+        Self::LEN + self.data_len as usize
+    }
+
+    pub fn validate_pda(
+        bump: u8,
+        pda: &Pubkey,
+        registry: &Pubkey,
+        number: u64,
+        program_id: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let number_bytes = number.to_le_bytes();
+        let seed_with_bump = &[Self::SEED.as_bytes(), registry, &number_bytes, &[bump]];
+        let derived = pubkey::create_program_address(seed_with_bump, program_id)?;
+        if derived != *pda {
+            return Err(MappingProgramError::PdaMismatch.into());
+        }
+        Ok(())
+    }
+
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let snapshot = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const Self) };
+        if !snapshot.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if data.len() < snapshot.account_size() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                self as *const Self as *const u8,
+                bytes.as_mut_ptr(),
+                Self::LEN,
+            );
+        }
+        bytes
+    }
+
+    /// The registry data held by a snapshot account, checked against its content hash. It opens
+    /// exactly like live registry account data, with `ScopeMappingRegistry::from_account_data`,
+    /// `ScopeMappingRegistry::mapping_offsets`, `MintMapping::resolve` and the other lookups.
+    pub fn registry_data(data: &[u8]) -> Result<&[u8], ProgramError> {
+        let snapshot = Self::from_account_data(data)?;
+        let registry_data = &data[Self::LEN..snapshot.account_size()];
+        if hash(registry_data) != snapshot.content_hash {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(registry_data)
+    }
+}
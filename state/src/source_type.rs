@@ -0,0 +1,356 @@
+use pinocchio::program_error::ProgramError;
+use shank::ShankType;
+
+use crate::{
+    error::MappingProgramError,
+    price::{within_band, Price},
+};
+
+/// `FixedPrice::reference` of a fixed price without a guard band.
+pub const NO_REFERENCE: u8 = u8::MAX;
+
+/// Known oracle source types. The value is the tag written in front of each entry of a mapping's
+/// source list; tag 0 is never used.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ShankType)]
+pub enum SourceType {
+    Scope = 1,       // Scope conversion chain, [u16; 3] LE
+    Pyth = 2,        // Pyth price update account
+    Switchboard = 3, // Switchboard pull feed account
+    Stork = 4,       // Stork temporal numeric value feed account
+    RedStone = 5,    // RedStone price data account
+    Chainlink = 6,   // Chainlink data feed account
+    StakePool = 7,   // SPL stake pool account + quote mint, 64 bytes
+    Fixed = 8,       // Constant price with an optional guard band, 15 bytes
+}
+
+impl SourceType {
+    pub const ALL: [SourceType; 8] = [
+        SourceType::Scope,
+        SourceType::Pyth,
+        SourceType::Switchboard,
+        SourceType::Stork,
+        SourceType::RedStone,
+        SourceType::Chainlink,
+        SourceType::StakePool,
+        SourceType::Fixed,
+    ];
+
+    /// Exact payload length of an entry of this type.
+    pub fn payload_len(self) -> usize {
+        match self {
+            SourceType::Scope => 6,
+            SourceType::StakePool => 64,
+            SourceType::Fixed => 15,
+            _ => 32,
+        }
+    }
+
+    /// Whether the payload starts with the address of an oracle account.
+    pub fn is_account(self) -> bool {
+        !matches!(self, SourceType::Scope | SourceType::Fixed)
+    }
+
+    /// Whether an oracle account is read to price the source. Scope sources read the
+    /// `OraclePrices` account, fixed prices nothing.
+    pub fn reads_account(self) -> bool {
+        self != SourceType::Fixed
+    }
+
+    /// The `mapping_details` bit of the fixed field holding this source, if any.
+    pub fn legacy_bit(self) -> Option<u8> {
+        match self {
+            SourceType::Scope => Some(0b001),
+            SourceType::Pyth => Some(0b010),
+            SourceType::Switchboard => Some(0b100),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SourceType::Scope => "scope",
+            SourceType::Pyth => "pyth",
+            SourceType::Switchboard => "switchboard",
+            SourceType::Stork => "stork",
+            SourceType::RedStone => "redstone",
+            SourceType::Chainlink => "chainlink",
+            SourceType::StakePool => "stake_pool",
+            SourceType::Fixed => "fixed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.name() == name)
+    }
+}
+
+impl TryFrom<u8> for SourceType {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|source| *source as u8 == value)
+            .ok_or(MappingProgramError::UnknownSourceType.into())
+    }
+}
+
+/// Constant price of a `Fixed` source. With a guard band, the price only counts while the price
+/// of the `reference`-th source of the same mapping is within `band_bps` of it, e.g. a
+/// stablecoin at 1.00 ± 2%.
+///
+/// Serialized as value(8) + expo(4) + band_bps(2) + reference(1), little-endian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedPrice {
+    pub value: i64,
+    pub expo: i32,
+    pub band_bps: u16,
+    /// Index into `MintMapping::oracle_sources`, `NO_REFERENCE` for an unguarded price
+    pub reference: u8,
+}
+
+impl FixedPrice {
+    pub fn price(&self) -> Price {
+        Price {
+            value: self.value,
+            expo: self.expo,
+            conf: 0,
+            timestamp: i64::MAX,
+        }
+    }
+
+    pub fn guard(&self) -> Option<(u8, u16)> {
+        (self.reference != NO_REFERENCE).then_some((self.reference, self.band_bps))
+    }
+
+    /// The constant price, or `None` when the guard band is set and `reference` is missing or
+    /// outside of it. A guarded price takes the publish time of its reference.
+    pub fn guarded_price(&self, reference: Option<&Price>) -> Option<Price> {
+        let price = self.price();
+        let Some((_, band_bps)) = self.guard() else {
+            return Some(price);
+        };
+        let reference = reference?;
+        within_band(&price, reference, band_bps).then_some(Price {
+            timestamp: reference.timestamp,
+            ..price
+        })
+    }
+}
+
+/// One tagged entry of a source list: tag(1) + len(1) + payload(len).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, ShankType)]
+pub struct SourceEntry {
+    pub tag: u8,
+    pub len: u8,
+    pub payload: [u8; 64],
+}
+
+impl Default for SourceEntry {
+    fn default() -> Self {
+        Self {
+            tag: 0,
+            len: 0,
+            payload: [0u8; 64],
+        }
+    }
+}
+
+impl SourceEntry {
+    pub const MAX_PAYLOAD_LEN: usize = 64;
+
+    pub fn new(tag: u8, payload: &[u8]) -> Result<Self, ProgramError> {
+        if payload.len() > Self::MAX_PAYLOAD_LEN {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        let mut entry = Self {
+            tag,
+            len: payload.len() as u8,
+            ..Self::default()
+        };
+        entry.payload[..payload.len()].copy_from_slice(payload);
+        Ok(entry)
+    }
+
+    pub fn scope(chain: [u16; 3]) -> Self {
+        let mut payload = [0u8; 6];
+        payload[0..2].copy_from_slice(&chain[0].to_le_bytes());
+        payload[2..4].copy_from_slice(&chain[1].to_le_bytes());
+        payload[4..6].copy_from_slice(&chain[2].to_le_bytes());
+        Self::new(SourceType::Scope as u8, &payload).unwrap()
+    }
+
+    pub fn account(source_type: SourceType, account: [u8; 32]) -> Self {
+        Self::new(source_type as u8, &account).unwrap()
+    }
+
+    /// A stake pool priced in the mapping of `quote_mint`, usually wrapped SOL.
+    pub fn stake_pool(pool: [u8; 32], quote_mint: [u8; 32]) -> Self {
+        let mut payload = [0u8; 64];
+        payload[..32].copy_from_slice(&pool);
+        payload[32..].copy_from_slice(&quote_mint);
+        Self::new(SourceType::StakePool as u8, &payload).unwrap()
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload[..(self.len as usize).min(Self::MAX_PAYLOAD_LEN)]
+    }
+
+    /// `None` for tags this version doesn't know.
+    pub fn source_type(&self) -> Option<SourceType> {
+        SourceType::try_from(self.tag).ok()
+    }
+
+    /// The oracle account of an account-based source.
+    pub fn oracle_account(&self) -> Option<[u8; 32]> {
+        let source_type = self.source_type()?;
+        if !source_type.is_account() || self.len as usize != source_type.payload_len() {
+            return None;
+        }
+        let mut account = [0u8; 32];
+        account.copy_from_slice(&self.payload[..32]);
+        Some(account)
+    }
+
+    /// The conversion chain of a Scope source.
+    pub fn scope_chain(&self) -> Option<[u16; 3]> {
+        if self.source_type()? != SourceType::Scope || self.len != 6 {
+            return None;
+        }
+        let p = &self.payload;
+        Some([
+            u16::from_le_bytes([p[0], p[1]]),
+            u16::from_le_bytes([p[2], p[3]]),
+            u16::from_le_bytes([p[4], p[5]]),
+        ])
+    }
+
+    /// A fixed price, guarded by the `reference`-th source when `guard` is set.
+    pub fn fixed(value: i64, expo: i32, guard: Option<(u8, u16)>) -> Self {
+        let (reference, band_bps) = guard.unwrap_or((NO_REFERENCE, 0));
+        let mut payload = [0u8; 15];
+        payload[0..8].copy_from_slice(&value.to_le_bytes());
+        payload[8..12].copy_from_slice(&expo.to_le_bytes());
+        payload[12..14].copy_from_slice(&band_bps.to_le_bytes());
+        payload[14] = reference;
+        Self::new(SourceType::Fixed as u8, &payload).unwrap()
+    }
+
+    pub fn fixed_price(&self) -> Option<FixedPrice> {
+        if self.source_type()? != SourceType::Fixed || self.len != 15 {
+            return None;
+        }
+        let p = &self.payload;
+        Some(FixedPrice {
+            value: i64::from_le_bytes(p[0..8].try_into().unwrap()),
+            expo: i32::from_le_bytes(p[8..12].try_into().unwrap()),
+            band_bps: u16::from_le_bytes([p[12], p[13]]),
+            reference: p[14],
+        })
+    }
+
+    /// The mint whose price a stake pool source is multiplied by.
+    pub fn quote_mint(&self) -> Option<[u8; 32]> {
+        if self.source_type()? != SourceType::StakePool || self.len != 64 {
+            return None;
+        }
+        let mut mint = [0u8; 32];
+        mint.copy_from_slice(&self.payload[32..64]);
+        Some(mint)
+    }
+
+    /// A known tag with the payload length its type expects, and a positive fixed price.
+    pub fn is_valid(&self) -> bool {
+        match self.source_type() {
+            Some(source_type) => {
+                self.len as usize == source_type.payload_len()
+                    && !matches!(self.fixed_price(), Some(fixed) if fixed.value <= 0)
+            }
+            None => false,
+        }
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        2 + self.len as usize
+    }
+}
+
+/// Tagged source list stored after the fixed sources of a mapping:
+/// count(1) + count * (tag(1) + len(1) + payload(len)).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, ShankType)]
+pub struct SourceList {
+    pub count: u8,
+    pub entries: [SourceEntry; 6],
+}
+
+impl SourceList {
+    pub const MAX_ENTRIES: usize = 6;
+
+    pub fn push(&mut self, entry: SourceEntry) -> Result<(), ProgramError> {
+        if self.count as usize >= Self::MAX_ENTRIES {
+            return Err(MappingProgramError::InvalidMappingSize.into());
+        }
+        self.entries[self.count as usize] = entry;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceEntry> {
+        self.entries[..(self.count as usize).min(Self::MAX_ENTRIES)].iter()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.count as usize <= Self::MAX_ENTRIES && self.iter().all(SourceEntry::is_valid)
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.iter().map(SourceEntry::serialized_size).sum::<usize>()
+    }
+
+    /// Reads a list from the start of `bytes`, returning it with the number of bytes read.
+    /// Entries with unknown tags are kept so newer entries still round-trip.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), ProgramError> {
+        let mut list = Self::default();
+        let count = *bytes.first().ok_or(ProgramError::InvalidAccountData)? as usize;
+        if count > Self::MAX_ENTRIES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut offset = 1;
+        for _ in 0..count {
+            if offset + 2 > bytes.len() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let tag = bytes[offset];
+            let len = bytes[offset + 1] as usize;
+            if len > SourceEntry::MAX_PAYLOAD_LEN || offset + 2 + len > bytes.len() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            list.push(SourceEntry::new(tag, &bytes[offset + 2..offset + 2 + len])?)?;
+            offset += 2 + len;
+        }
+
+        Ok((list, offset))
+    }
+
+    /// Writes the list to the start of `bytes` and returns the number of bytes written.
+    pub fn write_bytes(&self, bytes: &mut [u8]) -> usize {
+        bytes[0] = self.count;
+        let mut offset = 1;
+        for entry in self.iter() {
+            let payload = entry.payload();
+            bytes[offset] = entry.tag;
+            bytes[offset + 1] = payload.len() as u8;
+            bytes[offset + 2..offset + 2 + payload.len()].copy_from_slice(payload);
+            offset += 2 + payload.len();
+        }
+        offset
+    }
+}
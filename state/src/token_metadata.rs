@@ -0,0 +1,138 @@
+use core::fmt;
+
+use pinocchio::program_error::ProgramError;
+use shank::ShankType;
+
+use crate::error::MappingProgramError;
+
+// Category tags
+pub const TAG_STABLECOIN: u16 = 1 << 0;
+pub const TAG_LIQUID_STAKING: u16 = 1 << 1;
+pub const TAG_WRAPPED: u16 = 1 << 2;
+pub const TAG_LP_TOKEN: u16 = 1 << 3;
+pub const TAG_GOVERNANCE: u16 = 1 << 4;
+pub const TAG_MEME: u16 = 1 << 5;
+
+/// The top four bits of `tags` hold the risk tier, 0 = unrated.
+pub const RISK_TIER_SHIFT: u16 = 12;
+pub const RISK_TIER_MASK: u16 = 0xF000;
+
+/// Human readable metadata stored after the oracle sources of a mapping.
+///
+/// Serialized as symbol_len(1) + symbol + name_len(1) + name + tags(2, LE).
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, ShankType, Default)]
+pub struct TokenMetadata {
+    pub symbol_len: u8,
+    pub symbol: [u8; 10],
+    pub name_len: u8,
+    pub name: [u8; 32],
+    pub tags: u16,
+}
+
+impl TokenMetadata {
+    pub const MAX_SYMBOL_LEN: usize = 10;
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_LEN: usize = 1 + Self::MAX_SYMBOL_LEN + 1 + Self::MAX_NAME_LEN + 2;
+
+    pub fn new(symbol: &str, name: &str, tags: u16) -> Result<Self, ProgramError> {
+        if symbol.len() > Self::MAX_SYMBOL_LEN || name.len() > Self::MAX_NAME_LEN {
+            return Err(MappingProgramError::MetadataTooLong.into());
+        }
+
+        let mut metadata = Self {
+            symbol_len: symbol.len() as u8,
+            name_len: name.len() as u8,
+            tags,
+            ..Self::default()
+        };
+        metadata.symbol[..symbol.len()].copy_from_slice(symbol.as_bytes());
+        metadata.name[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(metadata)
+    }
+
+    pub fn symbol(&self) -> &str {
+        self.symbol
+            .get(..self.symbol_len as usize)
+            .and_then(|bytes| core::str::from_utf8(bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+            .get(..self.name_len as usize)
+            .and_then(|bytes| core::str::from_utf8(bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn has_tag(&self, tag: u16) -> bool {
+        (self.tags & tag) == tag
+    }
+
+    pub fn risk_tier(&self) -> u8 {
+        ((self.tags & RISK_TIER_MASK) >> RISK_TIER_SHIFT) as u8
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.symbol_len as usize <= Self::MAX_SYMBOL_LEN
+            && self.name_len as usize <= Self::MAX_NAME_LEN
+            && core::str::from_utf8(&self.symbol[..self.symbol_len as usize]).is_ok()
+            && core::str::from_utf8(&self.name[..self.name_len as usize]).is_ok()
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        1 + self.symbol_len as usize + 1 + self.name_len as usize + 2
+    }
+
+    /// Reads the metadata from the start of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProgramError> {
+        let mut metadata = Self::default();
+
+        let symbol_len = *bytes.first().ok_or(ProgramError::InvalidAccountData)? as usize;
+        if symbol_len > Self::MAX_SYMBOL_LEN || bytes.len() < 1 + symbol_len + 1 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        metadata.symbol_len = symbol_len as u8;
+        metadata.symbol[..symbol_len].copy_from_slice(&bytes[1..1 + symbol_len]);
+
+        let name_start = 1 + symbol_len + 1;
+        let name_len = bytes[name_start - 1] as usize;
+        if name_len > Self::MAX_NAME_LEN || bytes.len() < name_start + name_len + 2 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        metadata.name_len = name_len as u8;
+        metadata.name[..name_len].copy_from_slice(&bytes[name_start..name_start + name_len]);
+
+        let tags_start = name_start + name_len;
+        metadata.tags = u16::from_le_bytes([bytes[tags_start], bytes[tags_start + 1]]);
+
+        Ok(metadata)
+    }
+
+    /// Writes the metadata to the start of `bytes` and returns the number of bytes written.
+    pub fn write_bytes(&self, bytes: &mut [u8]) -> usize {
+        let symbol_len = self.symbol_len as usize;
+        let name_len = self.name_len as usize;
+
+        bytes[0] = self.symbol_len;
+        bytes[1..1 + symbol_len].copy_from_slice(&self.symbol[..symbol_len]);
+        let name_start = 1 + symbol_len + 1;
+        bytes[name_start - 1] = self.name_len;
+        bytes[name_start..name_start + name_len].copy_from_slice(&self.name[..name_len]);
+        let tags_start = name_start + name_len;
+        bytes[tags_start..tags_start + 2].copy_from_slice(&self.tags.to_le_bytes());
+
+        tags_start + 2
+    }
+}
+
+impl fmt::Debug for TokenMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenMetadata")
+            .field("symbol", &self.symbol())
+            .field("name", &self.name())
+            .field("tags", &format_args!("{:#06x}", self.tags))
+            .field("risk_tier", &self.risk_tier())
+            .finish()
+    }
+}
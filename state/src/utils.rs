@@ -82,3 +82,35 @@ pub unsafe fn try_from_account_info_mut<'a, T: DataLen>(
 
     Ok(&mut *(bytes.as_mut_ptr() as *mut T))
 }
+
+/// SHA-256 of `data`. Uses the `sol_sha256` syscall on-chain and `sha2` everywhere else so
+/// hashes computed by the program and by off-chain readers are interchangeable.
+pub fn hash(data: &[u8]) -> [u8; 32] {
+    hashv(&[data])
+}
+
+/// SHA-256 over the concatenation of `vals`.
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash_result = [0u8; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash_result.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        for val in vals {
+            hasher.update(val);
+        }
+        hash_result.copy_from_slice(&hasher.finalize());
+    }
+
+    hash_result
+}
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use oracle_mapping::ID as scope_mapping_id;
-use oracle_mapping_state::{DataLen, MintMapping, ScopeMappingRegistry, SCOPE_MAPPING_ADDRESS};
+use oracle_mapping_state::{
+    mappings_root, pages_root, DataLen, MintMapping, PriceSelection, ScopeMappingRegistry,
+    SCOPE_MAPPING_ADDRESS,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use solana_client::rpc_client::RpcClient;
@@ -31,39 +34,118 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let rpc = RpcClient::new("https://api.devnet.solana.com".to_string());
 
     let mapping_pda = Pubkey::from_str(SCOPE_MAPPING_ADDRESS).unwrap();
-    let mapping_data = rpc.get_account(&mapping_pda).unwrap().data;
 
     let rpc_mainnet = RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
-    let scope_account = rpc_mainnet
-        .get_account(&Pubkey::from(oracle_mapping::ID))
-        .unwrap();
-    let scope_data = scope_account.data;
 
     let reg = get_registry(&rpc, &mapping_pda);
 
-    println!("total mappings: {}", reg.total_mappings);
+    // Entries on continuation pages are checked and priced like those of the registry
+    let page_data = get_registry_pages(&rpc, &mapping_pda, &reg);
+    let pages: Vec<&[u8]> = page_data.iter().map(Vec::as_slice).collect();
+
+    // Scope chains index into the OraclePrices account of the feed their mapping references
+    let scope_feeds: Vec<Option<Vec<u8>>> = (0..reg.scope_feeds.len() as u8)
+        .map(|index| {
+            let feed = reg.scope_feed(index)?;
+            Some(rpc_mainnet.get_account(&Pubkey::from(feed)).unwrap().data)
+        })
+        .collect();
+    let scope_price_of = |mapping: &MintMapping| -> Option<f64> {
+        let scope_details = mapping.scope_details?;
+        let Some(data) = scope_feeds
+            .get(mapping.scope_feed() as usize)
+            .and_then(Option::as_deref)
+        else {
+            println!(
+                "{}: the registry has no Scope feed at index {}",
+                Pubkey::from(mapping.mint),
+                mapping.scope_feed()
+            );
+            return None;
+        };
+        let (price, exp) = get_scope_price_data(data, scope_details)
+            .inspect_err(|err| println!("{}: {}", Pubkey::from(mapping.mint), err))
+            .ok()?;
+        Some(price as f64 / 10_u64.pow(exp as u32) as f64)
+    };
+    let pyth_price_of = |mapping: &MintMapping| -> Option<f64> {
+        let pyth_account = mapping.pyth_account?;
+        let pyth_data = rpc_mainnet
+            .get_account(&Pubkey::from(pyth_account))
+            .unwrap()
+            .data;
+        let pyth = get_pyth_price_data(&pyth_data, 0, 0, &[0; 32])
+            .inspect_err(|err| println!("{}: {}", Pubkey::from(mapping.mint), err))
+            .ok()?;
+        let price = pyth.selected(mapping.price_selection());
+        Some(price as f64 * 10_f64.powi(pyth.exponent))
+    };
 
-    for i in 0..reg.total_mappings as usize {
-        let mint_mapping = get_mapping_by_index(&mapping_data, i);
+    // Each header commits to the entries that were read along with it, and the registry to the
+    // roots of its pages
+    let mut total_mappings = 0;
+    for (page, data) in pages.iter().enumerate() {
+        let header = ScopeMappingRegistry::from_account_data(data).unwrap();
+        total_mappings += header.total_mappings;
+        if mappings_root(data).unwrap() != header.mappings_root {
+            println!(
+                "mappings root of account {} does not match its entries",
+                page
+            );
+        }
+    }
+    if pages_root(&pages[1..]).unwrap() != reg.pages_root {
+        println!("pages root does not match the continuation pages");
+    }
+    println!("total mappings: {}", total_mappings);
+    let slot = rpc.get_slot().unwrap();
 
-        let token_mint = Pubkey::from(mint_mapping.mint);
+    let entries = pages.iter().flat_map(|data| {
+        ScopeMappingRegistry::mapping_offsets(data)
+            .unwrap()
+            .map(move |(start, end)| MintMapping::from_bytes(&data[start..end]).unwrap())
+    });
+    for (i, mint_mapping) in entries.enumerate() {
+        // Providers the registry disabled are left out like in on-chain lookups
+        let Ok(mint_mapping) = mint_mapping.without_providers(reg.disabled_providers) else {
+            let token_mint = Pubkey::from(mint_mapping.mint);
+            println!("{token_mint}: entry larger than 255 bytes, skipped");
+            continue;
+        };
 
-        let mut scope_price: f64 = 0.0;
-        if let Some(scope_details) = mint_mapping.scope_details {
-            let (price, exp) = get_scope_price_data(&scope_data, scope_details).unwrap();
-            scope_price = price as f64 / 10_u64.pow(exp as u32) as f64;
+        // Mappings outside their validity window are not priced
+        if !mint_mapping.is_active(slot) {
+            continue;
         }
 
-        let mut pyth_price: f64 = 0.0;
-        if let Some(pyth_account) = mint_mapping.pyth_account {
-            let pyth_data = rpc_mainnet
-                .get_account(&Pubkey::from(pyth_account))
-                .unwrap()
-                .data;
-            let (price, _, exp) = get_pyth_price_data(&pyth_data, 0, 0, &[0; 32]).unwrap();
-            pyth_price = price as f64 * 10_f64.powi(exp as i32);
+        let token_mint = Pubkey::from(mint_mapping.mint);
+
+        // Prices quoted in SOL are converted with the price of the registry's SOL/USD mint
+        let mut quote_to_usd: f64 = 1.0;
+        if let Some(conversion_mint) = reg.conversion_mint(mint_mapping.quote_currency()) {
+            let conversion = MintMapping::resolve_pages(&pages, &conversion_mint, slot)
+                .ok()
+                .and_then(|resolved| {
+                    resolved
+                        .mapping
+                        .without_providers(reg.disabled_providers)
+                        .ok()
+                });
+            let Some(price) = conversion.as_ref().and_then(|conversion| {
+                scope_price_of(conversion).or_else(|| pyth_price_of(conversion))
+            }) else {
+                println!(
+                    "{}: no USD price for its quote currency, skipped",
+                    token_mint
+                );
+                continue;
+            };
+            quote_to_usd = price;
         }
 
+        let scope_price = scope_price_of(&mint_mapping).map_or(0.0, |price| price * quote_to_usd);
+        let pyth_price = pyth_price_of(&mint_mapping).map_or(0.0, |price| price * quote_to_usd);
+
         // get token from tokens using the mint
         let token = tokens
             .iter()
@@ -124,30 +206,26 @@ fn get_registry(rpc: &RpcClient, state_pda: &Pubkey) -> ScopeMappingRegistry {
     ScopeMappingRegistry::from_slice(&data[..ScopeMappingRegistry::LEN]).unwrap()
 }
 
-fn get_mapping_by_index(data: &[u8], index: usize) -> MintMapping {
-    // Calculate the starting offset for this mapping
-    let mut current_offset = ScopeMappingRegistry::LEN;
-
-    // Skip previous mappings to find the start of this mapping
-    for i in 0..index {
-        if current_offset + 35 > data.len() {
-            panic!("Mapping index {} not found", index);
-        }
-        // Read the offset byte at position 32 of each mapping to get its actual size
-        let mapping_offset = data[current_offset + 32] as usize;
-        current_offset += mapping_offset;
-    }
-
-    // Now read the current mapping
-    if current_offset + 35 > data.len() {
-        panic!("Mapping index {} not found", index);
+/// Account data of the registry followed by that of its continuation pages, in page order.
+fn get_registry_pages(
+    rpc: &RpcClient,
+    state_pda: &Pubkey,
+    reg: &ScopeMappingRegistry,
+) -> Vec<Vec<u8>> {
+    let program_id = Pubkey::from(scope_mapping_id);
+    let mut pages = vec![rpc.get_account(state_pda).unwrap().data];
+    for index in 1..=reg.page_count {
+        let (page_pda, _) = Pubkey::find_program_address(
+            &[
+                ScopeMappingRegistry::PAGE_SEED.as_bytes(),
+                state_pda.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &program_id,
+        );
+        pages.push(rpc.get_account(&page_pda).unwrap().data);
     }
-
-    // Read the offset byte to determine the actual size of this mapping
-    let mapping_size = data[current_offset + 32] as usize;
-    let mapping_data = &data[current_offset..current_offset + mapping_size];
-
-    MintMapping::from_bytes(mapping_data).unwrap()
+    pages
 }
 
 fn get_scope_price_data(data: &[u8], price_chain: [u16; 3]) -> Result<(u64, u8)> {
@@ -282,12 +360,33 @@ fn get_scope_price_data(data: &[u8], price_chain: [u16; 3]) -> Result<(u64, u8)>
     Ok((final_value, final_exp))
 }
 
+/// Spot and EMA fields of a Pyth `PriceUpdateV2` account.
+pub struct PythPriceData {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl PythPriceData {
+    /// The price a mapping selected: spot, EMA, or the lower of both.
+    pub fn selected(&self, selection: PriceSelection) -> i64 {
+        match selection {
+            PriceSelection::Spot => self.price,
+            PriceSelection::Ema => self.ema_price,
+            PriceSelection::Conservative => self.price.min(self.ema_price),
+        }
+    }
+}
+
 pub fn get_pyth_price_data(
     price_update_data: &[u8],
     current_timestamp: i64,
     maximum_age: u64,
     feed_id: &[u8],
-) -> Result<(u64, u64, i32), anyhow::Error> {
+) -> Result<PythPriceData, anyhow::Error> {
     let verification_level = unsafe { *price_update_data.get_unchecked(40) };
     if verification_level != 1 {
         return Err(anyhow::anyhow!("Verification level failed"));
@@ -314,9 +413,32 @@ pub fn get_pyth_price_data(
     let publish_time =
         i64::from_le_bytes(unsafe { price_update_data.get_unchecked(93..101).try_into().unwrap() });
 
+    // prev_publish_time (8 bytes) [101..109], then ema_price (8 bytes) [109..117]
+    let ema_price = i64::from_le_bytes(unsafe {
+        price_update_data
+            .get_unchecked(109..117)
+            .try_into()
+            .unwrap()
+    });
+
+    // ema_conf (8 bytes) [117..125]
+    let ema_conf = u64::from_le_bytes(unsafe {
+        price_update_data
+            .get_unchecked(117..125)
+            .try_into()
+            .unwrap()
+    });
+
     if publish_time.saturating_add(maximum_age.try_into().unwrap()) < current_timestamp {
         return Err(anyhow::anyhow!("Price too old"));
     }
 
-    Ok((price as u64, confidence, exponent))
+    Ok(PythPriceData {
+        price,
+        conf: confidence,
+        exponent,
+        publish_time,
+        ema_price,
+        ema_conf,
+    })
 }